@@ -1,6 +1,8 @@
 use anyhow::{bail, ensure};
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser};
 use std::cmp;
+use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
@@ -18,6 +20,15 @@ const NO_NAMES_ARG: &str = "no_names";
 const RAW_ARG: &str = "raw";
 const TAG_ARG: &str = "tag";
 const CHECK_ARG: &str = "check";
+const RECURSIVE_ARG: &str = "recursive";
+
+// See tree_digest.md.
+const TREE_DIGEST_FILE_CONTEXT: &str = "BLAKE3 2026-10-15 b3sum tree-digest file v1";
+const TREE_DIGEST_DIRECTORY_CONTEXT: &str = "BLAKE3 2026-10-15 b3sum tree-digest directory v1";
+const TREE_DIGEST_FILE: u8 = 1;
+const TREE_DIGEST_EXECUTABLE: u8 = 2;
+const TREE_DIGEST_SYMLINK: u8 = 3;
+const TREE_DIGEST_DIRECTORY: u8 = 4;
 
 #[derive(Parser)]
 #[command(version, max_term_width(100))]
@@ -59,7 +70,7 @@ struct Inner {
 
     /// Disable memory mapping
     ///
-    /// Currently this also disables multithreading.
+    /// Currently this also disables multithreading within each file.
     #[arg(long)]
     no_mmap: bool,
 
@@ -77,13 +88,31 @@ struct Inner {
     #[arg(long)]
     tag: bool,
 
+    /// Output one JSON object per line, for hashing and for --check
+    ///
+    /// Each object has the path (as a lossy string and as hex-encoded raw
+    /// bytes), the mode, the length and seek, a status of OK, FAILED, or
+    /// error, and the hash or the error message. Errors for individual files go
+    /// to stdout with the rest of the objects. Cannot be used with --raw,
+    /// --tag, or --no-names.
+    #[arg(
+        long,
+        conflicts_with(RAW_ARG),
+        conflicts_with(TAG_ARG),
+        conflicts_with(NO_NAMES_ARG)
+    )]
+    json: bool,
+
     /// Read BLAKE3 sums from the [FILE]s and check them
+    ///
+    /// Each hash is checked at its own length, unless --length is given, in
+    /// which case every hash must have that length. With --keyed or
+    /// --derive-key, the hashes are checked in that mode. (With --keyed, the
+    /// key comes from stdin, so the checkfiles can't, and hashes shorter than
+    /// 32 bytes are rejected unless --length is given.)
     #[arg(
         short,
         long,
-        conflicts_with(DERIVE_KEY_ARG),
-        conflicts_with(KEYED_ARG),
-        conflicts_with(LENGTH_ARG),
         conflicts_with(RAW_ARG),
         conflicts_with(TAG_ARG),
         conflicts_with(NO_NAMES_ARG)
@@ -95,10 +124,56 @@ struct Inner {
     /// Must be used with --check.
     #[arg(long, requires(CHECK_ARG))]
     quiet: bool,
+
+    /// Hash the regular files in directories, recursively
+    ///
+    /// Files are listed in sorted order by name, with the contents of each
+    /// subdirectory in place of the subdirectory. Symlinks inside the
+    /// directories are skipped, unless --follow-symlinks is given.
+    #[arg(long, conflicts_with(CHECK_ARG), conflicts_with(RAW_ARG))]
+    recursive: bool,
+
+    /// Follow symlinks inside directories
+    ///
+    /// Must be used with --recursive.
+    #[arg(long, requires(RECURSIVE_ARG))]
+    follow_symlinks: bool,
+
+    /// Skip files and directories that match GLOB
+    ///
+    /// Must be used with --recursive. A GLOB without a slash is matched
+    /// against each name, and a GLOB with a slash is matched against the path
+    /// below the directory argument. * and ? match anything but a slash, **
+    /// matches anything, and [...] matches a class of characters. May be given
+    /// more than once.
+    #[arg(long, value_name("GLOB"), requires(RECURSIVE_ARG))]
+    exclude: Vec<String>,
+
+    /// Don't descend into directories on other filesystems
+    ///
+    /// Must be used with --recursive. Only supported on Unix.
+    #[arg(long, requires(RECURSIVE_ARG))]
+    one_file_system: bool,
+
+    /// Output one digest for each directory, covering everything in it
+    ///
+    /// The digest commits to the names, types, executable bits, symlink
+    /// targets, and file contents of the whole directory tree. Symlinks aren't
+    /// followed. See tree_digest.md for the exact encoding.
+    #[arg(
+        long,
+        conflicts_with(CHECK_ARG),
+        conflicts_with(DERIVE_KEY_ARG),
+        conflicts_with(KEYED_ARG),
+        conflicts_with(RECURSIVE_ARG)
+    )]
+    tree_digest: bool,
 }
 
 struct Args {
     inner: Inner,
+    // Whether --length was given explicitly, rather than defaulted.
+    length_given: bool,
     file_args: Vec<PathBuf>,
     base_hasher: blake3::Hasher,
 }
@@ -107,7 +182,9 @@ impl Args {
     fn parse() -> anyhow::Result<Self> {
         // wild::args_os() is equivalent to std::env::args_os() on Unix,
         // but on Windows it adds support for globbing.
-        let inner = Inner::parse_from(wild::args_os());
+        let matches = Inner::command().get_matches_from(wild::args_os());
+        let length_given = matches.value_source(LENGTH_ARG) == Some(ValueSource::CommandLine);
+        let inner = Inner::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
         let file_args = if !inner.file.is_empty() {
             inner.file.clone()
         } else {
@@ -116,6 +193,9 @@ impl Args {
         if inner.raw && file_args.len() > 1 {
             bail!("Only one filename can be provided when using --raw");
         }
+        if inner.one_file_system && !cfg!(unix) {
+            bail!("--one-file-system is only supported on Unix");
+        }
         let base_hasher = if inner.keyed {
             // In keyed mode, since stdin is used for the key, we can't handle
             // `-` arguments. Input::open handles that case below.
@@ -127,6 +207,7 @@ impl Args {
         };
         Ok(Self {
             inner,
+            length_given,
             file_args,
             base_hasher,
         })
@@ -148,6 +229,10 @@ impl Args {
         self.inner.tag
     }
 
+    fn json(&self) -> bool {
+        self.inner.json
+    }
+
     fn no_mmap(&self) -> bool {
         self.inner.no_mmap
     }
@@ -168,9 +253,33 @@ impl Args {
         self.inner.keyed
     }
 
+    fn derive_key(&self) -> Option<&str> {
+        self.inner.derive_key.as_deref()
+    }
+
     fn quiet(&self) -> bool {
         self.inner.quiet
     }
+
+    fn recursive(&self) -> bool {
+        self.inner.recursive
+    }
+
+    fn follow_symlinks(&self) -> bool {
+        self.inner.follow_symlinks
+    }
+
+    fn excludes(&self) -> &[String] {
+        &self.inner.exclude
+    }
+
+    fn one_file_system(&self) -> bool {
+        self.inner.one_file_system
+    }
+
+    fn tree_digest(&self) -> bool {
+        self.inner.tree_digest
+    }
 }
 
 fn hash_path(args: &Args, path: &Path) -> anyhow::Result<blake3::OutputReader> {
@@ -216,6 +325,60 @@ fn write_raw_output(output: blake3::OutputReader, args: &Args) -> anyhow::Result
     Ok(())
 }
 
+// Encodes a string as a JSON string literal, with quotes.
+fn json_string(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len() + 2);
+    encoded.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => encoded.push_str("\\\""),
+            '\\' => encoded.push_str("\\\\"),
+            '\n' => encoded.push_str("\\n"),
+            '\r' => encoded.push_str("\\r"),
+            '\t' => encoded.push_str("\\t"),
+            c if c < ' ' => encoded.push_str(&format!("\\u{:04x}", c as u32)),
+            c => encoded.push(c),
+        }
+    }
+    encoded.push('"');
+    encoded
+}
+
+fn json_mode(args: &Args) -> String {
+    if args.tree_digest() {
+        "\"tree_digest\"".to_string()
+    } else if args.keyed() {
+        "\"keyed\"".to_string()
+    } else if let Some(context) = args.derive_key() {
+        format!("\"derive_key\",\"context\":{}", json_string(context))
+    } else {
+        "\"hash\"".to_string()
+    }
+}
+
+// The fields that start every --json object about a file, followed by a
+// comma. The path is given both as a string, which is lossy if the path isn't
+// valid Unicode, and as the hex encoding of its raw bytes. (On Windows, that's
+// the WTF-8 encoding.)
+fn json_file_fields(path: &Path, len: u64, args: &Args) -> String {
+    format!(
+        "\"path\":{},\"path_hex\":\"{}\",\"mode\":{},\"length\":{},\"seek\":{},",
+        json_string(&path.to_string_lossy()),
+        hex::encode(path.as_os_str().as_encoded_bytes()),
+        json_mode(args),
+        len,
+        args.seek(),
+    )
+}
+
+fn print_json_error(path: &Path, args: &Args, e: &dyn std::fmt::Display) {
+    println!(
+        "{{{}\"status\":\"error\",\"message\":{}}}",
+        json_file_fields(path, args.len(), args),
+        json_string(&e.to_string()),
+    );
+}
+
 fn read_key_from_stdin() -> anyhow::Result<[u8; blake3::KEY_LEN]> {
     let mut bytes = Vec::with_capacity(blake3::KEY_LEN + 1);
     let n = std::io::stdin()
@@ -235,6 +398,323 @@ fn read_key_from_stdin() -> anyhow::Result<[u8; blake3::KEY_LEN]> {
     }
 }
 
+// Matches a --exclude pattern. `*` and `?` don't match slashes, `**` matches
+// anything, `[...]` is a character class (negated with `!` or `^`), and a
+// backslash matches the following character literally. An unclosed `[` is a
+// literal.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        ['*', rest @ ..] => {
+            let end = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+            (0..=end).any(|i| glob_match(rest, &text[i..]))
+        }
+        ['?', rest @ ..] => match text {
+            [c, text_rest @ ..] if *c != '/' => glob_match(rest, text_rest),
+            _ => false,
+        },
+        ['[', class @ ..] => match (parse_glob_class(class), text) {
+            (Some((matches, rest)), [c, text_rest @ ..]) => {
+                *c != '/' && matches(*c) && glob_match(rest, text_rest)
+            }
+            (Some(_), []) => false,
+            (None, _) => literal_match('[', &pattern[1..], text),
+        },
+        ['\\', c, rest @ ..] => literal_match(*c, rest, text),
+        [c, rest @ ..] => literal_match(*c, rest, text),
+    }
+}
+
+fn literal_match(c: char, pattern_rest: &[char], text: &[char]) -> bool {
+    match text {
+        [first, text_rest @ ..] => *first == c && glob_match(pattern_rest, text_rest),
+        [] => false,
+    }
+}
+
+// Parses the part of a `[...]` class after the opening bracket. Returns a
+// function that tests a character against the class, and the rest of the
+// pattern after the closing bracket, or None if there's no closing bracket.
+fn parse_glob_class(class: &[char]) -> Option<(impl Fn(char) -> bool + '_, &[char])> {
+    let (negated, body) = match class {
+        ['!' | '^', body @ ..] => (true, body),
+        _ => (false, class),
+    };
+    // A `]` right after the opening bracket is a literal.
+    let close = 1 + body.get(1..)?.iter().position(|&c| c == ']')?;
+    let items = &body[..close];
+    let matches = move |c: char| {
+        let mut found = false;
+        let mut i = 0;
+        while i < items.len() {
+            if i + 2 < items.len() && items[i + 1] == '-' {
+                found |= items[i] <= c && c <= items[i + 2];
+                i += 3;
+            } else {
+                found |= items[i] == c;
+                i += 1;
+            }
+        }
+        found != negated
+    };
+    Some((matches, &body[close + 1..]))
+}
+
+// A file to hash, or an error encountered while walking a directory.
+enum WalkItem {
+    File(PathBuf),
+    Error(PathBuf, anyhow::Error),
+}
+
+#[cfg(unix)]
+fn device_id(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device_id(_metadata: &fs::Metadata) -> Option<u64> {
+    // Args::parse rejects --one-file-system on other platforms.
+    None
+}
+
+struct Walker<'a> {
+    args: &'a Args,
+    root: &'a Path,
+    excludes: Vec<Vec<char>>,
+    root_device: Option<u64>,
+    // The canonical paths of the directories we're in, for detecting symlink
+    // loops. This is only used with --follow-symlinks.
+    ancestors: Vec<PathBuf>,
+    items: Vec<WalkItem>,
+}
+
+impl Walker<'_> {
+    fn is_excluded(&self, path: &Path, name: &std::ffi::OsStr) -> bool {
+        let name: Vec<char> = name.to_string_lossy().chars().collect();
+        let relative = path.strip_prefix(self.root).unwrap_or(path);
+        let relative: Vec<char> = filepath_to_string(relative)
+            .filepath_string
+            .chars()
+            .collect();
+        self.excludes.iter().any(|pattern| {
+            if pattern.contains(&'/') {
+                glob_match(pattern, &relative)
+            } else {
+                glob_match(pattern, &name)
+            }
+        })
+    }
+
+    fn walk_dir(&mut self, dir: &Path) -> anyhow::Result<()> {
+        if !self.args.follow_symlinks() {
+            return self.walk_entries(dir);
+        }
+        let canonical = fs::canonicalize(dir)?;
+        ensure!(
+            !self.ancestors.contains(&canonical),
+            "Filesystem loop detected"
+        );
+        self.ancestors.push(canonical);
+        let result = self.walk_entries(dir);
+        self.ancestors.pop();
+        result
+    }
+
+    fn walk_entries(&mut self, dir: &Path) -> anyhow::Result<()> {
+        let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            if self.is_excluded(&path, &entry.file_name()) {
+                continue;
+            }
+            let metadata = if self.args.follow_symlinks() {
+                fs::metadata(&path)
+            } else {
+                fs::symlink_metadata(&path)
+            };
+            let metadata = match metadata {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    self.items.push(WalkItem::Error(path, e.into()));
+                    continue;
+                }
+            };
+            if self.args.one_file_system() && device_id(&metadata) != self.root_device {
+                continue;
+            }
+            if metadata.is_dir() {
+                if let Err(e) = self.walk_dir(&path) {
+                    self.items.push(WalkItem::Error(path, e));
+                }
+            } else if metadata.is_file() {
+                self.items.push(WalkItem::File(path));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Lists the regular files under the directory `root`, in sorted order, for
+// --recursive. Errors don't stop the walk. They're returned in place of the
+// entries that couldn't be read.
+fn walk(root: &Path, args: &Args) -> Vec<WalkItem> {
+    let mut walker = Walker {
+        args,
+        root,
+        excludes: args
+            .excludes()
+            .iter()
+            .map(|p| p.chars().collect())
+            .collect(),
+        root_device: None,
+        ancestors: Vec::new(),
+        items: Vec::new(),
+    };
+    let result = fs::metadata(root)
+        .map_err(anyhow::Error::from)
+        .and_then(|metadata| {
+            walker.root_device = device_id(&metadata);
+            walker.walk_dir(root)
+        });
+    if let Err(e) = result {
+        walker.items.push(WalkItem::Error(root.to_owned(), e));
+    }
+    walker.items
+}
+
+#[cfg(unix)]
+fn name_bytes(name: &std::ffi::OsStr) -> anyhow::Result<Vec<u8>> {
+    use std::os::unix::ffi::OsStrExt;
+    Ok(name.as_bytes().to_vec())
+}
+
+#[cfg(not(unix))]
+fn name_bytes(name: &std::ffi::OsStr) -> anyhow::Result<Vec<u8>> {
+    match name.to_str() {
+        Some(name) => Ok(name.as_bytes().to_vec()),
+        None => bail!("Invalid Unicode in name"),
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o100 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+// A directory whose entries are partway through being hashed.
+struct TreeDigestDir {
+    hasher: blake3::Hasher,
+    // Sorted by name, and not yet hashed.
+    entries: std::vec::IntoIter<(Vec<u8>, PathBuf)>,
+}
+
+impl TreeDigestDir {
+    fn open(dir: &Path) -> Result<Self, (PathBuf, anyhow::Error)> {
+        let with_path = |e: io::Error| (dir.to_owned(), e.into());
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(dir).map_err(with_path)? {
+            let entry = entry.map_err(with_path)?;
+            let name = name_bytes(&entry.file_name()).map_err(|e| (entry.path(), e))?;
+            entries.push((name, entry.path()));
+        }
+        entries.sort();
+        Ok(Self {
+            hasher: blake3::Hasher::new_derive_key(TREE_DIGEST_DIRECTORY_CONTEXT),
+            entries: entries.into_iter(),
+        })
+    }
+}
+
+// Returns a derive_key hasher that has absorbed the encoding of the directory,
+// as described in tree_digest.md. The caller finalizes it. Errors come with
+// the path of the entry that caused them. This walks the tree with an explicit
+// stack rather than recursion, so that deep trees can't overflow the thread's
+// stack.
+fn tree_digest_hasher(
+    root: &Path,
+    args: &Args,
+) -> Result<blake3::Hasher, (PathBuf, anyhow::Error)> {
+    let mut stack = vec![TreeDigestDir::open(root)?];
+    loop {
+        let dir = stack.last_mut().unwrap();
+        if let Some((name, path)) = dir.entries.next() {
+            dir.hasher.update(&(name.len() as u64).to_le_bytes());
+            dir.hasher.update(&name);
+            let metadata = fs::symlink_metadata(&path).map_err(|e| (path.clone(), e.into()))?;
+            if metadata.is_dir() {
+                // The type byte and the digest follow when the subdirectory is
+                // finished.
+                stack.push(TreeDigestDir::open(&path)?);
+            } else {
+                tree_digest_leaf(&mut dir.hasher, &path, &metadata, args).map_err(|e| (path, e))?;
+            }
+        } else {
+            let finished = stack.pop().unwrap();
+            let Some(parent) = stack.last_mut() else {
+                return Ok(finished.hasher);
+            };
+            parent.hasher.update(&[TREE_DIGEST_DIRECTORY]);
+            parent.hasher.update(finished.hasher.finalize().as_bytes());
+        }
+    }
+}
+
+// Encodes a file or a symlink into the directory hasher.
+fn tree_digest_leaf(
+    hasher: &mut blake3::Hasher,
+    path: &Path,
+    metadata: &fs::Metadata,
+    args: &Args,
+) -> anyhow::Result<()> {
+    if metadata.is_file() {
+        let mut file_hasher = blake3::Hasher::new_derive_key(TREE_DIGEST_FILE_CONTEXT);
+        if args.no_mmap() {
+            file_hasher.update_reader(File::open(path)?)?;
+        } else {
+            file_hasher.update_mmap_rayon(path)?;
+        }
+        if is_executable(metadata) {
+            hasher.update(&[TREE_DIGEST_EXECUTABLE]);
+        } else {
+            hasher.update(&[TREE_DIGEST_FILE]);
+        }
+        hasher.update(file_hasher.finalize().as_bytes());
+    } else if metadata.file_type().is_symlink() {
+        let target = name_bytes(fs::read_link(path)?.as_os_str())?;
+        hasher.update(&[TREE_DIGEST_SYMLINK]);
+        hasher.update(&(target.len() as u64).to_le_bytes());
+        hasher.update(&target);
+    } else {
+        bail!("Unsupported file type");
+    }
+    Ok(())
+}
+
+fn tree_digest(args: &Args, path: &Path) -> anyhow::Result<blake3::OutputReader> {
+    ensure!(path != Path::new("-"), "Cannot use `-` with --tree-digest");
+    let hasher = tree_digest_hasher(path, args).map_err(|(error_path, e)| {
+        // Errors below the top directory get the path of the entry too.
+        if error_path == path {
+            e
+        } else {
+            let error_path_string = filepath_to_string(&error_path).filepath_string;
+            anyhow::anyhow!("{}: {}", error_path_string, e)
+        }
+    })?;
+    let mut output_reader = hasher.finalize_xof();
+    output_reader.set_position(args.seek());
+    Ok(output_reader)
+}
+
 struct FilepathString {
     filepath_string: String,
     is_escaped: bool,
@@ -331,7 +811,7 @@ struct ParsedCheckLine {
     file_string: String,
     is_escaped: bool,
     file_path: PathBuf,
-    expected_hash: blake3::Hash,
+    expected_hash: Vec<u8>,
 }
 
 fn split_untagged_check_line(line_after_slash: &str) -> Option<(&str, &str)> {
@@ -382,16 +862,19 @@ fn parse_check_line(mut line: &str) -> anyhow::Result<ParsedCheckLine> {
         bail!("Invalid check line format");
     }
 
-    // Decode the hex hash.
-    ensure!(hash_hex.len() == 2 * blake3::OUT_LEN, "Invalid hash length");
+    // Decode the hex hash. It can be any whole number of bytes, as produced by
+    // --length.
+    ensure!(
+        !hash_hex.is_empty() && hash_hex.len() & 1 == 0,
+        "Invalid hash length"
+    );
     let mut hex_chars = hash_hex.chars();
-    let mut hash_bytes = [0; blake3::OUT_LEN];
-    for byte in &mut hash_bytes {
+    let mut expected_hash = vec![0; hash_hex.len() / 2];
+    for byte in &mut expected_hash {
         let high_char = hex_chars.next().unwrap();
         let low_char = hex_chars.next().unwrap();
         *byte = 16 * hex_half_byte(high_char)? + hex_half_byte(low_char)?;
     }
-    let expected_hash: blake3::Hash = hash_bytes.into();
 
     // Unescape and validate the filepath.
     let file_path_string = if is_escaped {
@@ -410,8 +893,24 @@ fn parse_check_line(mut line: &str) -> anyhow::Result<ParsedCheckLine> {
     })
 }
 
-fn hash_one_input(path: &Path, args: &Args) -> anyhow::Result<()> {
-    let output = hash_path(args, path)?;
+fn hash_input(args: &Args, path: &Path) -> anyhow::Result<blake3::OutputReader> {
+    if args.tree_digest() {
+        tree_digest(args, path)
+    } else {
+        hash_path(args, path)
+    }
+}
+
+fn write_output(output: blake3::OutputReader, path: &Path, args: &Args) -> anyhow::Result<()> {
+    if args.json() {
+        print!(
+            "{{{}\"status\":\"OK\",\"hash\":\"",
+            json_file_fields(path, args.len(), args)
+        );
+        write_hex_output(output, args)?;
+        println!("\"}}");
+        return Ok(());
+    }
     if args.raw() {
         write_raw_output(output, args)?;
         return Ok(());
@@ -439,11 +938,113 @@ fn hash_one_input(path: &Path, args: &Args) -> anyhow::Result<()> {
     Ok(())
 }
 
+// The number of inputs that can be hashed ahead of the next one to be
+// printed. This limits how many results wait in memory behind a slow input.
+const MAX_INPUTS_IN_FLIGHT: usize = 1024;
+
+// The path to hash on the thread pool, if any. Stdin is hashed on the
+// printing thread when its turn comes, so that if `-` is given more than once,
+// the first one in order gets the input.
+fn pool_path(item: &WalkItem) -> Option<&Path> {
+    match item {
+        WalkItem::File(path) if path != Path::new("-") => Some(path),
+        _ => None,
+    }
+}
+
+// Hashes the inputs concurrently on the thread pool, and prints the results
+// in the same order as the inputs. Returns the number of inputs that failed.
+//
+// Errors encountered in hashing are tolerated and printed to stderr. This
+// allows e.g. `b3sum *` to print errors for non-files and keep going. However,
+// if we encounter any errors we'll still return non-zero at the end.
+fn hash_inputs(inputs: &[WalkItem], args: &Args, thread_pool: &rayon_core::ThreadPool) -> u64 {
+    let mut files_failed = 0u64;
+    // Hashes the item here if there's no result from the pool, and prints it.
+    let mut print_item = |item: &WalkItem, result: Option<anyhow::Result<blake3::OutputReader>>| {
+        let (path, result) = match item {
+            WalkItem::File(path) => {
+                let output = result.unwrap_or_else(|| hash_input(args, path));
+                (
+                    path,
+                    output.and_then(|output| write_output(output, path, args)),
+                )
+            }
+            WalkItem::Error(path, e) => (path, Err(anyhow::anyhow!("{}", e))),
+        };
+        if let Err(e) = result {
+            files_failed = files_failed.saturating_add(1);
+            if args.json() {
+                print_json_error(path, args, &e);
+            } else {
+                eprintln!("{}: {}: {}", NAME, path.to_string_lossy(), e);
+            }
+        }
+    };
+    if thread_pool.current_num_threads() == 1 {
+        // With only one thread, handing inputs back and forth is pure overhead.
+        thread_pool.install(|| inputs.iter().for_each(|item| print_item(item, None)));
+        return files_failed;
+    }
+    let (sender, receiver) = std::sync::mpsc::channel();
+    // This closure runs on the current thread, which isn't part of the pool,
+    // so blocking on the receiver doesn't take a worker away from hashing.
+    thread_pool.in_place_scope(|scope| {
+        // The results for inputs[next_print..next_spawn], as they arrive.
+        let mut results = std::collections::VecDeque::new();
+        let mut next_spawn = 0;
+        for (next_print, item) in inputs.iter().enumerate() {
+            while next_spawn < inputs.len() && next_spawn < next_print + MAX_INPUTS_IN_FLIGHT {
+                if let Some(path) = pool_path(&inputs[next_spawn]) {
+                    let sender = sender.clone();
+                    let index = next_spawn;
+                    scope.spawn(move |_| {
+                        // The receiver outlives the scope, so this can't fail.
+                        let _ = sender.send((index, hash_input(args, path)));
+                    });
+                }
+                results.push_back(None);
+                next_spawn += 1;
+            }
+            if pool_path(item).is_some() {
+                while results[0].is_none() {
+                    let (index, result) = receiver.recv().unwrap();
+                    results[index - next_print] = Some(result);
+                }
+            }
+            print_item(item, results.pop_front().unwrap());
+        }
+    });
+    files_failed
+}
+
+// A keyed hash is a MAC, and the checkfile might not be trusted, so it can't be
+// allowed to choose a short length that's easy to guess. Unless --length is
+// given explicitly, keyed hashes must be at least the default length.
+fn check_expected_len(expected_len: usize, args: &Args) -> anyhow::Result<()> {
+    if args.length_given {
+        ensure!(
+            expected_len as u64 == args.len(),
+            "Hash length doesn't match --length"
+        );
+    } else if args.keyed() {
+        ensure!(
+            expected_len >= blake3::OUT_LEN,
+            "Keyed hash is shorter than {} bytes (use --length to check it)",
+            blake3::OUT_LEN
+        );
+    }
+    Ok(())
+}
+
 // Returns true for success. Having a boolean return value here, instead of
 // passing down the files_failed reference, makes it less likely that we might
 // forget to set it in some error condition.
 fn check_one_line(line: &str, args: &Args) -> bool {
-    let parse_result = parse_check_line(&line);
+    let parse_result = parse_check_line(&line).and_then(|parsed| {
+        check_expected_len(parsed.expected_hash.len(), args)?;
+        Ok(parsed)
+    });
     let ParsedCheckLine {
         file_string,
         is_escaped,
@@ -452,7 +1053,15 @@ fn check_one_line(line: &str, args: &Args) -> bool {
     } = match parse_result {
         Ok(parsed) => parsed,
         Err(e) => {
-            eprintln!("{}: {}", NAME, e);
+            if args.json() {
+                println!(
+                    "{{\"line\":{},\"status\":\"error\",\"message\":{}}}",
+                    json_string(line.trim_end_matches(['\r', '\n'])),
+                    json_string(&e.to_string()),
+                );
+            } else {
+                eprintln!("{}: {}", NAME, e);
+            }
             return false;
         }
     };
@@ -461,28 +1070,44 @@ fn check_one_line(line: &str, args: &Args) -> bool {
     } else {
         file_string
     };
-    let found_hash: blake3::Hash;
+    let expected_len = expected_hash.len() as u64;
+    let mut found_hash = vec![0; expected_hash.len()];
     match hash_path(args, &file_path) {
-        Ok(mut output) => {
-            let mut found_hash_bytes = [0; blake3::OUT_LEN];
-            output.fill(&mut found_hash_bytes);
-            found_hash = found_hash_bytes.into();
-        }
+        Ok(mut output) => output.fill(&mut found_hash),
         Err(e) => {
-            println!("{}: FAILED ({})", file_string, e);
+            if args.json() {
+                println!(
+                    "{{{}\"status\":\"error\",\"expected\":\"{}\",\"message\":{}}}",
+                    json_file_fields(&file_path, expected_len, args),
+                    hex::encode(&expected_hash),
+                    json_string(&e.to_string()),
+                );
+            } else {
+                println!("{}: FAILED ({})", file_string, e);
+            }
             return false;
         }
     };
-    // This is a constant-time comparison.
-    if expected_hash == found_hash {
+    // This is a constant-time comparison, since the hash might be a MAC.
+    let success = constant_time_eq::constant_time_eq(&expected_hash, &found_hash);
+    if args.json() {
+        if !success || !args.quiet() {
+            println!(
+                "{{{}\"status\":\"{}\",\"expected\":\"{}\",\"hash\":\"{}\"}}",
+                json_file_fields(&file_path, expected_len, args),
+                if success { "OK" } else { "FAILED" },
+                hex::encode(&expected_hash),
+                hex::encode(&found_hash),
+            );
+        }
+    } else if success {
         if !args.quiet() {
             println!("{}: OK", file_string);
         }
-        true
     } else {
         println!("{}: FAILED", file_string);
-        false
     }
+    success
 }
 
 fn check_one_checkfile(path: &Path, args: &Args, files_failed: &mut u64) -> anyhow::Result<()> {
@@ -491,6 +1116,9 @@ fn check_one_checkfile(path: &Path, args: &Args, files_failed: &mut u64) -> anyh
     let mut stdin_lock;
     let mut bufreader: io::BufReader<&mut dyn Read>;
     if path == Path::new("-") {
+        if args.keyed() {
+            bail!("Cannot open `-` in keyed mode");
+        }
         stdin = io::stdin();
         stdin_lock = stdin.lock();
         bufreader = io::BufReader::new(&mut stdin_lock);
@@ -523,25 +1151,15 @@ fn main() -> anyhow::Result<()> {
         thread_pool_builder = thread_pool_builder.num_threads(num_threads);
     }
     let thread_pool = thread_pool_builder.build()?;
-    thread_pool.install(|| {
-        let mut files_failed = 0u64;
-        // Note that file_args automatically includes `-` if nothing is given.
-        for path in &args.file_args {
-            if args.check() {
+    let mut files_failed = 0u64;
+    if args.check() {
+        thread_pool.install(|| -> anyhow::Result<()> {
+            for path in &args.file_args {
                 check_one_checkfile(path, &args, &mut files_failed)?;
-            } else {
-                // Errors encountered in hashing are tolerated and printed to
-                // stderr. This allows e.g. `b3sum *` to print errors for
-                // non-files and keep going. However, if we encounter any
-                // errors we'll still return non-zero at the end.
-                let result = hash_one_input(path, &args);
-                if let Err(e) = result {
-                    files_failed = files_failed.saturating_add(1);
-                    eprintln!("{}: {}: {}", NAME, path.to_string_lossy(), e);
-                }
             }
-        }
-        if args.check() && files_failed > 0 {
+            Ok(())
+        })?;
+        if files_failed > 0 {
             eprintln!(
                 "{}: WARNING: {} computed checksum{} did NOT match",
                 NAME,
@@ -549,8 +1167,19 @@ fn main() -> anyhow::Result<()> {
                 if files_failed == 1 { "" } else { "s" },
             );
         }
-        std::process::exit(if files_failed > 0 { 1 } else { 0 });
-    })
+    } else {
+        // Note that file_args automatically includes `-` if nothing is given.
+        let mut inputs = Vec::new();
+        for path in &args.file_args {
+            if args.recursive() && path.is_dir() {
+                inputs.extend(walk(path, &args));
+            } else {
+                inputs.push(WalkItem::File(path.clone()));
+            }
+        }
+        files_failed = hash_inputs(&inputs, &args, &thread_pool);
+    }
+    std::process::exit(if files_failed > 0 { 1 } else { 0 });
 }
 
 #[cfg(test)]
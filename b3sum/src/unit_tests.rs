@@ -16,7 +16,7 @@ fn test_parse_check_line() {
         "0909090909090909090909090909090909090909090909090909090909090909  foo",
     )
     .unwrap();
-    assert_eq!(expected_hash, blake3::Hash::from([0x09; 32]));
+    assert_eq!(expected_hash, [0x09; 32]);
     assert!(!is_escaped);
     assert_eq!(file_string, "foo");
     assert_eq!(file_path, Path::new("foo"));
@@ -31,7 +31,7 @@ fn test_parse_check_line() {
         "fafafafafafafafafafafafafafafafafafafafafafafafafafafafafafafafa   \t\r\n\n\r \t\r\n\n\r",
     )
     .unwrap();
-    assert_eq!(expected_hash, blake3::Hash::from([0xfa; 32]));
+    assert_eq!(expected_hash, [0xfa; 32]);
     assert!(!is_escaped);
     assert_eq!(file_string, " \t\r\n\n\r \t");
     assert_eq!(file_path, Path::new(" \t\r\n\n\r \t"));
@@ -46,7 +46,7 @@ fn test_parse_check_line() {
         "4242424242424242424242424242424242424242424242424242424242424242   ",
     )
     .unwrap();
-    assert_eq!(expected_hash, blake3::Hash::from([0x42; 32]));
+    assert_eq!(expected_hash, [0x42; 32]);
     assert!(!is_escaped);
     assert_eq!(file_string, " ");
     assert_eq!(file_path, Path::new(" "));
@@ -65,7 +65,7 @@ fn test_parse_check_line() {
             "4343434343434343434343434343434343434343434343434343434343434343  fo\\a\\no",
         )
         .unwrap();
-        assert_eq!(expected_hash, blake3::Hash::from([0x43; 32]));
+        assert_eq!(expected_hash, [0x43; 32]);
         assert!(!is_escaped);
         assert_eq!(file_string, "fo\\a\\no");
         assert_eq!(file_path, Path::new("fo\\a\\no"));
@@ -81,7 +81,7 @@ fn test_parse_check_line() {
         "\\4444444444444444444444444444444444444444444444444444444444444444  fo\\r\\n\\n\\ro",
     )
     .unwrap();
-    assert_eq!(expected_hash, blake3::Hash::from([0x44; 32]));
+    assert_eq!(expected_hash, [0x44; 32]);
     assert!(is_escaped);
     assert_eq!(file_string, "fo\\r\\n\\n\\ro");
     assert_eq!(file_path, Path::new("fo\r\n\n\ro"));
@@ -98,7 +98,7 @@ fn test_parse_check_line() {
             "\\4545454545454545454545454545454545454545454545454545454545454545  fo\\n\\\\o",
         )
         .unwrap();
-        assert_eq!(expected_hash, blake3::Hash::from([0x45; 32]));
+        assert_eq!(expected_hash, [0x45; 32]);
         assert!(is_escaped);
         assert_eq!(file_string, "fo\\n\\\\o");
         assert_eq!(file_path, Path::new("fo\n\\o"));
@@ -114,7 +114,7 @@ fn test_parse_check_line() {
         "4646464646464646464646464646464646464646464646464646464646464646  否认",
     )
     .unwrap();
-    assert_eq!(expected_hash, blake3::Hash::from([0x46; 32]));
+    assert_eq!(expected_hash, [0x46; 32]);
     assert!(!is_escaped);
     assert_eq!(file_string, "否认");
     assert_eq!(file_path, Path::new("否认"));
@@ -129,7 +129,7 @@ fn test_parse_check_line() {
         "4747474747474747474747474747474747474747474747474747474747474747  foo  bar",
     )
     .unwrap();
-    assert_eq!(expected_hash, blake3::Hash::from([0x47; 32]));
+    assert_eq!(expected_hash, [0x47; 32]);
     assert!(!is_escaped);
     assert_eq!(file_string, "foo  bar");
     assert_eq!(file_path, Path::new("foo  bar"));
@@ -144,11 +144,28 @@ fn test_parse_check_line() {
         "BLAKE3 (foo) = bar) = 4848484848484848484848484848484848484848484848484848484848484848",
     )
     .unwrap();
-    assert_eq!(expected_hash, blake3::Hash::from([0x48; 32]));
+    assert_eq!(expected_hash, [0x48; 32]);
     assert!(!is_escaped);
     assert_eq!(file_string, "foo) = bar");
     assert_eq!(file_path, Path::new("foo) = bar"));
 
+    // hashes of other lengths, from --length
+    let crate::ParsedCheckLine {
+        file_path,
+        expected_hash,
+        ..
+    } = crate::parse_check_line("ab  foo").unwrap();
+    assert_eq!(expected_hash, [0xab]);
+    assert_eq!(file_path, Path::new("foo"));
+    let long_hex = "5a".repeat(100);
+    let crate::ParsedCheckLine {
+        file_path,
+        expected_hash,
+        ..
+    } = crate::parse_check_line(&format!("BLAKE3 (foo) = {}", long_hex)).unwrap();
+    assert_eq!(expected_hash, [0x5a; 100]);
+    assert_eq!(file_path, Path::new("foo"));
+
     // =========================
     // ===== Failure Cases =====
     // =========================
@@ -162,6 +179,11 @@ fn test_parse_check_line() {
     crate::parse_check_line("0000000000000000000000000000000000000000000000000000000000000000  ")
         .unwrap_err();
 
+    // odd or empty hash length
+    crate::parse_check_line("abc  foo").unwrap_err();
+    crate::parse_check_line("  foo").unwrap_err();
+    crate::parse_check_line("BLAKE3 (foo) = ").unwrap_err();
+
     // not enough spaces
     crate::parse_check_line("0000000000000000000000000000000000000000000000000000000000000000 foo")
         .unwrap_err();
@@ -233,3 +255,66 @@ fn test_filepath_to_string() {
     }
     assert!(output.is_escaped);
 }
+
+#[test]
+fn test_glob_match() {
+    fn glob_match(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        crate::glob_match(&pattern, &text)
+    }
+
+    assert!(glob_match("foo", "foo"));
+    assert!(!glob_match("foo", "foobar"));
+    assert!(!glob_match("foo", "fo"));
+    assert!(glob_match("", ""));
+    assert!(!glob_match("", "a"));
+
+    // * and ? don't match slashes
+    assert!(glob_match("*.txt", "a.txt"));
+    assert!(glob_match("*.txt", ".txt"));
+    assert!(!glob_match("*.txt", "a.txt.bak"));
+    assert!(!glob_match("*.txt", "dir/a.txt"));
+    assert!(glob_match("*/*.txt", "dir/a.txt"));
+    assert!(glob_match("a?c", "abc"));
+    assert!(!glob_match("a?c", "a/c"));
+    assert!(!glob_match("a?c", "ac"));
+
+    // ** matches anything
+    assert!(glob_match("**/*.txt", "x/y/z.txt"));
+    assert!(glob_match("target/**", "target/debug/b3sum"));
+    assert!(!glob_match("target/**", "src/target"));
+
+    // character classes
+    assert!(glob_match("[abc]x", "bx"));
+    assert!(!glob_match("[abc]x", "dx"));
+    assert!(glob_match("[a-c0-9]", "5"));
+    assert!(!glob_match("[a-c0-9]", "d"));
+    assert!(glob_match("[!a-c]", "d"));
+    assert!(glob_match("[^a-c]", "d"));
+    assert!(!glob_match("[!a-c]", "b"));
+    assert!(glob_match("[]]", "]"));
+    assert!(glob_match("[!]]", "a"));
+    assert!(glob_match("[-a]", "-"));
+    assert!(!glob_match("[a]", "/"));
+
+    // an unclosed [ is a literal
+    assert!(glob_match("[ab", "[ab"));
+    assert!(!glob_match("[ab", "a"));
+
+    // backslash escapes
+    assert!(glob_match("\\*", "*"));
+    assert!(!glob_match("\\*", "a"));
+    assert!(glob_match("\\[a]", "[a]"));
+}
+
+#[test]
+fn test_json_string() {
+    assert_eq!(crate::json_string(""), r#""""#);
+    assert_eq!(crate::json_string("foo bar"), r#""foo bar""#);
+    assert_eq!(
+        crate::json_string("a\"b\\c\nd\re\tf\0g\u{1f}h\u{7f}i"),
+        r#""a\"b\\c\nd\re\tf\u0000g\u001fh"#.to_string() + "\u{7f}i\"",
+    );
+    assert_eq!(crate::json_string("你好 �"), "\"你好 �\"");
+}
@@ -678,3 +678,675 @@ fn test_globbing() {
         .unwrap();
     assert_eq!(expected, output);
 }
+
+#[test]
+fn test_recursive() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("tree/sub/deeper")).unwrap();
+    fs::create_dir_all(dir.path().join("tree/.git")).unwrap();
+    fs::write(dir.path().join("tree/b.txt"), b"b").unwrap();
+    fs::write(dir.path().join("tree/a.txt"), b"a").unwrap();
+    fs::write(dir.path().join("tree/sub/c.txt"), b"c").unwrap();
+    fs::write(dir.path().join("tree/sub/c.txt.bak"), b"c bak").unwrap();
+    fs::write(dir.path().join("tree/sub/deeper/d.txt"), b"d").unwrap();
+    fs::write(dir.path().join("tree/.git/config"), b"config").unwrap();
+    fs::write(dir.path().join("other.txt"), b"other").unwrap();
+
+    let line = |input: &[u8], path: &str| format!("{}  {}\n", blake3::hash(input).to_hex(), path);
+
+    // Directories are listed in sorted order, and non-directory arguments
+    // are hashed as usual.
+    let output = cmd!(b3sum_exe(), "--recursive", "tree", "other.txt")
+        .dir(dir.path())
+        .stdout_capture()
+        .run()
+        .unwrap();
+    let expected = [
+        line(b"config", "tree/.git/config"),
+        line(b"a", "tree/a.txt"),
+        line(b"b", "tree/b.txt"),
+        line(b"c", "tree/sub/c.txt"),
+        line(b"c bak", "tree/sub/c.txt.bak"),
+        line(b"d", "tree/sub/deeper/d.txt"),
+        line(b"other", "other.txt"),
+    ]
+    .concat();
+    assert_eq!(expected.as_bytes(), &output.stdout[..]);
+
+    // The output works as a checkfile.
+    let checkfile = dir.path().join("checkfile");
+    fs::write(&checkfile, &output.stdout).unwrap();
+    cmd!(b3sum_exe(), "--check", &checkfile)
+        .dir(dir.path())
+        .stdout_null()
+        .run()
+        .unwrap();
+
+    // Patterns without a slash match names, and patterns with a slash match
+    // paths below the directory argument.
+    let output = cmd!(
+        b3sum_exe(),
+        "--recursive",
+        "--exclude=.git",
+        "--exclude=*.bak",
+        "--exclude=sub/deeper",
+        "tree"
+    )
+    .dir(dir.path())
+    .read()
+    .unwrap();
+    let expected = [
+        line(b"a", "tree/a.txt"),
+        line(b"b", "tree/b.txt"),
+        line(b"c", "tree/sub/c.txt"),
+    ]
+    .concat();
+    assert_eq!(expected.trim_end(), output);
+
+    // Without --recursive, directories are an error.
+    let output = cmd!(b3sum_exe(), "tree")
+        .dir(dir.path())
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked()
+        .run()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    // The walking options require --recursive.
+    for flag in ["--follow-symlinks", "--exclude=foo", "--one-file-system"] {
+        let output = cmd!(b3sum_exe(), flag, "tree")
+            .dir(dir.path())
+            .stdout_capture()
+            .stderr_capture()
+            .unchecked()
+            .run()
+            .unwrap();
+        assert!(!output.status.success());
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn test_recursive_symlinks() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("tree/sub")).unwrap();
+    fs::create_dir_all(dir.path().join("outside")).unwrap();
+    fs::write(dir.path().join("tree/a"), b"a").unwrap();
+    fs::write(dir.path().join("outside/b"), b"b").unwrap();
+    std::os::unix::fs::symlink("../outside", dir.path().join("tree/link")).unwrap();
+    std::os::unix::fs::symlink("../a", dir.path().join("tree/sub/file_link")).unwrap();
+
+    let line = |input: &[u8], path: &str| format!("{}  {}\n", blake3::hash(input).to_hex(), path);
+
+    // Symlinks are skipped by default.
+    let output = cmd!(b3sum_exe(), "--recursive", "tree")
+        .dir(dir.path())
+        .read()
+        .unwrap();
+    assert_eq!(line(b"a", "tree/a").trim_end(), output);
+
+    let output = cmd!(b3sum_exe(), "--recursive", "--follow-symlinks", "tree")
+        .dir(dir.path())
+        .read()
+        .unwrap();
+    let expected = [
+        line(b"a", "tree/a"),
+        line(b"b", "tree/link/b"),
+        line(b"a", "tree/sub/file_link"),
+    ]
+    .concat();
+    assert_eq!(expected.trim_end(), output);
+
+    // Symlink loops are reported as errors, but the rest of the walk goes on.
+    std::os::unix::fs::symlink("..", dir.path().join("tree/sub/loop")).unwrap();
+    let output = cmd!(b3sum_exe(), "--recursive", "--follow-symlinks", "tree")
+        .dir(dir.path())
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked()
+        .run()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(expected.as_bytes(), &output.stdout[..]);
+    let stderr = std::str::from_utf8(&output.stderr).unwrap();
+    assert_eq!("b3sum: tree/sub/loop: Filesystem loop detected\n", stderr);
+
+    // --one-file-system doesn't skip anything here.
+    let output = cmd!(b3sum_exe(), "--recursive", "--one-file-system", "tree")
+        .dir(dir.path())
+        .read()
+        .unwrap();
+    assert_eq!(line(b"a", "tree/a").trim_end(), output);
+}
+
+#[test]
+fn test_tree_digest() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("foo/bar")).unwrap();
+    fs::create_dir_all(dir.path().join("foo/empty")).unwrap();
+    fs::write(dir.path().join("foo/a"), b"hi\n").unwrap();
+    fs::write(dir.path().join("foo/bar/b"), b"lo\n").unwrap();
+
+    // Recompute the encoding from tree_digest.md by hand.
+    fn entry(name: &str, type_byte: u8, payload: &[u8]) -> Vec<u8> {
+        let mut encoded = (name.len() as u64).to_le_bytes().to_vec();
+        encoded.extend_from_slice(name.as_bytes());
+        encoded.push(type_byte);
+        encoded.extend_from_slice(payload);
+        encoded
+    }
+    let file = |contents: &[u8]| {
+        blake3::derive_key("BLAKE3 2026-10-15 b3sum tree-digest file v1", contents)
+    };
+    let directory = |entries: &[Vec<u8>]| {
+        blake3::derive_key(
+            "BLAKE3 2026-10-15 b3sum tree-digest directory v1",
+            &entries.concat(),
+        )
+    };
+    let bar = directory(&[entry("b", 1, &file(b"lo\n"))]);
+    let expected = directory(&[
+        entry("a", 1, &file(b"hi\n")),
+        entry("bar", 4, &bar),
+        entry("empty", 4, &directory(&[])),
+    ]);
+
+    let output = cmd!(b3sum_exe(), "--tree-digest", "foo")
+        .dir(dir.path())
+        .read()
+        .unwrap();
+    assert_eq!(format!("{}  foo", hex::encode(expected)), output);
+
+    // The digest doesn't depend on the name of the directory.
+    let output = cmd!(
+        b3sum_exe(),
+        "--tree-digest",
+        "--no-names",
+        "--no-mmap",
+        "foo/bar"
+    )
+    .dir(dir.path())
+    .read()
+    .unwrap();
+    assert_eq!(hex::encode(bar), output);
+
+    // --length and --seek work with the extended output.
+    let mut expected_xof = [0; 100];
+    blake3::Hasher::new_derive_key("BLAKE3 2026-10-15 b3sum tree-digest directory v1")
+        .update(&entry("b", 1, &file(b"lo\n")))
+        .finalize_xof()
+        .fill(&mut expected_xof);
+    let output = cmd!(
+        b3sum_exe(),
+        "--tree-digest",
+        "--raw",
+        "-l60",
+        "--seek=40",
+        "foo/bar"
+    )
+    .dir(dir.path())
+    .stdout_capture()
+    .run()
+    .unwrap()
+    .stdout;
+    assert_eq!(expected_xof[40..], output);
+
+    // Files and stdin aren't directories.
+    for arg in ["foo/a", "-"] {
+        let output = cmd!(b3sum_exe(), "--tree-digest", arg)
+            .dir(dir.path())
+            .stdin_bytes("foo")
+            .stdout_capture()
+            .stderr_capture()
+            .unchecked()
+            .run()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+}
+
+// The walk doesn't recurse, so it handles a 1000-level tree. This is Unix-only,
+// because the paths are longer than Windows allows by default.
+#[test]
+#[cfg(unix)]
+fn test_tree_digest_deep() {
+    const DEPTH: usize = 1000;
+    let dir = tempfile::tempdir().unwrap();
+    let mut path = dir.path().join("root");
+    for _ in 0..DEPTH {
+        path.push("d");
+    }
+    fs::create_dir_all(&path).unwrap();
+
+    // Each level is a directory with a single entry, "d".
+    let context = "BLAKE3 2026-10-15 b3sum tree-digest directory v1";
+    let mut expected = blake3::derive_key(context, b"");
+    for _ in 0..DEPTH {
+        let mut hasher = blake3::Hasher::new_derive_key(context);
+        hasher.update(&1u64.to_le_bytes());
+        hasher.update(b"d");
+        hasher.update(&[4]);
+        hasher.update(&expected);
+        expected = *hasher.finalize().as_bytes();
+    }
+
+    let output = cmd!(b3sum_exe(), "--tree-digest", "--no-names", "root")
+        .dir(dir.path())
+        .read()
+        .unwrap();
+    assert_eq!(hex::encode(expected), output);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_tree_digest_unix() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("foo")).unwrap();
+    fs::write(dir.path().join("foo/a"), b"hi\n").unwrap();
+    let digest = || {
+        cmd!(b3sum_exe(), "--tree-digest", "--no-names", "foo")
+            .dir(dir.path())
+            .read()
+            .unwrap()
+    };
+    let mode =
+        |mode| fs::set_permissions(dir.path().join("foo/a"), fs::Permissions::from_mode(mode));
+    // A directory with the single entry "a", of the given kind.
+    let expected = |kind| {
+        let file_context = "BLAKE3 2026-10-15 b3sum tree-digest file v1";
+        let dir_context = "BLAKE3 2026-10-15 b3sum tree-digest directory v1";
+        let mut hasher = blake3::Hasher::new_derive_key(dir_context);
+        hasher.update(&1u64.to_le_bytes());
+        hasher.update(b"a");
+        hasher.update(&[kind]);
+        hasher.update(&blake3::derive_key(file_context, b"hi\n"));
+        hasher.finalize().to_hex().to_string()
+    };
+    mode(0o644).unwrap();
+    let plain = digest();
+    assert_eq!(expected(1), plain);
+
+    // Only the owner's executable bit counts.
+    mode(0o655).unwrap();
+    assert_eq!(plain, digest());
+    mode(0o744).unwrap();
+    let executable = digest();
+    assert_eq!(expected(2), executable);
+
+    // Symlinks aren't followed, and dangling symlinks are fine.
+    std::os::unix::fs::symlink("nowhere", dir.path().join("foo/link")).unwrap();
+    let with_link = digest();
+    assert_ne!(executable, with_link);
+    fs::remove_file(dir.path().join("foo/link")).unwrap();
+    std::os::unix::fs::symlink("elsewhere", dir.path().join("foo/link")).unwrap();
+    assert_ne!(with_link, digest());
+
+    // Other file types are an error, and the error includes the path.
+    cmd!("mkfifo", dir.path().join("foo/fifo")).run().unwrap();
+    let output = cmd!(b3sum_exe(), "--tree-digest", "foo")
+        .dir(dir.path())
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked()
+        .run()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = std::str::from_utf8(&output.stderr).unwrap();
+    assert_eq!("b3sum: foo: foo/fifo: Unsupported file type\n", stderr);
+}
+
+#[test]
+fn test_many_files_in_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut args: Vec<OsString> = Vec::new();
+    let mut expected_stdout = String::new();
+    let mut expected_stderr = String::new();
+    for i in 0..300 {
+        let name = format!("file{}", i);
+        if i % 7 == 3 {
+            // Leave some files missing, to check the error reporting.
+            let error = fs::File::open(dir.path().join(&name)).unwrap_err();
+            expected_stderr += &format!("b3sum: {}: {}\n", name, error);
+        } else {
+            // Vary the sizes, so that the files take different amounts of time.
+            let contents = vec![i as u8; (i * 997) % 5000];
+            fs::write(dir.path().join(&name), &contents).unwrap();
+            expected_stdout += &format!("{}  {}\n", blake3::hash(&contents).to_hex(), name);
+        }
+        args.push(name.into());
+    }
+
+    for threads in ["--num-threads=1", "--num-threads=4"] {
+        let mut threads_args: Vec<OsString> = vec![threads.into()];
+        threads_args.extend(args.iter().cloned());
+        let output = cmd(b3sum_exe(), &threads_args)
+            .dir(dir.path())
+            .stdout_capture()
+            .stderr_capture()
+            .unchecked()
+            .run()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_eq!(expected_stdout.as_bytes(), &output.stdout[..]);
+        assert_eq!(expected_stderr.as_bytes(), &output.stderr[..]);
+    }
+
+    // Stdin goes to the first `-`, in order, and later ones see it empty.
+    fs::write(dir.path().join("a"), b"a").unwrap();
+    let output = cmd!(b3sum_exe(), "--num-threads=4", "a", "-", "a", "-")
+        .dir(dir.path())
+        .stdin_bytes("foo")
+        .read()
+        .unwrap();
+    let expected = format!(
+        "{0}  a\n{1}  -\n{0}  a\n{2}  -",
+        blake3::hash(b"a").to_hex(),
+        blake3::hash(b"foo").to_hex(),
+        blake3::hash(b"").to_hex(),
+    );
+    assert_eq!(expected, output);
+}
+
+#[test]
+fn test_json() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a"), b"foo").unwrap();
+    fs::write(dir.path().join("b\"c"), b"bar").unwrap();
+    let missing_error = fs::File::open(dir.path().join("missing")).unwrap_err();
+    let foo_hash = blake3::hash(b"foo").to_hex();
+    let bar_hash = blake3::hash(b"bar").to_hex();
+
+    let output = cmd!(b3sum_exe(), "--json", "a", "b\"c", "missing")
+        .dir(dir.path())
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked()
+        .run()
+        .unwrap();
+    assert!(!output.status.success());
+    let expected = format!(
+        concat!(
+            r#"{{"path":"a","path_hex":"61","mode":"hash","length":32,"seek":0,"#,
+            r#""status":"OK","hash":"{}"}}"#,
+            "\n",
+            r#"{{"path":"b\"c","path_hex":"622263","mode":"hash","length":32,"seek":0,"#,
+            r#""status":"OK","hash":"{}"}}"#,
+            "\n",
+            r#"{{"path":"missing","path_hex":"6d697373696e67","mode":"hash","length":32,"#,
+            r#""seek":0,"status":"error","message":"{}"}}"#,
+            "\n",
+        ),
+        foo_hash, bar_hash, missing_error,
+    );
+    assert_eq!(expected.as_bytes(), &output.stdout[..]);
+    // Errors go to stdout with everything else.
+    assert!(output.stderr.is_empty());
+
+    // The mode, length, and seek are included.
+    let mut expected_xof = [0; 10];
+    blake3::Hasher::new_derive_key("ctx")
+        .update(b"foo")
+        .finalize_xof()
+        .fill(&mut expected_xof);
+    let output = cmd!(
+        b3sum_exe(),
+        "--json",
+        "--derive-key=ctx",
+        "-l5",
+        "--seek=5",
+        "a"
+    )
+    .dir(dir.path())
+    .read()
+    .unwrap();
+    let expected = format!(
+        concat!(
+            r#"{{"path":"a","path_hex":"61","mode":"derive_key","context":"ctx","length":5,"#,
+            r#""seek":5,"status":"OK","hash":"{}"}}"#,
+        ),
+        hex::encode(&expected_xof[5..]),
+    );
+    assert_eq!(expected, output);
+
+    // --check reports the expected hash, and the found hash or the error.
+    let checkfile = format!(
+        "{foo}  a\n{foo}  b\"c\n{foo}  missing\nnot a check line\n",
+        foo = foo_hash,
+    );
+    let output = cmd!(b3sum_exe(), "--json", "--check")
+        .dir(dir.path())
+        .stdin_bytes(checkfile.as_bytes())
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked()
+        .run()
+        .unwrap();
+    assert!(!output.status.success());
+    let expected = format!(
+        concat!(
+            r#"{{"path":"a","path_hex":"61","mode":"hash","length":32,"seek":0,"#,
+            r#""status":"OK","expected":"{foo}","hash":"{foo}"}}"#,
+            "\n",
+            r#"{{"path":"b\"c","path_hex":"622263","mode":"hash","length":32,"seek":0,"#,
+            r#""status":"FAILED","expected":"{foo}","hash":"{bar}"}}"#,
+            "\n",
+            r#"{{"path":"missing","path_hex":"6d697373696e67","mode":"hash","length":32,"#,
+            r#""seek":0,"status":"error","expected":"{foo}","message":"{error}"}}"#,
+            "\n",
+            r#"{{"line":"not a check line","status":"error","#,
+            r#""message":"Invalid check line format"}}"#,
+            "\n",
+        ),
+        foo = foo_hash,
+        bar = bar_hash,
+        error = missing_error,
+    );
+    assert_eq!(expected, std::str::from_utf8(&output.stdout).unwrap());
+
+    // --quiet skips the OK objects.
+    let output = cmd!(b3sum_exe(), "--json", "--check", "--quiet")
+        .dir(dir.path())
+        .stdin_bytes(checkfile.as_bytes())
+        .stdout_capture()
+        .unchecked()
+        .run()
+        .unwrap();
+    let stdout = std::str::from_utf8(&output.stdout).unwrap();
+    assert_eq!(3, stdout.lines().count());
+    assert!(!stdout.contains(r#""status":"OK""#));
+
+    // --json doesn't go with the other output formats.
+    for flag in ["--raw", "--tag", "--no-names"] {
+        let output = cmd!(b3sum_exe(), "--json", flag, "a")
+            .dir(dir.path())
+            .stdout_capture()
+            .stderr_capture()
+            .unchecked()
+            .run()
+            .unwrap();
+        assert!(!output.status.success());
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn test_json_invalid_unicode_on_unix() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let name = std::ffi::OsStr::from_bytes(b"abc\xffxyz\n");
+    fs::write(dir.path().join(name), b"foo").unwrap();
+    let output = cmd!(b3sum_exe(), "--json", name)
+        .dir(dir.path())
+        .read()
+        .unwrap();
+    let expected = format!(
+        concat!(
+            r#"{{"path":"abc�xyz\n","path_hex":"616263ff78797a0a","mode":"hash","length":32,"#,
+            r#""seek":0,"status":"OK","hash":"{}"}}"#,
+        ),
+        blake3::hash(b"foo").to_hex(),
+    );
+    assert_eq!(expected, output);
+}
+
+#[test]
+fn test_check_keyed_derive_key_and_length() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a"), b"foo").unwrap();
+    fs::write(dir.path().join("b"), b"bar").unwrap();
+    let key = [42; blake3::KEY_LEN];
+
+    // Checkfiles made with --keyed and --length check with --keyed, and not
+    // without it.
+    let checkfile = dir.path().join("checkfile");
+    let output = cmd!(b3sum_exe(), "--keyed", "--length=100", "a", "b")
+        .dir(dir.path())
+        .stdin_bytes(&key[..])
+        .stdout_capture()
+        .run()
+        .unwrap()
+        .stdout;
+    fs::write(&checkfile, &output).unwrap();
+    let output = cmd!(b3sum_exe(), "--keyed", "--check", &checkfile)
+        .dir(dir.path())
+        .stdin_bytes(&key[..])
+        .read()
+        .unwrap();
+    assert_eq!("a: OK\nb: OK", output);
+    let output = cmd!(b3sum_exe(), "--check", &checkfile)
+        .dir(dir.path())
+        .stdout_capture()
+        .stderr_null()
+        .unchecked()
+        .run()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(b"a: FAILED\nb: FAILED\n", &output.stdout[..]);
+    let wrong_key = [43; blake3::KEY_LEN];
+    let output = cmd!(b3sum_exe(), "--keyed", "--check", &checkfile)
+        .dir(dir.path())
+        .stdin_bytes(&wrong_key[..])
+        .stdout_capture()
+        .stderr_null()
+        .unchecked()
+        .run()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(b"a: FAILED\nb: FAILED\n", &output.stdout[..]);
+
+    // In keyed mode, the key comes from stdin, so the checkfile can't.
+    let output = cmd!(b3sum_exe(), "--keyed", "--check", "-")
+        .dir(dir.path())
+        .stdin_bytes(&key[..])
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked()
+        .run()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = std::str::from_utf8(&output.stderr).unwrap();
+    assert!(stderr.starts_with("Error: Cannot open `-` in keyed mode\n"));
+
+    // Same for --derive-key, and short tagged hashes.
+    let output = cmd!(b3sum_exe(), "--derive-key=ctx", "--tag", "-l1", "a", "b")
+        .dir(dir.path())
+        .read()
+        .unwrap();
+    let expected = format!(
+        "BLAKE3 (a) = {}\nBLAKE3 (b) = {}",
+        hex::encode(&blake3::derive_key("ctx", b"foo")[..1]),
+        hex::encode(&blake3::derive_key("ctx", b"bar")[..1]),
+    );
+    assert_eq!(expected, output);
+    let output = cmd!(b3sum_exe(), "--derive-key=ctx", "--check")
+        .dir(dir.path())
+        .stdin_bytes(output)
+        .read()
+        .unwrap();
+    assert_eq!("a: OK\nb: OK", output);
+
+    // With --length, every hash must have that length.
+    let output = cmd!(
+        b3sum_exe(),
+        "--keyed",
+        "--check",
+        "--length=100",
+        &checkfile
+    )
+    .dir(dir.path())
+    .stdin_bytes(&key[..])
+    .read()
+    .unwrap();
+    assert_eq!("a: OK\nb: OK", output);
+    let output = cmd!(b3sum_exe(), "--keyed", "--check", "--length=99", &checkfile)
+        .dir(dir.path())
+        .stdin_bytes(&key[..])
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked()
+        .run()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(b"", &output.stdout[..]);
+    let stderr = std::str::from_utf8(&output.stderr).unwrap();
+    assert!(stderr.starts_with("b3sum: Hash length doesn't match --length\n"));
+}
+
+#[test]
+fn test_check_keyed_short_hash() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("f"), b"foo").unwrap();
+    let key = [42; blake3::KEY_LEN];
+
+    // Someone who can edit a manifest of MACs shouldn't be able to replace a
+    // MAC with a short one and guess it. Try every 1-byte value.
+    let checkfile = dir.path().join("checkfile");
+    let lines: String = (0..=255).map(|b| format!("{:02x}  f\n", b)).collect();
+    fs::write(&checkfile, &lines).unwrap();
+    let output = cmd!(b3sum_exe(), "--keyed", "--check", &checkfile)
+        .dir(dir.path())
+        .stdin_bytes(&key[..])
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked()
+        .run()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(b"", &output.stdout[..]);
+    let stderr = std::str::from_utf8(&output.stderr).unwrap();
+    assert_eq!(
+        256,
+        stderr
+            .matches("b3sum: Keyed hash is shorter than 32 bytes (use --length to check it)\n")
+            .count()
+    );
+
+    // An explicit --length allows short keyed hashes.
+    let output = cmd!(b3sum_exe(), "--keyed", "--check", "--length=1", &checkfile)
+        .dir(dir.path())
+        .stdin_bytes(&key[..])
+        .stdout_capture()
+        .stderr_null()
+        .unchecked()
+        .run()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = std::str::from_utf8(&output.stdout).unwrap();
+    assert_eq!(1, stdout.matches("f: OK\n").count());
+    assert_eq!(255, stdout.matches("f: FAILED\n").count());
+
+    // Unkeyed short hashes are fine, since anyone could compute them anyway.
+    let short = hex::encode(&blake3::hash(b"foo").as_bytes()[..1]);
+    let output = cmd!(b3sum_exe(), "--check")
+        .dir(dir.path())
+        .stdin_bytes(format!("{}  f\n", short))
+        .read()
+        .unwrap();
+    assert_eq!("f: OK", output);
+}
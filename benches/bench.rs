@@ -291,6 +291,50 @@ fn bench_atonce_1024_kib(b: &mut Bencher) {
     bench_atonce(b, 1024 * KIB);
 }
 
+// Hash 1024 independent messages of the same length, either in a loop or with hash_many.
+fn bench_many_messages(b: &mut Bencher, len: usize, batched: bool) {
+    const COUNT: usize = 1024;
+    let mut input = RandomInput::new(b, COUNT * len);
+    b.iter(|| {
+        let messages: Vec<&[u8]> = input.get().chunks_exact(len).collect();
+        if batched {
+            blake3::hash_many(&messages)
+        } else {
+            messages.iter().map(|m| blake3::hash(m)).collect()
+        }
+    });
+}
+
+#[bench]
+fn bench_many_messages_0064_loop(b: &mut Bencher) {
+    bench_many_messages(b, 64, false);
+}
+
+#[bench]
+fn bench_many_messages_0064_batched(b: &mut Bencher) {
+    bench_many_messages(b, 64, true);
+}
+
+#[bench]
+fn bench_many_messages_0500_loop(b: &mut Bencher) {
+    bench_many_messages(b, 500, false);
+}
+
+#[bench]
+fn bench_many_messages_0500_batched(b: &mut Bencher) {
+    bench_many_messages(b, 500, true);
+}
+
+#[bench]
+fn bench_many_messages_1024_loop(b: &mut Bencher) {
+    bench_many_messages(b, 1024, false);
+}
+
+#[bench]
+fn bench_many_messages_1024_batched(b: &mut Bencher) {
+    bench_many_messages(b, 1024, true);
+}
+
 fn bench_incremental(b: &mut Bencher, len: usize) {
     let mut input = RandomInput::new(b, len);
     b.iter(|| blake3::Hasher::new().update(input.get()).finalize());
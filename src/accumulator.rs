@@ -0,0 +1,684 @@
+//! An append-only log with a running root and Merkle inclusion and consistency proofs.
+//!
+//! An [`Accumulator`] is a Merkle Mountain Range over a sequence of records. Each record is
+//! hashed as a leaf, and the leaves form a binary tree with the same shape as the BLAKE3 chunk
+//! tree: the left subtree of every parent holds the largest power of two leaves that leaves
+//! something on the right. That's also the tree shape of Certificate Transparency
+//! ([RFC 9162](https://datatracker.ietf.org/doc/html/rfc9162#section-2.1)), and the proofs here
+//! follow its algorithms. Like the `cv_stack` inside [`Hasher`](crate::Hasher), appending a
+//! record merges the new leaf with the complete subtrees to its left.
+//!
+//! - [`Accumulator::root`] is the root after the most recent append. Publish it (and the size)
+//!   after each record.
+//! - An [`InclusionProof`] shows that a record is at a given index in a log of a given size.
+//! - A [`ConsistencyProof`] shows that the log with one root is a prefix of the log with another
+//!   root, so nothing was changed or removed in between.
+//!
+//! The accumulator keeps every leaf and complete subtree in memory, about 64 bytes per record,
+//! so that it can prove things about any earlier size. [`to_bytes`](Accumulator::to_bytes)
+//! stores just the leaves.
+//!
+//! # Construction
+//!
+//! All integers below are encoded as 8 little-endian bytes, and `||` is concatenation.
+//!
+//! ```text
+//! leaf = derive_key("BLAKE3 2026-10-15 accumulator leaf v1", record)
+//! node = merge_subtrees_non_root(left, right,
+//!                                DeriveKeyMaterial("BLAKE3 2026-10-15 accumulator node v1"))
+//! root = derive_key("BLAKE3 2026-10-15 accumulator root v1", size || top node)
+//! ```
+//!
+//! The top node is the single leaf for a log of size 1, and it's omitted for the empty log. The
+//! nodes use [`merge_subtrees_non_root`] from the [`hazmat`](crate::hazmat) module, and the root
+//! is a separate finalization that commits to the size of the log.
+//!
+//! # Example
+//!
+//! ```
+//! # fn main() -> Result<(), blake3::accumulator::AccumulatorError> {
+//! use blake3::accumulator::Accumulator;
+//!
+//! let mut log = Accumulator::new();
+//! log.append(b"record 0");
+//! log.append(b"record 1");
+//! let old_root = log.root();
+//! log.append(b"record 2");
+//! let new_root = log.root();
+//!
+//! // Prove that record 1 is in the log.
+//! let proof = log.inclusion_proof(1, log.len())?;
+//! proof.verify(b"record 1", &new_root)?;
+//!
+//! // Prove that the log only grew.
+//! let proof = log.consistency_proof(2, 3)?;
+//! proof.verify(&old_root, &new_root)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::hazmat::{hash_derive_key_context, merge_subtrees_non_root, ChainingValue, Mode};
+use crate::{Hash, OUT_LEN};
+use core::fmt;
+
+const LEAF_CONTEXT: &str = "BLAKE3 2026-10-15 accumulator leaf v1";
+const NODE_CONTEXT: &str = "BLAKE3 2026-10-15 accumulator node v1";
+const ROOT_CONTEXT: &str = "BLAKE3 2026-10-15 accumulator root v1";
+
+fn leaf_hash(record: &[u8]) -> ChainingValue {
+    crate::derive_key(LEAF_CONTEXT, record)
+}
+
+fn root_hash(size: u64, top: Option<&ChainingValue>) -> Hash {
+    let mut hasher = crate::Hasher::new_derive_key(ROOT_CONTEXT);
+    hasher.update(&size.to_le_bytes());
+    if let Some(top) = top {
+        hasher.update(top);
+    }
+    hasher.finalize()
+}
+
+// Parent nodes in all the functions below use this key, from hash_derive_key_context.
+fn merge(node_key: &[u8; OUT_LEN], left: &ChainingValue, right: &ChainingValue) -> ChainingValue {
+    merge_subtrees_non_root(left, right, Mode::DeriveKeyMaterial(node_key))
+}
+
+// The number of leaves in the left subtree of a tree with `size` leaves, which must be at least 2.
+fn left_subtree_size(size: u64) -> u64 {
+    debug_assert!(size >= 2);
+    1 << (63 - (size - 1).leading_zeros())
+}
+
+/// An append-only log of records, with a running root hash.
+///
+/// See the [module level docs](self).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Accumulator {
+    // levels[k][i] is the node covering leaves i*2^k through (i+1)*2^k - 1. Only complete
+    // subtrees are stored, so levels[0] is all the leaves.
+    levels: Vec<Vec<ChainingValue>>,
+}
+
+impl Accumulator {
+    /// Construct a new, empty `Accumulator`.
+    pub fn new() -> Self {
+        Self {
+            levels: vec![Vec::new()],
+        }
+    }
+
+    /// The number of records in the log.
+    pub fn len(&self) -> u64 {
+        self.levels[0].len() as u64
+    }
+
+    /// Whether the log is empty.
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// Append a record to the log, and return its index.
+    pub fn append(&mut self, record: &[u8]) -> u64 {
+        let node_key = hash_derive_key_context(NODE_CONTEXT);
+        self.push_leaf(&node_key, leaf_hash(record))
+    }
+
+    fn push_leaf(&mut self, node_key: &[u8; OUT_LEN], leaf: ChainingValue) -> u64 {
+        let index = self.len();
+        self.levels[0].push(leaf);
+        // Merge complete subtrees upward, the same way Hasher merges its cv_stack.
+        let mut level = 0;
+        while self.levels[level].len() & 1 == 0 {
+            let nodes = &self.levels[level];
+            let parent = merge(node_key, &nodes[nodes.len() - 2], &nodes[nodes.len() - 1]);
+            if self.levels.len() == level + 1 {
+                self.levels.push(Vec::new());
+            }
+            self.levels[level + 1].push(parent);
+            level += 1;
+        }
+        index
+    }
+
+    // The node covering leaves start..end. The left subtree of every node on the way down is
+    // complete, so this is a lookup or a merge along the right edge.
+    fn subtree(&self, node_key: &[u8; OUT_LEN], start: u64, end: u64) -> ChainingValue {
+        let size = end - start;
+        if size.is_power_of_two() {
+            let level = size.trailing_zeros() as usize;
+            return self.levels[level][(start >> level) as usize];
+        }
+        let split = start + left_subtree_size(size);
+        let left = self.subtree(node_key, start, split);
+        let right = self.subtree(node_key, split, end);
+        merge(node_key, &left, &right)
+    }
+
+    fn check_size(&self, size: u64) -> Result<(), AccumulatorError> {
+        if size <= self.len() {
+            Ok(())
+        } else {
+            Err(AccumulatorError(AccumulatorErrorInner::SizeOutOfRange))
+        }
+    }
+
+    /// The root hash of the whole log.
+    pub fn root(&self) -> Hash {
+        // The current size can't be out of range.
+        self.root_at(self.len()).unwrap()
+    }
+
+    /// The root hash of the log as it was when it had `size` records.
+    ///
+    /// Returns an error if `size` is greater than [`len`](Self::len).
+    pub fn root_at(&self, size: u64) -> Result<Hash, AccumulatorError> {
+        self.check_size(size)?;
+        if size == 0 {
+            return Ok(root_hash(0, None));
+        }
+        let node_key = hash_derive_key_context(NODE_CONTEXT);
+        Ok(root_hash(size, Some(&self.subtree(&node_key, 0, size))))
+    }
+
+    /// Prove that record `index` is in the log of size `size`, whose root is
+    /// [`root_at(size)`](Self::root_at).
+    ///
+    /// Returns an error if `size` is greater than [`len`](Self::len), or if `index` isn't less
+    /// than `size`.
+    pub fn inclusion_proof(
+        &self,
+        index: u64,
+        size: u64,
+    ) -> Result<InclusionProof, AccumulatorError> {
+        self.check_size(size)?;
+        if index >= size {
+            return Err(AccumulatorError(AccumulatorErrorInner::IndexOutOfRange));
+        }
+        let node_key = hash_derive_key_context(NODE_CONTEXT);
+        // Walk down from the root, then reverse, so the path goes from the leaf up.
+        let mut path = Vec::new();
+        let (mut start, mut end) = (0, size);
+        while end - start > 1 {
+            let split = start + left_subtree_size(end - start);
+            if index < split {
+                path.push(self.subtree(&node_key, split, end));
+                end = split;
+            } else {
+                path.push(self.subtree(&node_key, start, split));
+                start = split;
+            }
+        }
+        path.reverse();
+        Ok(InclusionProof { index, size, path })
+    }
+
+    /// Prove that the log of size `old_size` is a prefix of the log of size `new_size`.
+    ///
+    /// Returns an error if `new_size` is greater than [`len`](Self::len), or if `old_size` is
+    /// greater than `new_size`.
+    pub fn consistency_proof(
+        &self,
+        old_size: u64,
+        new_size: u64,
+    ) -> Result<ConsistencyProof, AccumulatorError> {
+        self.check_size(new_size)?;
+        if old_size > new_size {
+            return Err(AccumulatorError(AccumulatorErrorInner::SizeOutOfRange));
+        }
+        let mut path = Vec::new();
+        if 0 < old_size && old_size < new_size {
+            // This is SUBPROOF from RFC 9162, except that the old tree's top node is always
+            // included, because the old root doesn't reveal it.
+            let node_key = hash_derive_key_context(NODE_CONTEXT);
+            let (mut m, mut start, mut end) = (old_size, 0, new_size);
+            while m != end - start {
+                let k = left_subtree_size(end - start);
+                if m <= k {
+                    path.push(self.subtree(&node_key, start + k, end));
+                    end = start + k;
+                } else {
+                    path.push(self.subtree(&node_key, start, start + k));
+                    start += k;
+                    m -= k;
+                }
+            }
+            path.push(self.subtree(&node_key, start, end));
+            path.reverse();
+        }
+        Ok(ConsistencyProof {
+            old_size,
+            new_size,
+            path,
+        })
+    }
+
+    /// Decode the state from the format produced by [`to_bytes`](Self::to_bytes).
+    ///
+    /// This rebuilds the tree, so it takes time proportional to the number of records.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AccumulatorError> {
+        let invalid_len = AccumulatorError(AccumulatorErrorInner::InvalidLen(bytes.len()));
+        let header = bytes.get(..8).ok_or(invalid_len.clone())?;
+        let size = u64::from_le_bytes(header.try_into().unwrap());
+        let leaves = &bytes[8..];
+        if leaves.len() as u64 != size.saturating_mul(OUT_LEN as u64) {
+            return Err(invalid_len);
+        }
+        let node_key = hash_derive_key_context(NODE_CONTEXT);
+        let mut accumulator = Self::new();
+        for leaf in leaves.chunks_exact(OUT_LEN) {
+            accumulator.push_leaf(&node_key, leaf.try_into().unwrap());
+        }
+        Ok(accumulator)
+    }
+
+    /// Encode the state as the little-endian number of records (8 bytes), followed by the leaf
+    /// hash of each record (32 bytes each).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + self.levels[0].len() * OUT_LEN);
+        bytes.extend_from_slice(&self.len().to_le_bytes());
+        for leaf in &self.levels[0] {
+            bytes.extend_from_slice(leaf);
+        }
+        bytes
+    }
+}
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn decode_proof(bytes: &[u8]) -> Result<(u64, u64, Vec<ChainingValue>), AccumulatorError> {
+    let invalid_len = AccumulatorError(AccumulatorErrorInner::InvalidLen(bytes.len()));
+    let path_len = bytes.len().saturating_sub(16) / OUT_LEN;
+    if bytes.len() != 16 + path_len * OUT_LEN {
+        return Err(invalid_len);
+    }
+    let first = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+    let second = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+    let path = bytes[16..]
+        .chunks_exact(OUT_LEN)
+        .map(|cv| cv.try_into().unwrap())
+        .collect();
+    Ok((first, second, path))
+}
+
+fn encode_proof(first: u64, second: u64, path: &[ChainingValue]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(16 + path.len() * OUT_LEN);
+    bytes.extend_from_slice(&first.to_le_bytes());
+    bytes.extend_from_slice(&second.to_le_bytes());
+    for cv in path {
+        bytes.extend_from_slice(cv);
+    }
+    bytes
+}
+
+/// A proof that a record is at a given index in a log of a given size.
+///
+/// See the [module level docs](self).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InclusionProof {
+    index: u64,
+    size: u64,
+    // Sibling nodes from the leaf up to the root.
+    path: Vec<ChainingValue>,
+}
+
+impl InclusionProof {
+    /// The index of the record.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// The size of the log that the proof is for.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Check that `record` is at [`index`](Self::index) in the log of [`size`](Self::size)
+    /// records with root hash `root`.
+    pub fn verify(&self, record: &[u8], root: &Hash) -> Result<(), AccumulatorError> {
+        let failed = AccumulatorError(AccumulatorErrorInner::VerificationFailed);
+        if self.index >= self.size {
+            return Err(failed);
+        }
+        // This is the verification algorithm from RFC 9162, section 2.1.3.2.
+        let node_key = hash_derive_key_context(NODE_CONTEXT);
+        let mut f = self.index;
+        let mut s = self.size - 1;
+        let mut node = leaf_hash(record);
+        for sibling in &self.path {
+            if s == 0 {
+                return Err(failed);
+            }
+            if f & 1 == 1 || f == s {
+                node = merge(&node_key, sibling, &node);
+                while f & 1 == 0 && f != 0 {
+                    f >>= 1;
+                    s >>= 1;
+                }
+            } else {
+                node = merge(&node_key, &node, sibling);
+            }
+            f >>= 1;
+            s >>= 1;
+        }
+        // Hash equality is constant-time.
+        if s == 0 && root_hash(self.size, Some(&node)) == *root {
+            Ok(())
+        } else {
+            Err(failed)
+        }
+    }
+
+    /// Decode a proof from the format produced by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AccumulatorError> {
+        let (index, size, path) = decode_proof(bytes)?;
+        Ok(Self { index, size, path })
+    }
+
+    /// Encode the proof as the little-endian index (8 bytes), the little-endian size (8 bytes),
+    /// and the sibling nodes from the leaf up to the root (32 bytes each).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_proof(self.index, self.size, &self.path)
+    }
+}
+
+/// A proof that one log is a prefix of another.
+///
+/// See the [module level docs](self).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsistencyProof {
+    old_size: u64,
+    new_size: u64,
+    path: Vec<ChainingValue>,
+}
+
+impl ConsistencyProof {
+    /// The size of the older log.
+    pub fn old_size(&self) -> u64 {
+        self.old_size
+    }
+
+    /// The size of the newer log.
+    pub fn new_size(&self) -> u64 {
+        self.new_size
+    }
+
+    /// Check that the log of [`old_size`](Self::old_size) records with root hash `old_root` is a
+    /// prefix of the log of [`new_size`](Self::new_size) records with root hash `new_root`.
+    pub fn verify(&self, old_root: &Hash, new_root: &Hash) -> Result<(), AccumulatorError> {
+        let failed = AccumulatorError(AccumulatorErrorInner::VerificationFailed);
+        if self.old_size > self.new_size {
+            return Err(failed);
+        }
+        if self.old_size == 0 || self.old_size == self.new_size {
+            // Every log extends the empty log, and a log only extends itself if the roots match.
+            let expected_old_root = if self.old_size == 0 {
+                root_hash(0, None)
+            } else {
+                *new_root
+            };
+            // Hash equality is constant-time.
+            return if self.path.is_empty() && *old_root == expected_old_root {
+                Ok(())
+            } else {
+                Err(failed)
+            };
+        }
+        // This is the verification algorithm from RFC 9162, section 2.1.4.2, except that the
+        // old tree's top node is always the first element of the path.
+        let (first, rest) = self.path.split_first().ok_or(failed.clone())?;
+        let node_key = hash_derive_key_context(NODE_CONTEXT);
+        let mut f = self.old_size - 1;
+        let mut s = self.new_size - 1;
+        while f & 1 == 1 {
+            f >>= 1;
+            s >>= 1;
+        }
+        let mut old_node = *first;
+        let mut new_node = *first;
+        for c in rest {
+            if s == 0 {
+                return Err(failed);
+            }
+            if f & 1 == 1 || f == s {
+                old_node = merge(&node_key, c, &old_node);
+                new_node = merge(&node_key, c, &new_node);
+                while f & 1 == 0 && f != 0 {
+                    f >>= 1;
+                    s >>= 1;
+                }
+            } else {
+                new_node = merge(&node_key, &new_node, c);
+            }
+            f >>= 1;
+            s >>= 1;
+        }
+        // Hash equality is constant-time.
+        if s == 0
+            && root_hash(self.old_size, Some(&old_node)) == *old_root
+            && root_hash(self.new_size, Some(&new_node)) == *new_root
+        {
+            Ok(())
+        } else {
+            Err(failed)
+        }
+    }
+
+    /// Decode a proof from the format produced by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AccumulatorError> {
+        let (old_size, new_size, path) = decode_proof(bytes)?;
+        Ok(Self {
+            old_size,
+            new_size,
+            path,
+        })
+    }
+
+    /// Encode the proof as the little-endian old size (8 bytes), the little-endian new size (8
+    /// bytes), and the nodes of the proof (32 bytes each).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_proof(self.old_size, self.new_size, &self.path)
+    }
+}
+
+/// The error type for [`Accumulator`] proofs and decoding.
+///
+/// The `.to_string()` representation of this error currently distinguishes between the different
+/// kinds of errors. This is to help with logging and debugging, but it isn't a stable API detail,
+/// and it may change at any time.
+#[derive(Clone, Debug)]
+pub struct AccumulatorError(AccumulatorErrorInner);
+
+#[derive(Clone, Debug)]
+enum AccumulatorErrorInner {
+    SizeOutOfRange,
+    IndexOutOfRange,
+    InvalidLen(usize),
+    VerificationFailed,
+}
+
+impl fmt::Display for AccumulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            AccumulatorErrorInner::SizeOutOfRange => write!(f, "log size out of range"),
+            AccumulatorErrorInner::IndexOutOfRange => write!(f, "record index out of range"),
+            AccumulatorErrorInner::InvalidLen(len) => write!(f, "invalid encoding length: {}", len),
+            AccumulatorErrorInner::VerificationFailed => write!(f, "proof verification failed"),
+        }
+    }
+}
+
+impl std::error::Error for AccumulatorError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MAX_TEST_SIZE: u64 = 20;
+
+    fn test_log(size: u64) -> (Accumulator, Vec<Vec<u8>>) {
+        let mut log = Accumulator::new();
+        let mut records = Vec::new();
+        for i in 0..size {
+            let record = format!("record {}", i).into_bytes();
+            assert_eq!(log.append(&record), i);
+            records.push(record);
+        }
+        (log, records)
+    }
+
+    // The top node as RFC 9162 defines it, recursively.
+    fn naive_top_node(leaves: &[ChainingValue]) -> ChainingValue {
+        if leaves.len() == 1 {
+            return leaves[0];
+        }
+        let k = left_subtree_size(leaves.len() as u64) as usize;
+        let node_key = hash_derive_key_context(NODE_CONTEXT);
+        merge(
+            &node_key,
+            &naive_top_node(&leaves[..k]),
+            &naive_top_node(&leaves[k..]),
+        )
+    }
+
+    #[test]
+    fn test_roots() {
+        let (log, records) = test_log(MAX_TEST_SIZE);
+        let leaves: Vec<_> = records.iter().map(|r| leaf_hash(r)).collect();
+        assert_eq!(log.root_at(0).unwrap(), root_hash(0, None));
+        for size in 1..=MAX_TEST_SIZE {
+            let top = naive_top_node(&leaves[..size as usize]);
+            assert_eq!(log.root_at(size).unwrap(), root_hash(size, Some(&top)));
+            // Appending one record at a time gives the same roots.
+            assert_eq!(test_log(size).0.root(), log.root_at(size).unwrap());
+        }
+        assert!(log.root_at(MAX_TEST_SIZE + 1).is_err());
+        assert!(Accumulator::new().is_empty());
+        assert_eq!(log.len(), MAX_TEST_SIZE);
+    }
+
+    // Recompute the empty and one-record roots with the reference implementation.
+    #[test]
+    fn test_compare_reference_impl() {
+        let (log, _) = test_log(1);
+        let mut leaf = [0; OUT_LEN];
+        let mut reference = reference_impl::Hasher::new_derive_key(LEAF_CONTEXT);
+        reference.update(b"record 0");
+        reference.finalize(&mut leaf);
+        for (size, top) in [(0u64, &[][..]), (1, &leaf[..])] {
+            let mut expected = [0; OUT_LEN];
+            let mut reference = reference_impl::Hasher::new_derive_key(ROOT_CONTEXT);
+            reference.update(&size.to_le_bytes());
+            reference.update(top);
+            reference.finalize(&mut expected);
+            assert_eq!(log.root_at(size).unwrap(), expected);
+        }
+    }
+
+    // A five-record root, which has parent nodes at two levels.
+    #[test]
+    fn test_five_record_root() {
+        let (log, _) = test_log(5);
+        assert_eq!(log.root().to_hex().as_str(), FIVE_ROOT_VECTOR);
+    }
+
+    const FIVE_ROOT_VECTOR: &str =
+        "90c92528d1b680f5054e8ecb775b6e10b87afc41fcc117b182e5e82ffd9036a4";
+
+    #[test]
+    fn test_inclusion_proofs() {
+        let (log, records) = test_log(MAX_TEST_SIZE);
+        for size in 1..=MAX_TEST_SIZE {
+            let root = log.root_at(size).unwrap();
+            for index in 0..size {
+                let proof = log.inclusion_proof(index, size).unwrap();
+                assert_eq!(proof.index(), index);
+                assert_eq!(proof.size(), size);
+                let record = &records[index as usize];
+                proof.verify(record, &root).unwrap();
+                let decoded = InclusionProof::from_bytes(&proof.to_bytes()).unwrap();
+                assert_eq!(decoded, proof);
+
+                // The wrong record, root, or position fails.
+                assert!(proof.verify(b"wrong", &root).is_err());
+                assert!(proof
+                    .verify(record, &log.root_at(size - 1).unwrap())
+                    .is_err());
+                for &(other_index, other_size) in &[(index + 1, size), (index, size + 1)] {
+                    let moved = InclusionProof {
+                        index: other_index,
+                        size: other_size,
+                        path: proof.path.clone(),
+                    };
+                    assert!(moved.verify(record, &root).is_err());
+                }
+                // A corrupt path fails.
+                for i in 0..proof.path.len() {
+                    let mut corrupt = proof.clone();
+                    corrupt.path[i][0] ^= 1;
+                    assert!(corrupt.verify(record, &root).is_err());
+                }
+                let mut extended = proof.clone();
+                extended.path.push([0; OUT_LEN]);
+                assert!(extended.verify(record, &root).is_err());
+            }
+        }
+        assert!(log.inclusion_proof(5, 5).is_err());
+        assert!(log.inclusion_proof(0, MAX_TEST_SIZE + 1).is_err());
+    }
+
+    #[test]
+    fn test_consistency_proofs() {
+        let (log, _) = test_log(MAX_TEST_SIZE);
+        for new_size in 0..=MAX_TEST_SIZE {
+            let new_root = log.root_at(new_size).unwrap();
+            for old_size in 0..=new_size {
+                let old_root = log.root_at(old_size).unwrap();
+                let proof = log.consistency_proof(old_size, new_size).unwrap();
+                assert_eq!(proof.old_size(), old_size);
+                assert_eq!(proof.new_size(), new_size);
+                proof.verify(&old_root, &new_root).unwrap();
+                let decoded = ConsistencyProof::from_bytes(&proof.to_bytes()).unwrap();
+                assert_eq!(decoded, proof);
+
+                // Roots from a different log fail. (The empty log is a prefix of every log.)
+                if old_size > 0 {
+                    let other_root = test_log(new_size + 1).0.root_at(new_size + 1).unwrap();
+                    assert!(proof.verify(&old_root, &other_root).is_err());
+                    assert!(proof.verify(&other_root, &new_root).is_err());
+                }
+                // A corrupt path fails.
+                for i in 0..proof.path.len() {
+                    let mut corrupt = proof.clone();
+                    corrupt.path[i][0] ^= 1;
+                    assert!(corrupt.verify(&old_root, &new_root).is_err());
+                }
+            }
+        }
+        assert!(log.consistency_proof(3, 2).is_err());
+        assert!(log.consistency_proof(0, MAX_TEST_SIZE + 1).is_err());
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let (log, _) = test_log(MAX_TEST_SIZE);
+        let bytes = log.to_bytes();
+        assert_eq!(bytes.len(), 8 + MAX_TEST_SIZE as usize * OUT_LEN);
+        let decoded = Accumulator::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, log);
+        assert_eq!(decoded.root(), log.root());
+
+        assert!(Accumulator::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Accumulator::from_bytes(&bytes[..7]).is_err());
+        let mut wrong_size = bytes.clone();
+        wrong_size[0] += 1;
+        assert!(Accumulator::from_bytes(&wrong_size).is_err());
+        assert_eq!(
+            Accumulator::from_bytes(&Accumulator::new().to_bytes()).unwrap(),
+            Accumulator::new(),
+        );
+    }
+}
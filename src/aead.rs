@@ -0,0 +1,314 @@
+//! Experimental authenticated encryption with associated data, using only BLAKE3.
+//!
+//! This is an encrypt-then-MAC construction, built from the [`stream`](crate::stream) and
+//! [`mac`](crate::mac) modules. It's meant for applications that already ship BLAKE3 and would
+//! rather not add a second primitive. It hasn't had the analysis that standard AEADs like
+//! ChaCha20-Poly1305 have had, and this crate makes no SemVer guarantees for it. In particular,
+//! the construction might change in a patch release, which would make existing ciphertexts
+//! undecryptable. Don't use it for data at rest that you can't re-encrypt.
+//!
+//! # Construction
+//!
+//! All integers below are encoded as 8 little-endian bytes, and `||` is concatenation.
+//!
+//! ```text
+//! enc_key    = derive_key("BLAKE3 2026-10-15 aead encryption key v1", key)
+//! mac_key    = derive_key("BLAKE3 2026-10-15 aead authentication key v1", key)
+//! ciphertext = plaintext XOR (XOF of keyed_hash(enc_key, nonce))
+//! tag        = keyed_hash(mac_key, nonce || ad || ciphertext || len(ad) || len(ciphertext))
+//! ```
+//!
+//! Decryption checks the tag in constant time before decrypting anything. The nonce is 24 bytes,
+//! which is long enough to be chosen at random for every message. **Never use the same key and
+//! nonce for two different messages.** The tag is 32 bytes.
+//!
+//! This module requires the `aead-preview` Cargo feature. With the `aead-traits-preview` feature,
+//! [`AeadKey`] also implements the `AeadInPlace` trait from the RustCrypto
+//! [`aead`](https://crates.io/crates/aead) crate.
+//!
+//! # Example
+//!
+//! ```
+//! # fn main() -> Result<(), blake3::aead::AeadError> {
+//! use blake3::aead::AeadKey;
+//!
+//! let key = AeadKey::new(&[42; 32]);
+//! # let random_nonce = [7; 24];
+//! let mut message = *b"attack at dawn";
+//! let tag = key.seal_in_place(&random_nonce, b"header", &mut message);
+//! key.open_in_place(&random_nonce, b"header", &mut message, &tag)?;
+//! assert_eq!(&message, b"attack at dawn");
+//!
+//! // Tampering with the associated data (or the ciphertext, or the tag) fails.
+//! let tag = key.seal_in_place(&random_nonce, b"header", &mut message);
+//! assert!(key.open_in_place(&random_nonce, b"HEADER", &mut message, &tag).is_err());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::mac::Mac;
+use crate::stream::KeyStream;
+use crate::{KEY_LEN, OUT_LEN};
+use core::fmt;
+
+pub use crate::stream::NONCE_LEN;
+
+/// The number of bytes in an authentication tag, 32.
+pub const TAG_LEN: usize = OUT_LEN;
+
+const ENCRYPTION_KEY_CONTEXT: &str = "BLAKE3 2026-10-15 aead encryption key v1";
+const AUTHENTICATION_KEY_CONTEXT: &str = "BLAKE3 2026-10-15 aead authentication key v1";
+
+/// A key for authenticated encryption, holding the derived encryption and authentication subkeys.
+///
+/// See the [module level docs](self) for the construction and the security requirements.
+#[derive(Clone)]
+pub struct AeadKey {
+    encryption_key: [u8; KEY_LEN],
+    authentication_key: [u8; KEY_LEN],
+}
+
+impl AeadKey {
+    /// Derive the encryption and authentication subkeys from a 32-byte key.
+    pub fn new(key: &[u8; KEY_LEN]) -> Self {
+        Self {
+            encryption_key: crate::derive_key(ENCRYPTION_KEY_CONTEXT, key),
+            authentication_key: crate::derive_key(AUTHENTICATION_KEY_CONTEXT, key),
+        }
+    }
+
+    fn mac(&self, nonce: &[u8; NONCE_LEN], associated_data: &[u8], ciphertext: &[u8]) -> Mac {
+        let mut mac = Mac::new(&self.authentication_key);
+        mac.update(nonce);
+        mac.update(associated_data);
+        mac.update(ciphertext);
+        mac.update(&(associated_data.len() as u64).to_le_bytes());
+        mac.update(&(ciphertext.len() as u64).to_le_bytes());
+        mac
+    }
+
+    /// Encrypt `buffer` in place and return the authentication tag.
+    pub fn seal_in_place(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> [u8; TAG_LEN] {
+        KeyStream::from_subkey(&self.encryption_key, nonce).apply_keystream(buffer);
+        self.mac(nonce, associated_data, buffer).finalize().into()
+    }
+
+    /// Check the authentication tag and decrypt `buffer` in place.
+    ///
+    /// If the tag doesn't match, this returns an error and leaves `buffer` unmodified.
+    pub fn open_in_place(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8; TAG_LEN],
+    ) -> Result<(), AeadError> {
+        self.mac(nonce, associated_data, buffer)
+            .verify(tag)
+            .map_err(|_| AeadError(()))?;
+        KeyStream::from_subkey(&self.encryption_key, nonce).apply_keystream(buffer);
+        Ok(())
+    }
+
+    /// Encrypt `plaintext` and return the ciphertext with the tag appended.
+    ///
+    /// This method requires the `std` Cargo feature, which is enabled by default.
+    #[cfg(feature = "std")]
+    pub fn seal(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        associated_data: &[u8],
+        plaintext: &[u8],
+    ) -> std::vec::Vec<u8> {
+        let mut output = std::vec::Vec::with_capacity(plaintext.len() + TAG_LEN);
+        output.extend_from_slice(plaintext);
+        let tag = self.seal_in_place(nonce, associated_data, &mut output);
+        output.extend_from_slice(&tag);
+        output
+    }
+
+    /// Check and decrypt a ciphertext with the tag appended, as returned by
+    /// [`seal`](Self::seal), and return the plaintext.
+    ///
+    /// This method requires the `std` Cargo feature, which is enabled by default.
+    #[cfg(feature = "std")]
+    pub fn open(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        associated_data: &[u8],
+        ciphertext_and_tag: &[u8],
+    ) -> Result<std::vec::Vec<u8>, AeadError> {
+        if ciphertext_and_tag.len() < TAG_LEN {
+            return Err(AeadError(()));
+        }
+        let (ciphertext, tag) = ciphertext_and_tag.split_at(ciphertext_and_tag.len() - TAG_LEN);
+        let mut output = ciphertext.to_vec();
+        self.open_in_place(nonce, associated_data, &mut output, tag.try_into().unwrap())?;
+        Ok(output)
+    }
+}
+
+// Don't derive(Debug), because the keys are secret.
+impl fmt::Debug for AeadKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AeadKey").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for AeadKey {
+    fn zeroize(&mut self) {
+        // Destructuring to trigger compile error as a reminder to update this impl.
+        let Self {
+            encryption_key,
+            authentication_key,
+        } = self;
+
+        encryption_key.zeroize();
+        authentication_key.zeroize();
+    }
+}
+
+/// The error type for [`AeadKey::open_in_place`] and [`AeadKey::open`].
+///
+/// This error deliberately carries no detail about why decryption failed.
+#[derive(Clone, Debug)]
+pub struct AeadError(());
+
+impl fmt::Display for AeadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("AEAD decryption failed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AeadError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{paint_test_input, TEST_KEY};
+    use crate::Hasher;
+
+    const TEST_NONCE: [u8; NONCE_LEN] = *b"twenty four byte nonce!!";
+
+    // Recompute the construction from its parts.
+    #[test]
+    fn test_construction() {
+        let mut plaintext = [0; 3000];
+        paint_test_input(&mut plaintext);
+        let associated_data = b"associated data";
+
+        let enc_key = crate::derive_key(ENCRYPTION_KEY_CONTEXT, &TEST_KEY);
+        let mac_key = crate::derive_key(AUTHENTICATION_KEY_CONTEXT, &TEST_KEY);
+        let mut expected_ciphertext = [0; 3000];
+        Hasher::new_keyed(&enc_key)
+            .update(&TEST_NONCE)
+            .finalize_xof()
+            .fill(&mut expected_ciphertext);
+        for (c, p) in expected_ciphertext.iter_mut().zip(plaintext.iter()) {
+            *c ^= *p;
+        }
+        let expected_tag = Hasher::new_keyed(&mac_key)
+            .update(&TEST_NONCE)
+            .update(associated_data)
+            .update(&expected_ciphertext)
+            .update(&(associated_data.len() as u64).to_le_bytes())
+            .update(&3000u64.to_le_bytes())
+            .finalize();
+
+        let key = AeadKey::new(&TEST_KEY);
+        let mut buffer = plaintext;
+        let tag = key.seal_in_place(&TEST_NONCE, associated_data, &mut buffer);
+        assert_eq!(buffer[..], expected_ciphertext[..]);
+        assert_eq!(expected_tag, tag);
+
+        key.open_in_place(&TEST_NONCE, associated_data, &mut buffer, &tag)
+            .unwrap();
+        assert_eq!(buffer[..], plaintext[..]);
+    }
+
+    // Pin down the construction, so that any change to it shows up here.
+    #[test]
+    fn test_vector() {
+        let key = AeadKey::new(&TEST_KEY);
+        let mut buffer = *b"hello world";
+        let tag = key.seal_in_place(&TEST_NONCE, b"ad", &mut buffer);
+        assert_eq!(crate::Hash::from_hex(TEST_VECTOR_TAG).unwrap(), tag);
+        let mut ciphertext_hex = arrayvec::ArrayString::<22>::new();
+        for byte in buffer {
+            core::fmt::Write::write_fmt(&mut ciphertext_hex, format_args!("{:02x}", byte)).unwrap();
+        }
+        assert_eq!(ciphertext_hex.as_str(), TEST_VECTOR_CIPHERTEXT);
+    }
+
+    const TEST_VECTOR_TAG: &str =
+        "e4703fa288467c3d25d93abe5bff56d4fe067b3c5b7e8569a5f205500e75a074";
+    const TEST_VECTOR_CIPHERTEXT: &str = "e0e19db91018e8922058fc";
+
+    #[test]
+    fn test_open_rejects_tampering() {
+        let key = AeadKey::new(&TEST_KEY);
+        let mut plaintext = [0; 200];
+        paint_test_input(&mut plaintext);
+        let mut ciphertext = plaintext;
+        let tag = key.seal_in_place(&TEST_NONCE, b"ad", &mut ciphertext);
+
+        let check_rejected =
+            |nonce: &[u8; NONCE_LEN], ad: &[u8], ct: &[u8], tag: &[u8; TAG_LEN]| {
+                let mut buffer = [0; 201];
+                let buffer = &mut buffer[..ct.len()];
+                buffer.copy_from_slice(ct);
+                assert!(key.open_in_place(nonce, ad, buffer, tag).is_err());
+                // A failed open leaves the buffer alone.
+                assert_eq!(buffer, ct);
+            };
+
+        let mut bad_nonce = TEST_NONCE;
+        bad_nonce[0] ^= 1;
+        check_rejected(&bad_nonce, b"ad", &ciphertext, &tag);
+        check_rejected(&TEST_NONCE, b"aD", &ciphertext, &tag);
+        check_rejected(&TEST_NONCE, b"", &ciphertext, &tag);
+        check_rejected(&TEST_NONCE, b"ad", &ciphertext[..199], &tag);
+        let mut bad_ciphertext = ciphertext;
+        bad_ciphertext[100] ^= 1;
+        check_rejected(&TEST_NONCE, b"ad", &bad_ciphertext, &tag);
+        let mut bad_tag = tag;
+        bad_tag[31] ^= 1;
+        check_rejected(&TEST_NONCE, b"ad", &ciphertext, &bad_tag);
+        let wrong_key = AeadKey::new(&[0; KEY_LEN]);
+        let mut buffer = ciphertext;
+        assert!(wrong_key
+            .open_in_place(&TEST_NONCE, b"ad", &mut buffer, &tag)
+            .is_err());
+
+        // Moving bytes from the associated data into the ciphertext changes the tag.
+        let mut moved = [0; 201];
+        moved[0] = b'd';
+        moved[1..].copy_from_slice(&ciphertext);
+        check_rejected(&TEST_NONCE, b"a", &moved, &tag);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_seal_open() {
+        let key = AeadKey::new(&TEST_KEY);
+        for &len in &[0, 1, 64, 1000] {
+            let mut plaintext = vec![0; len];
+            paint_test_input(&mut plaintext);
+            let sealed = key.seal(&TEST_NONCE, b"ad", &plaintext);
+            assert_eq!(sealed.len(), len + TAG_LEN);
+            assert_eq!(key.open(&TEST_NONCE, b"ad", &sealed).unwrap(), plaintext);
+
+            let mut tampered = sealed.clone();
+            tampered[len / 2] ^= 1;
+            assert!(key.open(&TEST_NONCE, b"ad", &tampered).is_err());
+        }
+        assert!(key.open(&TEST_NONCE, b"ad", &[0; TAG_LEN - 1]).is_err());
+    }
+}
@@ -0,0 +1,524 @@
+//! Content-defined chunking, hashing each chunk in the same pass.
+//!
+//! Deduplicating storage splits its input into chunks and stores each distinct chunk once, keyed
+//! by its hash. Cutting at fixed offsets works badly for that, because inserting one byte near
+//! the start shifts every chunk after it. Content-defined chunking instead picks cut points based
+//! on the bytes around them, so after an edit, the chunk boundaries fall back into line with the
+//! old ones, and only the chunks near the edit change.
+//!
+//! A [`Chunker`] finds cut points with [FastCDC], a rolling "gear" hash with normalized chunking.
+//! [`Chunker::chunks`] reads from any [`Read`] into a large buffer, and yields a [`Chunk`]
+//! (offset, length, and BLAKE3 [`Hash`](struct@Hash)) for each chunk, without reading the input
+//! twice. Chunks found in the same buffer are hashed together with
+//! [`hash_many`](crate::hash_many), but that only batches chunks of up to 1 KiB into SIMD lanes,
+//! so it only helps when `min_size` is under 1 KiB. With the default sizes, each chunk is hashed
+//! on its own, which still uses SIMD across the 1 KiB pieces within the chunk.
+//!
+//! Chunk boundaries depend only on the content and the size parameters. They don't depend on how
+//! the reader splits up its reads. The table of gear hash constants is fixed, but it isn't
+//! compatible with other FastCDC implementations.
+//!
+//! This module requires the `std` Cargo feature.
+//!
+//! [FastCDC]: https://www.usenix.org/conference/atc16/technical-sessions/presentation/xia
+//! [`Read`]: std::io::Read
+//!
+//! # Example
+//!
+//! ```
+//! # fn main() -> std::io::Result<()> {
+//! use blake3::cdc::Chunker;
+//! use std::collections::HashSet;
+//!
+//! # let mut input = vec![0; 1_000_000];
+//! # blake3::Hasher::new().finalize_xof().fill(&mut input);
+//! let mut seen = HashSet::new();
+//! let mut new_bytes = 0;
+//! for chunk in Chunker::new().chunks(&input[..]) {
+//!     let chunk = chunk?;
+//!     assert_eq!(chunk.hash, blake3::hash(&input[chunk.offset as usize..][..chunk.len]));
+//!     if seen.insert(chunk.hash) {
+//!         new_bytes += chunk.len;
+//!     }
+//! }
+//! assert_eq!(new_bytes, input.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::Hash;
+use core::cmp;
+use core::fmt;
+use std::collections::VecDeque;
+use std::io::Read;
+
+/// The default minimum chunk size, 2 KiB.
+pub const DEFAULT_MIN_SIZE: usize = 2 * 1024;
+
+/// The default average chunk size, 8 KiB.
+pub const DEFAULT_AVG_SIZE: usize = 8 * 1024;
+
+/// The default maximum chunk size, 64 KiB.
+pub const DEFAULT_MAX_SIZE: usize = 64 * 1024;
+
+// The limits on the size parameters.
+const MIN_MIN_SIZE: usize = 64;
+const MAX_MAX_SIZE: usize = 1 << 30;
+
+// The read buffer holds at least two maximum-size chunks, so that each refill makes progress, and
+// at least this many bytes, so that small chunks are hashed in large batches.
+const MIN_BUF_LEN: usize = 1 << 20;
+
+// The gear hash constants, from the SplitMix64 generator with a seed of 0.
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0; 256];
+    let mut state: u64 = 0;
+    let mut i = 0;
+    while i < table.len() {
+        state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+// A mask of the top `bits` bits. The gear hash mixes the most input into its high bits.
+fn top_bits_mask(bits: u32) -> u64 {
+    !0 << (64 - bits)
+}
+
+/// A FastCDC content-defined chunker with given minimum, average, and maximum chunk sizes.
+///
+/// See the [module level docs](self).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chunker {
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
+    // A harder mask before the average size and an easier one after it, so that chunk sizes
+    // cluster around the average. This is FastCDC's normalized chunking, at level 2.
+    mask_small: u64,
+    mask_large: u64,
+}
+
+impl Chunker {
+    /// Construct a `Chunker` with the default sizes: [`DEFAULT_MIN_SIZE`], [`DEFAULT_AVG_SIZE`],
+    /// and [`DEFAULT_MAX_SIZE`].
+    pub fn new() -> Self {
+        Self::with_sizes(DEFAULT_MIN_SIZE, DEFAULT_AVG_SIZE, DEFAULT_MAX_SIZE).unwrap()
+    }
+
+    /// Construct a `Chunker` with the given minimum, average, and maximum chunk sizes.
+    ///
+    /// Returns an error unless `min_size <= avg_size <= max_size`, the average size is a power of
+    /// two, the minimum size is at least 64 bytes, and the maximum size is at most 1 GiB.
+    pub fn with_sizes(min_size: usize, avg_size: usize, max_size: usize) -> Result<Self, CdcError> {
+        if min_size < MIN_MIN_SIZE
+            || min_size > avg_size
+            || avg_size > max_size
+            || max_size > MAX_MAX_SIZE
+            || !avg_size.is_power_of_two()
+        {
+            return Err(CdcError(()));
+        }
+        let avg_bits = avg_size.trailing_zeros();
+        Ok(Self {
+            min_size,
+            avg_size,
+            max_size,
+            mask_small: top_bits_mask(avg_bits + 2),
+            mask_large: top_bits_mask(avg_bits - 2),
+        })
+    }
+
+    /// The minimum chunk size. Only the final chunk can be smaller.
+    pub fn min_size(&self) -> usize {
+        self.min_size
+    }
+
+    /// The target average chunk size.
+    pub fn avg_size(&self) -> usize {
+        self.avg_size
+    }
+
+    /// The maximum chunk size.
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    // The length of the first chunk of `data`. When more input might follow, `data` must be at
+    // least max_size bytes long, so that the cut point doesn't depend on buffering.
+    fn cut(&self, data: &[u8]) -> usize {
+        if data.len() <= self.min_size {
+            return data.len();
+        }
+        let end = cmp::min(data.len(), self.max_size);
+        let normal = cmp::min(end, self.avg_size);
+        let mut hash: u64 = 0;
+        // Cut-point skipping: the bytes before min_size don't affect the hash.
+        let mut i = self.min_size;
+        while i < normal {
+            hash = (hash << 1).wrapping_add(GEAR[data[i] as usize]);
+            if hash & self.mask_small == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        while i < end {
+            hash = (hash << 1).wrapping_add(GEAR[data[i] as usize]);
+            if hash & self.mask_large == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        end
+    }
+
+    /// Return an iterator over the chunks of `reader` and their hashes.
+    ///
+    /// The iterator yields an error if the reader does, and then stops. Errors of kind
+    /// [`Interrupted`](std::io::ErrorKind::Interrupted) are retried.
+    pub fn chunks<R: Read>(&self, reader: R) -> Chunks<R> {
+        Chunks {
+            chunker: *self,
+            reader: Some(reader),
+            buf: vec![0; buf_len(self.max_size)],
+            start: 0,
+            end: 0,
+            offset: 0,
+            ready: VecDeque::new(),
+        }
+    }
+}
+
+// If twice the maximum size is too big to allocate, which is only possible on 32-bit targets, one
+// maximum-size chunk is still enough to make progress.
+fn buf_len(max_size: usize) -> usize {
+    match max_size.checked_mul(2) {
+        Some(len) if len <= isize::MAX as usize => cmp::max(len, MIN_BUF_LEN),
+        _ => max_size,
+    }
+}
+
+impl Default for Chunker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A content-defined chunk, from [`Chunks`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chunk {
+    /// The position of the chunk in the input.
+    pub offset: u64,
+    /// The length of the chunk in bytes.
+    pub len: usize,
+    /// The BLAKE3 hash of the chunk.
+    pub hash: Hash,
+}
+
+/// An iterator over the chunks of a reader, returned by [`Chunker::chunks`].
+#[derive(Debug)]
+pub struct Chunks<R> {
+    chunker: Chunker,
+    // None after EOF or an error.
+    reader: Option<R>,
+    buf: Vec<u8>,
+    // The bytes of buf[start..end] have been read but not chunked yet.
+    start: usize,
+    end: usize,
+    // The input offset of buf[start].
+    offset: u64,
+    ready: VecDeque<Chunk>,
+}
+
+impl<R: Read> Chunks<R> {
+    // Move the leftover bytes to the front of the buffer, and fill the rest of it. Clear the
+    // reader at EOF.
+    fn fill_buf(&mut self) -> std::io::Result<()> {
+        self.buf.copy_within(self.start..self.end, 0);
+        self.end -= self.start;
+        self.start = 0;
+        while let Some(reader) = &mut self.reader {
+            if self.end == self.buf.len() {
+                break;
+            }
+            match reader.read(&mut self.buf[self.end..]) {
+                Ok(0) => self.reader = None,
+                Ok(n) => self.end += n,
+                // see test_update_reader_interrupted
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    // Drop the leftover bytes too, so that iteration stops here.
+                    self.reader = None;
+                    self.end = 0;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Cut as many chunks as possible from a full buffer, and hash them all together.
+    fn cut_chunks(&mut self) {
+        let eof = self.reader.is_none();
+        let mut ranges = Vec::new();
+        while self.start < self.end && (eof || self.end - self.start >= self.chunker.max_size) {
+            let len = self.chunker.cut(&self.buf[self.start..self.end]);
+            ranges.push(self.start..self.start + len);
+            self.start += len;
+        }
+        let inputs: Vec<&[u8]> = ranges.iter().map(|r| &self.buf[r.clone()]).collect();
+        let hashes = crate::hash_many(&inputs);
+        for (range, hash) in ranges.into_iter().zip(hashes) {
+            self.ready.push_back(Chunk {
+                offset: self.offset,
+                len: range.len(),
+                hash,
+            });
+            self.offset += range.len() as u64;
+        }
+    }
+}
+
+impl<R: Read> Iterator for Chunks<R> {
+    type Item = std::io::Result<Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.ready.is_empty() {
+            if self.reader.is_none() && self.start == self.end {
+                return None;
+            }
+            if let Err(e) = self.fill_buf() {
+                return Some(Err(e));
+            }
+            self.cut_chunks();
+        }
+        self.ready.pop_front().map(Ok)
+    }
+}
+
+/// The error type for [`Chunker::with_sizes`].
+#[derive(Clone, Debug)]
+pub struct CdcError(());
+
+impl fmt::Display for CdcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid chunk size parameters")
+    }
+}
+
+impl std::error::Error for CdcError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn random_input(len: usize) -> Vec<u8> {
+        let mut input = vec![0; len];
+        crate::Hasher::new().finalize_xof().fill(&mut input);
+        input
+    }
+
+    fn chunk_all(chunker: &Chunker, reader: impl Read) -> Vec<Chunk> {
+        chunker.chunks(reader).map(|chunk| chunk.unwrap()).collect()
+    }
+
+    // A reader that returns at most `max_read` bytes at a time, and is sometimes interrupted.
+    struct SlowReader<'a> {
+        input: &'a [u8],
+        max_read: usize,
+        interrupt: bool,
+    }
+
+    impl Read for SlowReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(std::io::ErrorKind::Interrupted.into());
+            }
+            let n = cmp::min(cmp::min(buf.len(), self.max_read), self.input.len());
+            buf[..n].copy_from_slice(&self.input[..n]);
+            self.input = &self.input[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_chunks_cover_input() {
+        let input = random_input(1_000_000);
+        let chunker = Chunker::new();
+        let chunks = chunk_all(&chunker, &input[..]);
+        let mut offset = 0;
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert_eq!(chunk.offset, offset as u64);
+            assert!(chunk.len <= DEFAULT_MAX_SIZE);
+            if i < chunks.len() - 1 {
+                assert!(chunk.len >= DEFAULT_MIN_SIZE);
+            }
+            assert_eq!(chunk.hash, crate::hash(&input[offset..][..chunk.len]));
+            offset += chunk.len;
+        }
+        assert_eq!(offset, input.len());
+
+        // The sizes are about right on average, without too many maximum-size chunks.
+        let avg = input.len() / chunks.len();
+        assert!(
+            avg > DEFAULT_AVG_SIZE / 2 && avg < DEFAULT_AVG_SIZE * 2,
+            "{}",
+            avg
+        );
+        let max_count = chunks.iter().filter(|c| c.len == DEFAULT_MAX_SIZE).count();
+        assert!(max_count < chunks.len() / 10);
+
+        assert!(chunk_all(&chunker, &[][..]).is_empty());
+        let short = chunk_all(&chunker, &input[..100]);
+        assert_eq!(short.len(), 1);
+        assert_eq!(short[0].hash, crate::hash(&input[..100]));
+    }
+
+    #[test]
+    fn test_boundaries_dont_depend_on_reads() {
+        let input = random_input(300_000);
+        let chunker = Chunker::with_sizes(256, 1024, 4096).unwrap();
+        let expected = chunk_all(&chunker, &input[..]);
+        for &max_read in &[1, 100, 4095, 4097, 100_000] {
+            let reader = SlowReader {
+                input: &input,
+                max_read,
+                interrupt: false,
+            };
+            assert_eq!(chunk_all(&chunker, reader), expected);
+        }
+    }
+
+    #[test]
+    fn test_boundaries_resync_after_edit() {
+        let input = random_input(300_000);
+        let chunker = Chunker::new();
+        let original = chunk_all(&chunker, &input[..]);
+        let mut edited = b"a few extra bytes at the front".to_vec();
+        edited.extend_from_slice(&input);
+        let edited_chunks = chunk_all(&chunker, &edited[..]);
+
+        // Almost all the chunks should be shared.
+        let original_hashes: std::collections::HashSet<_> =
+            original.iter().map(|c| c.hash).collect();
+        let shared = edited_chunks
+            .iter()
+            .filter(|c| original_hashes.contains(&c.hash))
+            .count();
+        assert!(
+            shared + 3 >= original.len(),
+            "{} {}",
+            shared,
+            original.len()
+        );
+    }
+
+    // The first and last outputs of SplitMix64 with a seed of 0, as published with the generator.
+    #[test]
+    fn test_gear_table() {
+        assert_eq!(GEAR[0], 0xe220a8397b1dcdaf);
+        assert_eq!(GEAR[1], 0x6e789e6aa1b965f4);
+        assert_eq!(GEAR[255], 0x5a5832bb47bcf19e);
+    }
+
+    // FastCDC's cut points, computing the gear hash at each position from scratch.
+    fn naive_lens(input: &[u8], min_size: usize, avg_size: usize, max_size: usize) -> Vec<usize> {
+        let avg_bits = avg_size.trailing_zeros();
+        let mut lens = Vec::new();
+        let mut start = 0;
+        while start < input.len() {
+            let data = &input[start..cmp::min(input.len(), start + max_size)];
+            let mut len = data.len();
+            for i in min_size..data.len() {
+                let mut hash: u64 = 0;
+                // Each byte is shifted left once per byte after it, until it drops out.
+                for j in min_size..=i {
+                    if i - j < 64 {
+                        hash = hash.wrapping_add(GEAR[data[j] as usize] << (i - j));
+                    }
+                }
+                let bits = if i < avg_size {
+                    avg_bits + 2
+                } else {
+                    avg_bits - 2
+                };
+                if hash >> (64 - bits) == 0 {
+                    len = i + 1;
+                    break;
+                }
+            }
+            lens.push(len);
+            start += len;
+        }
+        lens
+    }
+
+    #[test]
+    fn test_compare_naive_cuts() {
+        let input = random_input(20_000);
+        for &(min, avg, max) in &[(256, 1024, 4096), (64, 64, 64), (64, 128, 100_000)] {
+            let chunker = Chunker::with_sizes(min, avg, max).unwrap();
+            let lens: Vec<usize> = chunk_all(&chunker, &input[..])
+                .iter()
+                .map(|c| c.len)
+                .collect();
+            assert_eq!(lens, naive_lens(&input, min, avg, max));
+        }
+    }
+
+    #[test]
+    fn test_invalid_sizes() {
+        Chunker::with_sizes(64, 64, 64).unwrap();
+        Chunker::with_sizes(64, 1 << 29, 1 << 30).unwrap();
+        for &(min, avg, max) in &[
+            (63, 64, 64),
+            (128, 64, 256),
+            (64, 256, 128),
+            (64, 100, 256),
+            (64, 1 << 20, (1 << 30) + 1),
+        ] {
+            assert!(Chunker::with_sizes(min, avg, max).is_err());
+        }
+        let chunker = Chunker::default();
+        assert_eq!(chunker.min_size(), DEFAULT_MIN_SIZE);
+        assert_eq!(chunker.avg_size(), DEFAULT_AVG_SIZE);
+        assert_eq!(chunker.max_size(), DEFAULT_MAX_SIZE);
+    }
+
+    #[test]
+    fn test_buf_len() {
+        assert_eq!(buf_len(DEFAULT_MAX_SIZE), MIN_BUF_LEN);
+        assert_eq!(buf_len(MIN_BUF_LEN), 2 * MIN_BUF_LEN);
+        let half = isize::MAX as usize / 2;
+        assert_eq!(buf_len(half), 2 * half);
+        assert_eq!(buf_len(half + 1), half + 1);
+        assert_eq!(buf_len(usize::MAX), usize::MAX);
+    }
+
+    #[test]
+    fn test_read_error() {
+        struct FailingReader(usize);
+        impl Read for FailingReader {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.0 == 0 {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other, "oops"));
+                }
+                let n = cmp::min(self.0, buf.len());
+                buf[..n].fill(1);
+                self.0 -= n;
+                Ok(n)
+            }
+        }
+        let mut chunks = Chunker::new().chunks(FailingReader(1000));
+        assert!(chunks.next().unwrap().is_err());
+        assert!(chunks.next().is_none());
+    }
+}
@@ -148,6 +148,10 @@
 //! right subtrees you're giving them, and they can't help you catch mistakes. The best way to
 //! catch mistakes with these is to compare your root output to the [`blake3::hash`](crate::hash)
 //! of the same input.
+//!
+//! If what you need is to prove that one chunk belongs to an input with a known hash, the
+//! [`proof`](crate::proof) module builds and verifies those proofs for you, using the functions
+//! in this module.
 
 use crate::platform::Platform;
 use crate::{CVWords, Hasher, CHUNK_LEN, IV, KEY_LEN, OUT_LEN};
@@ -246,6 +250,36 @@ impl HasherExt for Hasher {
     }
 }
 
+/// Finalize the current chunk or subtree of a [`Hasher`] as a [`SubtreeHash`], which keeps track
+/// of its offset and length.
+///
+/// Unlike [`finalize_non_root`](HasherExt::finalize_non_root), this doesn't commit to whether the
+/// subtree is the root. Subtree hashes can be joined with [`SubtreeHash::combine`], which checks
+/// the tree structure for you, and the one that covers the whole input can be finalized with
+/// [`SubtreeHash::finalize_root`].
+///
+/// # Panics
+///
+/// This function panics if the `Hasher` has a non-zero [input
+/// offset](HasherExt::set_input_offset) and hasn't accepted any input. (Empty subtrees are never
+/// valid, but the empty input at offset zero is.)
+pub fn finalize_subtree(hasher: &Hasher) -> SubtreeHash {
+    let offset = hasher.initial_chunk_counter * CHUNK_LEN as u64;
+    assert!(
+        offset == 0 || hasher.count() != 0,
+        "empty subtrees are never valid",
+    );
+    let output = hasher.final_output();
+    SubtreeHash {
+        offset,
+        len: hasher.count(),
+        key: hasher.key,
+        flags: hasher.chunk_state.flags,
+        cv: output.chaining_value(),
+        output: Some(output),
+    }
+}
+
 /// The maximum length of a subtree in bytes, given its starting offset in bytes
 ///
 /// If you try to hash more than this many bytes as one subtree, you'll end up merging parent nodes
@@ -377,13 +411,15 @@ fn test_max_subtree_len() {
 #[inline(always)]
 pub fn left_subtree_len(input_len: u64) -> u64 {
     debug_assert!(input_len > CHUNK_LEN as u64);
-    // Note that .next_power_of_two() is greater than *or equal*.
-    ((input_len + 1) / 2).next_power_of_two()
+    // Note that .next_power_of_two() is greater than *or equal*. This is half the length rounded
+    // up, written so that it doesn't overflow for u64::MAX.
+    (input_len / 2 + input_len % 2).next_power_of_two()
 }
 
 #[test]
 fn test_left_subtree_len() {
     assert_eq!(left_subtree_len(1025), 1024);
+    assert_eq!(left_subtree_len(u64::MAX), 1 << 63);
     for boundary_case in [2, 4, 8, 16, 32, 64] {
         let input_len = boundary_case * CHUNK_LEN as u64;
         assert_eq!(left_subtree_len(input_len - 1), input_len / 2);
@@ -410,7 +446,7 @@ pub enum Mode<'a> {
 }
 
 impl<'a> Mode<'a> {
-    fn key_words(&self) -> CVWords {
+    pub(crate) fn key_words(&self) -> CVWords {
         match self {
             Mode::Hash => *IV,
             Mode::KeyedHash(key) => crate::platform::words_from_le_bytes_32(key),
@@ -418,7 +454,7 @@ impl<'a> Mode<'a> {
         }
     }
 
-    fn flags_byte(&self) -> u8 {
+    pub(crate) fn flags_byte(&self) -> u8 {
         match self {
             Mode::Hash => 0,
             Mode::KeyedHash(_) => crate::KEYED_HASH,
@@ -566,6 +602,226 @@ pub fn hash_derive_key_context(context: &str) -> ContextKey {
     .0
 }
 
+fn exceeds_max_subtree_len(offset: u64, len: u64) -> bool {
+    match max_subtree_len(offset) {
+        Some(max) => len > max,
+        None => false,
+    }
+}
+
+/// The hash of a chunk-aligned range of the input, along with its offset and length
+///
+/// This is a higher-level alternative to working with raw [`ChainingValue`]s. You get a
+/// `SubtreeHash` from [`finalize_subtree`] (or from
+/// [`from_chaining_value`](SubtreeHash::from_chaining_value), for example after sending one over
+/// the network), join adjacent ones with [`combine`](SubtreeHash::combine), and finally get the
+/// root hash with [`finalize_root`](SubtreeHash::finalize_root) or
+/// [`finalize_root_xof`](SubtreeHash::finalize_root_xof). Each step checks that the pieces fit the
+/// BLAKE3 tree structure, and returns a [`SubtreeError`] if they don't.
+///
+/// Combining only works for pieces that are actually siblings in the tree. The left piece must be
+/// a power-of-two number of chunks, the right piece can't be longer than the left, and the pair
+/// must start at an offset where a subtree of their combined size is allowed (see
+/// [`max_subtree_len`]). Splitting the input into fixed-size power-of-two pieces and combining
+/// them pairwise, layer by layer, always satisfies these rules.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), blake3::hazmat::SubtreeError> {
+/// use blake3::hazmat::{finalize_subtree, HasherExt, SubtreeHash};
+/// use blake3::{Hasher, CHUNK_LEN};
+///
+/// let input = vec![0xab; 3 * CHUNK_LEN + 42];
+///
+/// // Different workers could hash these pieces independently.
+/// let first = finalize_subtree(Hasher::new().update(&input[..2 * CHUNK_LEN]));
+/// let second = finalize_subtree(
+///     Hasher::new()
+///         .set_input_offset(2 * CHUNK_LEN as u64)
+///         .update(&input[2 * CHUNK_LEN..]),
+/// );
+///
+/// let whole = SubtreeHash::combine(&first, &second)?;
+/// assert_eq!(whole.len(), input.len() as u64);
+/// assert_eq!(whole.finalize_root()?, blake3::hash(&input));
+///
+/// // Combining them in the wrong order doesn't work.
+/// assert!(SubtreeHash::combine(&second, &first).is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SubtreeHash {
+    offset: u64,
+    len: u64,
+    key: CVWords,
+    flags: u8,
+    cv: ChainingValue,
+    // Only available if we computed this subtree ourselves. Needed for root finalization.
+    output: Option<crate::Output>,
+}
+
+impl SubtreeHash {
+    /// Construct a `SubtreeHash` from a chaining value that was computed elsewhere, for example
+    /// with [`finalize_non_root`](HasherExt::finalize_non_root) or from another `SubtreeHash`.
+    ///
+    /// This can be combined with other subtree hashes like usual, but it can't be finalized by
+    /// itself, because a root hash can't be computed from a chaining value. This returns an
+    /// error if the `offset` isn't a multiple of [`CHUNK_LEN`], if `len` is zero, or if `len` is
+    /// larger than [`max_subtree_len`] allows.
+    pub fn from_chaining_value(
+        offset: u64,
+        len: u64,
+        chaining_value: &ChainingValue,
+        mode: Mode,
+    ) -> Result<Self, SubtreeError> {
+        if offset & (CHUNK_LEN as u64 - 1) != 0 || len == 0 {
+            return Err(SubtreeError(SubtreeErrorInner::InvalidShape));
+        }
+        if exceeds_max_subtree_len(offset, len) {
+            return Err(SubtreeError(SubtreeErrorInner::InvalidShape));
+        }
+        Ok(Self {
+            offset,
+            len,
+            key: mode.key_words(),
+            flags: mode.flags_byte(),
+            cv: *chaining_value,
+            output: None,
+        })
+    }
+
+    /// Combine two adjacent subtree hashes into the hash of their parent.
+    ///
+    /// This returns an error if `left` and `right` used different [`Mode`]s, if they aren't
+    /// adjacent, or if they aren't siblings in the BLAKE3 tree. See the [type level
+    /// docs](SubtreeHash).
+    pub fn combine(left: &Self, right: &Self) -> Result<Self, SubtreeError> {
+        if left.key != right.key || left.flags != right.flags {
+            return Err(SubtreeError(SubtreeErrorInner::ModeMismatch));
+        }
+        if left.offset.checked_add(left.len) != Some(right.offset) {
+            return Err(SubtreeError(SubtreeErrorInner::NotAdjacent));
+        }
+        let len = left.len + right.len;
+        if left.len < CHUNK_LEN as u64
+            || !left.len.is_power_of_two()
+            || right.len == 0
+            || right.len > left.len
+            || exceeds_max_subtree_len(left.offset, len)
+        {
+            return Err(SubtreeError(SubtreeErrorInner::InvalidShape));
+        }
+        debug_assert_eq!(left_subtree_len(len), left.len);
+        let output = crate::parent_node_output(
+            &left.cv,
+            &right.cv,
+            &left.key,
+            left.flags,
+            Platform::detect(),
+        );
+        Ok(Self {
+            offset: left.offset,
+            len,
+            key: left.key,
+            flags: left.flags,
+            cv: output.chaining_value(),
+            output: Some(output),
+        })
+    }
+
+    /// The offset of the subtree in bytes from the start of the whole input.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The length of the subtree in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the subtree is empty. That's only possible for the empty input, at offset zero.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The non-root hash ("chaining value") of the subtree, the same value that
+    /// [`finalize_non_root`](HasherExt::finalize_non_root) returns.
+    pub fn chaining_value(&self) -> ChainingValue {
+        self.cv
+    }
+
+    fn root_output(&self) -> Result<&crate::Output, SubtreeError> {
+        if self.offset != 0 {
+            return Err(SubtreeError(SubtreeErrorInner::NotRoot));
+        }
+        self.output
+            .as_ref()
+            .ok_or(SubtreeError(SubtreeErrorInner::NoRootOutput))
+    }
+
+    /// Finalize the root hash, assuming this subtree is the whole input.
+    ///
+    /// The result is the same as [`Hasher::finalize`] would give for the whole input. This
+    /// returns an error if the subtree doesn't start at offset zero, or if it came from
+    /// [`from_chaining_value`](SubtreeHash::from_chaining_value).
+    pub fn finalize_root(&self) -> Result<crate::Hash, SubtreeError> {
+        Ok(self.root_output()?.root_hash())
+    }
+
+    /// Finalize an [`OutputReader`](crate::OutputReader), assuming this subtree is the whole
+    /// input.
+    ///
+    /// This is the extended-output version of [`finalize_root`](SubtreeHash::finalize_root), and
+    /// it has the same requirements.
+    pub fn finalize_root_xof(&self) -> Result<crate::OutputReader, SubtreeError> {
+        Ok(crate::OutputReader::new(self.root_output()?.clone()))
+    }
+}
+
+// Don't derive(Debug), because the key may be secret.
+impl core::fmt::Debug for SubtreeHash {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("SubtreeHash")
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+/// The error type for [`SubtreeHash`] operations.
+///
+/// The `.to_string()` representation of this error currently describes what went wrong. This is
+/// to help with logging and debugging, but it isn't a stable API detail, and it may change at any
+/// time.
+#[derive(Clone, Debug)]
+pub struct SubtreeError(SubtreeErrorInner);
+
+#[derive(Clone, Debug)]
+enum SubtreeErrorInner {
+    ModeMismatch,
+    NotAdjacent,
+    InvalidShape,
+    NotRoot,
+    NoRootOutput,
+}
+
+impl core::fmt::Display for SubtreeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str(match self.0 {
+            SubtreeErrorInner::ModeMismatch => "subtrees use different modes",
+            SubtreeErrorInner::NotAdjacent => "subtrees are not adjacent",
+            SubtreeErrorInner::InvalidShape => "subtrees don't fit the tree structure",
+            SubtreeErrorInner::NotRoot => "subtree doesn't start at offset zero",
+            SubtreeErrorInner::NoRootOutput => "subtree was constructed from a chaining value",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SubtreeError {}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -712,4 +968,143 @@ mod test {
         let derived_key = merge_subtrees_root(&left, &right, Mode::DeriveKeyMaterial(&cx_key)).0;
         assert_eq!(expected, derived_key);
     }
+
+    #[test]
+    fn test_subtree_hash_combine() {
+        let mut input_buf = [0; crate::test::TEST_CASES_MAX];
+        crate::test::paint_test_input(&mut input_buf);
+        let key = &crate::test::TEST_KEY;
+        const MAX_CHUNKS: usize = (crate::test::TEST_CASES_MAX + 1) / CHUNK_LEN + 1;
+        for subtree_chunks in [1, 2, 4, 16] {
+            let subtree_len = subtree_chunks * CHUNK_LEN;
+            for &case in crate::test::TEST_CASES {
+                let input = &input_buf[..case];
+
+                // Hash fixed-size pieces, then combine them pairwise, layer by layer.
+                let mut layer = arrayvec::ArrayVec::<SubtreeHash, MAX_CHUNKS>::new();
+                let mut offset = 0;
+                loop {
+                    let take = core::cmp::min(subtree_len, input.len() - offset);
+                    let mut hasher = Hasher::new_keyed(key);
+                    hasher.set_input_offset(offset as u64);
+                    hasher.update(&input[offset..][..take]);
+                    layer.push(finalize_subtree(&hasher));
+                    offset += take;
+                    if offset == input.len() {
+                        break;
+                    }
+                }
+                while layer.len() > 1 {
+                    layer = layer
+                        .chunks(2)
+                        .map(|pair| match pair {
+                            [left, right] => SubtreeHash::combine(left, right).unwrap(),
+                            [odd] => odd.clone(),
+                            _ => unreachable!(),
+                        })
+                        .collect();
+                }
+                let root = &layer[0];
+                assert_eq!(root.offset(), 0);
+                assert_eq!(root.len(), case as u64);
+                assert_eq!(root.finalize_root().unwrap(), crate::keyed_hash(key, input));
+                let mut expected_output = [0; 100];
+                Hasher::new_keyed(key)
+                    .update(input)
+                    .finalize_xof()
+                    .fill(&mut expected_output);
+                let mut output = [0; 100];
+                root.finalize_root_xof().unwrap().fill(&mut output);
+                assert_eq!(expected_output, output);
+                if case > 0 {
+                    let expected_cv = Hasher::new_keyed(key).update(input).finalize_non_root();
+                    assert_eq!(root.chaining_value(), expected_cv);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_subtree_hash_errors() {
+        let input = [42; 8 * CHUNK_LEN];
+        let subtree = |start: usize, end: usize| {
+            finalize_subtree(
+                Hasher::new()
+                    .set_input_offset(start as u64)
+                    .update(&input[start..end]),
+            )
+        };
+        let chunk0 = subtree(0, CHUNK_LEN);
+        let chunk1 = subtree(CHUNK_LEN, 2 * CHUNK_LEN);
+        let chunk2 = subtree(2 * CHUNK_LEN, 3 * CHUNK_LEN);
+        let chunks23 = subtree(2 * CHUNK_LEN, 4 * CHUNK_LEN);
+        let chunks01 = SubtreeHash::combine(&chunk0, &chunk1).unwrap();
+        assert!(SubtreeHash::combine(&chunks01, &chunks23).is_ok());
+        assert!(SubtreeHash::combine(&chunks01, &chunk2).is_ok());
+
+        // Not adjacent, or in the wrong order.
+        assert!(SubtreeHash::combine(&chunk0, &chunk2).is_err());
+        assert!(SubtreeHash::combine(&chunk1, &chunk0).is_err());
+        // Adjacent, but not siblings.
+        assert!(SubtreeHash::combine(&chunk1, &chunk2).is_err());
+        assert!(SubtreeHash::combine(&chunk1, &chunks23).is_err());
+        // The right side can't be larger than the left.
+        let chunks1_3 = SubtreeHash::combine(&chunk1, &chunk2);
+        assert!(chunks1_3.is_err());
+        let short_chunk0 = subtree(0, 100);
+        assert!(SubtreeHash::combine(&short_chunk0, &chunk1).is_err());
+        // Different modes.
+        let keyed_chunk1 = finalize_subtree(
+            Hasher::new_keyed(&[0; 32])
+                .set_input_offset(CHUNK_LEN as u64)
+                .update(&input[CHUNK_LEN..2 * CHUNK_LEN]),
+        );
+        assert!(SubtreeHash::combine(&chunk0, &keyed_chunk1).is_err());
+
+        // Only subtrees at offset zero can be finalized.
+        assert!(chunk1.finalize_root().is_err());
+        assert!(chunk1.finalize_root_xof().is_err());
+        assert_eq!(
+            chunk0.finalize_root().unwrap(),
+            crate::hash(&input[..CHUNK_LEN])
+        );
+    }
+
+    #[test]
+    fn test_subtree_hash_from_chaining_value() {
+        let mut input = [0; 3 * CHUNK_LEN];
+        crate::test::paint_test_input(&mut input);
+        let left_cv = Hasher::new()
+            .update(&input[..2 * CHUNK_LEN])
+            .finalize_non_root();
+        let right_cv = Hasher::new()
+            .set_input_offset(2 * CHUNK_LEN as u64)
+            .update(&input[2 * CHUNK_LEN..])
+            .finalize_non_root();
+        let left = SubtreeHash::from_chaining_value(0, 2 * CHUNK_LEN as u64, &left_cv, Mode::Hash)
+            .unwrap();
+        let right = SubtreeHash::from_chaining_value(
+            2 * CHUNK_LEN as u64,
+            CHUNK_LEN as u64,
+            &right_cv,
+            Mode::Hash,
+        )
+        .unwrap();
+        // A subtree built from a chaining value can't be finalized, but a parent of two can.
+        assert!(left.finalize_root().is_err());
+        let root = SubtreeHash::combine(&left, &right).unwrap();
+        assert_eq!(root.finalize_root().unwrap(), crate::hash(&input));
+
+        assert!(SubtreeHash::from_chaining_value(1, 1, &left_cv, Mode::Hash).is_err());
+        assert!(SubtreeHash::from_chaining_value(0, 0, &left_cv, Mode::Hash).is_err());
+        let too_long = 2 * CHUNK_LEN as u64 + 1;
+        let offset = 2 * CHUNK_LEN as u64;
+        assert!(SubtreeHash::from_chaining_value(offset, too_long, &left_cv, Mode::Hash).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_subtree_hash_should_panic() {
+        finalize_subtree(Hasher::new().set_input_offset(CHUNK_LEN as u64));
+    }
 }
@@ -0,0 +1,700 @@
+//! Experimental stateful hash-based signatures, in the style of
+//! [XMSS](https://datatracker.ietf.org/doc/html/rfc8391).
+//!
+//! Hash-based signatures rely only on the security of the hash function, so they're believed to
+//! resist attacks by quantum computers. The tradeoffs are that signatures are large (about 2.7
+//! KB), and that **the signing key is stateful**. Each [`SigningKey`] holds a fixed number of
+//! one-time keys, 2<sup>height</sup> of them, and every signature uses up the next one. Signing
+//! two different messages with the same one-time key can let an attacker forge signatures. That
+//! makes this scheme a good fit for something like signing firmware updates, where signatures are
+//! rare and come from one place, and a poor fit for almost everything else.
+//!
+//! **Persist the state before releasing each signature.** After calling
+//! [`sign`](SigningKey::sign), save [`next_index`](SigningKey::next_index) to durable storage
+//! before publishing the signature, and restore the key with [`SigningKey::restore`]. Never
+//! restore from a backup or a copy that might be behind. For the same reason, `SigningKey`
+//! doesn't implement `Clone`.
+//!
+//! # Construction
+//!
+//! The one-time keys are WOTS+ with a Winternitz parameter of 16: each one has 67 hash chains of
+//! length 16, covering the 64 hex digits of a 32-byte message digest and a 3-digit checksum. Each
+//! chain step is one compression, the [`keyed_hash`](crate::keyed_hash) of a 64-byte block, and
+//! all the chains of a key advance together through [`keyed_hash_many`](crate::keyed_hash_many),
+//! so that they use SIMD. All integers below are encoded as 4 little-endian bytes, and `||` is
+//! concatenation.
+//!
+//! ```text
+//! secret_seed = derive_key("BLAKE3 2026-10-15 hbs secret seed v1", seed)
+//! prf_key     = derive_key("BLAKE3 2026-10-15 hbs message randomizer v1", seed)
+//! public_seed = derive_key("BLAKE3 2026-10-15 hbs public seed v1", seed)
+//! addr(tag, i, j, k) = tag || i || j || k || 16 zero bytes
+//!
+//! chain start for leaf i, chain j:  keyed_hash(secret_seed, addr(1, i, j, 0))
+//! chain step k for leaf i, chain j: x = keyed_hash(public_seed, addr(0, i, j, k) || x)
+//! leaf i: keyed_hash(public_seed, addr(2, i, 0, 0) || chain end 0 || ... || chain end 66)
+//! ```
+//!
+//! The leaves are joined into a binary Merkle tree with [`merge_subtrees_non_root`] and, at the
+//! top, [`merge_subtrees_root`], in the keyed mode with `public_seed` as the key. (This tree
+//! doesn't correspond to the BLAKE3 hash of any input. It only reuses the parent node
+//! compression.) The public key is the height, `public_seed`, and the root.
+//!
+//! To sign message `m` with leaf `i`, the signer computes a randomizer `r = keyed_hash(prf_key, i
+//! || m)` and a digest `keyed_hash(root, r || i || m)`, and then publishes `i`, `r`, the WOTS+
+//! chain values at the positions given by the digest and its checksum, and the siblings of the
+//! path from leaf `i` up to the root.
+//!
+//! This module requires the `hbs-preview` Cargo feature. The construction hasn't had much outside
+//! analysis yet, and it might change in a patch release.
+//!
+//! # Example
+//!
+//! ```
+//! # fn main() -> Result<(), blake3::hbs::HbsError> {
+//! use blake3::hbs::{PublicKey, Signature, SigningKey};
+//!
+//! # let seed = [42; 32];
+//! // In a real application, the seed comes from a CSPRNG and stays secret.
+//! let mut signing_key = SigningKey::new(&seed, 4)?;
+//! assert_eq!(signing_key.remaining_signatures(), 16);
+//! let public_key_bytes = signing_key.public_key().to_bytes();
+//!
+//! let signature = signing_key.sign(b"firmware v1.2.3")?;
+//! // Persist this before publishing the signature.
+//! assert_eq!(signing_key.next_index(), 1);
+//! let signature_bytes = signature.to_bytes();
+//!
+//! // The verifier needs only the public key.
+//! let public_key = PublicKey::from_bytes(&public_key_bytes)?;
+//! let signature = Signature::from_bytes(&signature_bytes)?;
+//! public_key.verify(b"firmware v1.2.3", &signature)?;
+//! assert!(public_key.verify(b"firmware v6.6.6", &signature).is_err());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::hazmat::{merge_subtrees_non_root, merge_subtrees_root, ChainingValue, Mode};
+use crate::{Hash, Hasher, BLOCK_LEN, KEY_LEN, OUT_LEN};
+use arrayvec::ArrayVec;
+use core::fmt;
+
+const SECRET_SEED_CONTEXT: &str = "BLAKE3 2026-10-15 hbs secret seed v1";
+const PRF_KEY_CONTEXT: &str = "BLAKE3 2026-10-15 hbs message randomizer v1";
+const PUBLIC_SEED_CONTEXT: &str = "BLAKE3 2026-10-15 hbs public seed v1";
+
+// The Winternitz parameter. Each digit of the message digest is a hex digit.
+const W: usize = 16;
+// 64 digits for the message digest, and 3 for the checksum, which is at most 64 * 15 = 960.
+const DIGEST_DIGITS: usize = 2 * OUT_LEN;
+const CHECKSUM_DIGITS: usize = 3;
+const WOTS_LEN: usize = DIGEST_DIGITS + CHECKSUM_DIGITS;
+
+const ADDR_LEN: usize = 32;
+const CHAIN_TAG: u32 = 0;
+const SECRET_TAG: u32 = 1;
+const LEAF_TAG: u32 = 2;
+
+/// The largest allowed tree height, 16, for 65536 signatures per key.
+pub const MAX_HEIGHT: u8 = 16;
+
+/// The length of an encoded [`PublicKey`], 65 bytes.
+pub const PUBLIC_KEY_LEN: usize = 1 + 2 * OUT_LEN;
+
+/// The length of an encoded [`Signature`] with a tree of height [`MAX_HEIGHT`].
+///
+/// Each level less than that makes the signature 32 bytes shorter.
+pub const MAX_SIGNATURE_LEN: usize = signature_len(MAX_HEIGHT as usize);
+
+const fn signature_len(height: usize) -> usize {
+    4 + OUT_LEN + WOTS_LEN * OUT_LEN + height * OUT_LEN
+}
+
+fn check_height(height: u8) -> Result<(), HbsError> {
+    if (1..=MAX_HEIGHT).contains(&height) {
+        Ok(())
+    } else {
+        Err(HbsError(HbsErrorInner::InvalidHeight(height)))
+    }
+}
+
+fn address(tag: u32, leaf: u32, chain: u32, step: u32) -> [u8; ADDR_LEN] {
+    let mut addr = [0; ADDR_LEN];
+    addr[0..4].copy_from_slice(&tag.to_le_bytes());
+    addr[4..8].copy_from_slice(&leaf.to_le_bytes());
+    addr[8..12].copy_from_slice(&chain.to_le_bytes());
+    addr[12..16].copy_from_slice(&step.to_le_bytes());
+    addr
+}
+
+// Advance chain j from step start[j] to step end[j], for every j at once. The chains move in
+// lockstep, so that each step is one batched call to keyed_hash_many.
+fn advance_chains(
+    public_seed: &[u8; KEY_LEN],
+    leaf: u32,
+    values: &mut [ChainingValue; WOTS_LEN],
+    start: &[u8; WOTS_LEN],
+    end: &[u8; WOTS_LEN],
+) {
+    for step in 0..W as u8 - 1 {
+        let mut chains = ArrayVec::<usize, WOTS_LEN>::new();
+        let mut blocks = ArrayVec::<[u8; BLOCK_LEN], WOTS_LEN>::new();
+        for j in 0..WOTS_LEN {
+            if start[j] <= step && step < end[j] {
+                let addr = address(CHAIN_TAG, leaf, j as u32, step as u32);
+                let mut block = [0; BLOCK_LEN];
+                block[..ADDR_LEN].copy_from_slice(&addr);
+                block[ADDR_LEN..].copy_from_slice(&values[j]);
+                chains.push(j);
+                blocks.push(block);
+            }
+        }
+        if chains.is_empty() {
+            continue;
+        }
+        let inputs: ArrayVec<&[u8], WOTS_LEN> = blocks.iter().map(|b| &b[..]).collect();
+        let outputs = crate::keyed_hash_many(public_seed, &inputs);
+        for (&j, output) in chains.iter().zip(outputs.iter()) {
+            values[j] = *output.as_bytes();
+        }
+    }
+}
+
+fn wots_secret(secret_seed: &[u8; KEY_LEN], leaf: u32) -> [ChainingValue; WOTS_LEN] {
+    let mut chains = [[0; OUT_LEN]; WOTS_LEN];
+    for (j, chain) in chains.iter_mut().enumerate() {
+        let addr = address(SECRET_TAG, leaf, j as u32, 0);
+        *chain = *crate::keyed_hash(secret_seed, &addr).as_bytes();
+    }
+    chains
+}
+
+fn leaf_hash(
+    public_seed: &[u8; KEY_LEN],
+    leaf: u32,
+    chain_ends: &[ChainingValue; WOTS_LEN],
+) -> ChainingValue {
+    let mut hasher = Hasher::new_keyed(public_seed);
+    hasher.update(&address(LEAF_TAG, leaf, 0, 0));
+    for end in chain_ends {
+        hasher.update(end);
+    }
+    *hasher.finalize().as_bytes()
+}
+
+fn message_digest(root: &Hash, randomizer: &[u8; OUT_LEN], index: u32, message: &[u8]) -> Hash {
+    let mut hasher = Hasher::new_keyed(root.as_bytes());
+    hasher.update(randomizer);
+    hasher.update(&index.to_le_bytes());
+    hasher.update(message);
+    hasher.finalize()
+}
+
+// The base-16 digits of the digest, followed by the base-16 digits of their checksum.
+fn digits(digest: &Hash) -> [u8; WOTS_LEN] {
+    let mut digits = [0; WOTS_LEN];
+    for (i, byte) in digest.as_bytes().iter().enumerate() {
+        digits[2 * i] = byte >> 4;
+        digits[2 * i + 1] = byte & 0xf;
+    }
+    let checksum: u32 = digits[..DIGEST_DIGITS]
+        .iter()
+        .map(|&d| (W - 1) as u32 - d as u32)
+        .sum();
+    for i in 0..CHECKSUM_DIGITS {
+        let shift = 4 * (CHECKSUM_DIGITS - 1 - i);
+        digits[DIGEST_DIGITS + i] = (checksum >> shift) as u8 & 0xf;
+    }
+    digits
+}
+
+/// A stateful signing key, which can make 2<sup>height</sup> signatures.
+///
+/// See the [module level docs](self) for how to manage the state. This type doesn't implement
+/// `Clone`, because two copies of a signing key would sign with the same one-time keys.
+pub struct SigningKey {
+    secret_seed: [u8; KEY_LEN],
+    prf_key: [u8; KEY_LEN],
+    public_key: PublicKey,
+    // tree[k] holds the 2^(height-k) chaining values at level k, where level 0 is the leaves. The
+    // root isn't included.
+    tree: Vec<Vec<ChainingValue>>,
+    next_index: u32,
+}
+
+impl SigningKey {
+    /// Generate a new signing key from a secret 32-byte seed, with a tree of the given height.
+    ///
+    /// Key generation computes every one-time key, so it takes time proportional to
+    /// 2<sup>height</sup>. Returns an error if `height` is 0 or greater than [`MAX_HEIGHT`].
+    pub fn new(seed: &[u8; KEY_LEN], height: u8) -> Result<Self, HbsError> {
+        Self::restore(seed, height, 0)
+    }
+
+    /// Regenerate a signing key, starting from one-time key `next_index`.
+    ///
+    /// `next_index` must be the value last saved from [`next_index`](Self::next_index). Returns
+    /// an error if `height` is 0 or greater than [`MAX_HEIGHT`], or if `next_index` is greater
+    /// than 2<sup>height</sup>.
+    pub fn restore(seed: &[u8; KEY_LEN], height: u8, next_index: u32) -> Result<Self, HbsError> {
+        check_height(height)?;
+        let num_leaves = 1u32 << height;
+        if next_index > num_leaves {
+            return Err(HbsError(HbsErrorInner::IndexOutOfRange));
+        }
+        let secret_seed = crate::derive_key(SECRET_SEED_CONTEXT, seed);
+        let prf_key = crate::derive_key(PRF_KEY_CONTEXT, seed);
+        let public_seed = crate::derive_key(PUBLIC_SEED_CONTEXT, seed);
+        let mode = Mode::KeyedHash(&public_seed);
+
+        let mut leaves = Vec::with_capacity(num_leaves as usize);
+        for leaf in 0..num_leaves {
+            let mut chains = wots_secret(&secret_seed, leaf);
+            advance_chains(
+                &public_seed,
+                leaf,
+                &mut chains,
+                &[0; WOTS_LEN],
+                &[W as u8 - 1; WOTS_LEN],
+            );
+            leaves.push(leaf_hash(&public_seed, leaf, &chains));
+        }
+        let mut tree = vec![leaves];
+        while tree.last().unwrap().len() > 2 {
+            let level = tree
+                .last()
+                .unwrap()
+                .chunks_exact(2)
+                .map(|pair| merge_subtrees_non_root(&pair[0], &pair[1], mode))
+                .collect();
+            tree.push(level);
+        }
+        let top = tree.last().unwrap();
+        let root = merge_subtrees_root(&top[0], &top[1], mode);
+
+        Ok(Self {
+            secret_seed,
+            prf_key,
+            public_key: PublicKey {
+                height,
+                public_seed,
+                root,
+            },
+            tree,
+            next_index,
+        })
+    }
+
+    /// The public key that verifies this key's signatures.
+    pub fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    /// The index of the one-time key that the next signature will use.
+    pub fn next_index(&self) -> u32 {
+        self.next_index
+    }
+
+    /// The number of signatures this key can still make.
+    pub fn remaining_signatures(&self) -> u32 {
+        (1u32 << self.public_key.height) - self.next_index
+    }
+
+    /// Sign a message with the next one-time key, and advance [`next_index`](Self::next_index).
+    ///
+    /// Returns an error if all the one-time keys are used up.
+    pub fn sign(&mut self, message: &[u8]) -> Result<Signature, HbsError> {
+        if self.remaining_signatures() == 0 {
+            return Err(HbsError(HbsErrorInner::KeyExhausted));
+        }
+        let index = self.next_index;
+        self.next_index += 1;
+
+        let mut randomizer_hasher = Hasher::new_keyed(&self.prf_key);
+        randomizer_hasher.update(&index.to_le_bytes());
+        randomizer_hasher.update(message);
+        let randomizer = *randomizer_hasher.finalize().as_bytes();
+        let digest = message_digest(&self.public_key.root, &randomizer, index, message);
+
+        let mut wots = wots_secret(&self.secret_seed, index);
+        advance_chains(
+            &self.public_key.public_seed,
+            index,
+            &mut wots,
+            &[0; WOTS_LEN],
+            &digits(&digest),
+        );
+        let auth_path = self
+            .tree
+            .iter()
+            .enumerate()
+            .map(|(level, nodes)| nodes[(index >> level) as usize ^ 1])
+            .collect();
+        Ok(Signature {
+            index,
+            randomizer,
+            wots,
+            auth_path,
+        })
+    }
+}
+
+// Don't derive(Debug), because the seeds are secret.
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("public_key", &self.public_key)
+            .field("next_index", &self.next_index)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for SigningKey {
+    fn zeroize(&mut self) {
+        // Destructuring to trigger compile error as a reminder to update this impl.
+        let Self {
+            secret_seed,
+            prf_key,
+            public_key: _,
+            tree: _,
+            next_index: _,
+        } = self;
+
+        // The public key, the tree, and the index aren't secret.
+        secret_seed.zeroize();
+        prf_key.zeroize();
+    }
+}
+
+/// A public key that verifies [`Signature`]s from one [`SigningKey`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublicKey {
+    height: u8,
+    public_seed: [u8; KEY_LEN],
+    root: Hash,
+}
+
+impl PublicKey {
+    /// Decode a public key from the format produced by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8; PUBLIC_KEY_LEN]) -> Result<Self, HbsError> {
+        let height = bytes[0];
+        check_height(height)?;
+        Ok(Self {
+            height,
+            public_seed: *arrayref::array_ref!(bytes, 1, KEY_LEN),
+            root: Hash::from_bytes(*arrayref::array_ref!(bytes, 1 + KEY_LEN, OUT_LEN)),
+        })
+    }
+
+    /// Encode the public key as the tree height (1 byte), the public seed (32 bytes), and the
+    /// root hash (32 bytes).
+    pub fn to_bytes(&self) -> [u8; PUBLIC_KEY_LEN] {
+        let mut bytes = [0; PUBLIC_KEY_LEN];
+        bytes[0] = self.height;
+        bytes[1..][..KEY_LEN].copy_from_slice(&self.public_seed);
+        bytes[1 + KEY_LEN..].copy_from_slice(self.root.as_bytes());
+        bytes
+    }
+
+    /// The height of the tree. The key can make 2<sup>height</sup> signatures.
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    /// Check that `signature` is a signature of `message` under this key.
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> Result<(), HbsError> {
+        let failed = HbsError(HbsErrorInner::VerificationFailed);
+        if signature.auth_path.len() != self.height as usize {
+            return Err(failed);
+        }
+        let index = signature.index;
+        let digest = message_digest(&self.root, &signature.randomizer, index, message);
+        let mut chains = signature.wots;
+        advance_chains(
+            &self.public_seed,
+            index,
+            &mut chains,
+            &digits(&digest),
+            &[W as u8 - 1; WOTS_LEN],
+        );
+        let mode = Mode::KeyedHash(&self.public_seed);
+        let mut cv = leaf_hash(&self.public_seed, index, &chains);
+        let (top_sibling, lower_siblings) = signature.auth_path.split_last().unwrap();
+        for (level, sibling) in lower_siblings.iter().enumerate() {
+            cv = if (index >> level) & 1 == 0 {
+                merge_subtrees_non_root(&cv, sibling, mode)
+            } else {
+                merge_subtrees_non_root(sibling, &cv, mode)
+            };
+        }
+        let root = if (index >> (self.height - 1)) & 1 == 0 {
+            merge_subtrees_root(&cv, top_sibling, mode)
+        } else {
+            merge_subtrees_root(top_sibling, &cv, mode)
+        };
+        // Hash equality is constant-time.
+        if root == self.root {
+            Ok(())
+        } else {
+            Err(failed)
+        }
+    }
+}
+
+/// A signature from a [`SigningKey`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    index: u32,
+    randomizer: [u8; OUT_LEN],
+    wots: [ChainingValue; WOTS_LEN],
+    // Sibling chaining values from the leaf up to the root.
+    auth_path: ArrayVec<ChainingValue, { MAX_HEIGHT as usize }>,
+}
+
+impl Signature {
+    /// Decode a signature from the format produced by [`to_bytes`](Self::to_bytes).
+    ///
+    /// The height of the tree is implied by the length.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HbsError> {
+        let invalid_len = HbsError(HbsErrorInner::InvalidLen(bytes.len()));
+        let fixed_len = signature_len(0);
+        let height = bytes.len().saturating_sub(fixed_len) / OUT_LEN;
+        if height == 0 || height > MAX_HEIGHT as usize || bytes.len() != signature_len(height) {
+            return Err(invalid_len);
+        }
+        let index = u32::from_le_bytes(*arrayref::array_ref!(bytes, 0, 4));
+        if u64::from(index) >= 1 << height {
+            return Err(HbsError(HbsErrorInner::IndexOutOfRange));
+        }
+        let mut wots = [[0; OUT_LEN]; WOTS_LEN];
+        for (chain, chunk) in wots
+            .iter_mut()
+            .zip(bytes[4 + OUT_LEN..fixed_len].chunks_exact(OUT_LEN))
+        {
+            chain.copy_from_slice(chunk);
+        }
+        let auth_path = bytes[fixed_len..]
+            .chunks_exact(OUT_LEN)
+            .map(|cv| cv.try_into().unwrap())
+            .collect();
+        Ok(Self {
+            index,
+            randomizer: *arrayref::array_ref!(bytes, 4, OUT_LEN),
+            wots,
+            auth_path,
+        })
+    }
+
+    /// Encode the signature as the little-endian leaf index (4 bytes), the randomizer (32
+    /// bytes), the 67 WOTS+ chain values (32 bytes each), and the sibling chaining values from
+    /// the leaf up to the root (32 bytes each).
+    pub fn to_bytes(&self) -> ArrayVec<u8, MAX_SIGNATURE_LEN> {
+        let mut bytes = ArrayVec::new();
+        bytes.extend(self.index.to_le_bytes());
+        bytes.extend(self.randomizer);
+        for cv in self.wots.iter().chain(&self.auth_path) {
+            bytes.extend(cv.iter().copied());
+        }
+        bytes
+    }
+
+    /// The index of the one-time key that made this signature.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+/// The error type for this module.
+///
+/// The `.to_string()` representation of this error currently distinguishes between the different
+/// kinds of errors. This is to help with logging and debugging, but it isn't a stable API detail,
+/// and it may change at any time.
+#[derive(Clone, Debug)]
+pub struct HbsError(HbsErrorInner);
+
+#[derive(Clone, Debug)]
+enum HbsErrorInner {
+    InvalidHeight(u8),
+    IndexOutOfRange,
+    KeyExhausted,
+    InvalidLen(usize),
+    VerificationFailed,
+}
+
+impl fmt::Display for HbsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            HbsErrorInner::InvalidHeight(height) => write!(f, "invalid tree height: {}", height),
+            HbsErrorInner::IndexOutOfRange => write!(f, "one-time key index out of range"),
+            HbsErrorInner::KeyExhausted => write!(f, "all one-time keys are used up"),
+            HbsErrorInner::InvalidLen(len) => write!(f, "invalid signature length: {}", len),
+            HbsErrorInner::VerificationFailed => write!(f, "signature verification failed"),
+        }
+    }
+}
+
+impl std::error::Error for HbsError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::paint_test_input;
+
+    fn test_seed() -> [u8; KEY_LEN] {
+        let mut seed = [0; KEY_LEN];
+        paint_test_input(&mut seed);
+        seed
+    }
+
+    // A height-3 key's public key and second signature. Only the hash of the signature is kept, to
+    // keep it short.
+    #[test]
+    fn test_vectors() {
+        let mut key = SigningKey::new(&test_seed(), 3).unwrap();
+        let public_key = key.public_key().to_bytes();
+        assert_eq!(hex::encode(public_key), PUBLIC_KEY_VECTOR);
+        key.sign(b"first").unwrap();
+        let signature = key.sign(b"hello world").unwrap().to_bytes();
+        assert_eq!(signature.len(), signature_len(3));
+        assert_eq!(
+            crate::hash(&signature).to_hex().as_str(),
+            SIGNATURE_HASH_VECTOR
+        );
+    }
+
+    const PUBLIC_KEY_VECTOR: &str = concat!(
+        "03",
+        "4e3cd306da2c6ce2921bd5a90841aaefa8120196f2e19590ca5f16b7ba08fd3b",
+        "a4ddd8594b6a3c3da9878c9dbb99497ea8ec16103c42b789be4976f91a8708c2",
+    );
+    const SIGNATURE_HASH_VECTOR: &str =
+        "fad1d9f9374a976c8f63f0f3f15b04ce477832728477b653748425f34a773842";
+
+    // Recompute one leaf's signature chains one hash at a time with the reference implementation,
+    // without advance_chains.
+    #[test]
+    fn test_compare_unbatched() {
+        let public_seed = [1; KEY_LEN];
+        let secret_seed = [2; KEY_LEN];
+        let leaf = 5;
+        let mut chains = wots_secret(&secret_seed, leaf);
+        let mut expected = chains;
+        for (j, value) in expected.iter_mut().enumerate() {
+            for step in 0..W as u32 - 1 {
+                let mut block = [0; BLOCK_LEN];
+                block[..ADDR_LEN].copy_from_slice(&address(CHAIN_TAG, leaf, j as u32, step));
+                block[ADDR_LEN..].copy_from_slice(value);
+                let mut reference = reference_impl::Hasher::new_keyed(&public_seed);
+                reference.update(&block);
+                reference.finalize(value);
+            }
+        }
+        let mut start = [0; WOTS_LEN];
+        paint_test_input(&mut start);
+        for s in &mut start {
+            *s %= W as u8;
+        }
+        // Advancing in two stages, from 0 to start and from start to the end, matches.
+        advance_chains(&public_seed, leaf, &mut chains, &[0; WOTS_LEN], &start);
+        advance_chains(
+            &public_seed,
+            leaf,
+            &mut chains,
+            &start,
+            &[W as u8 - 1; WOTS_LEN],
+        );
+        assert_eq!(chains, expected);
+    }
+
+    #[test]
+    fn test_digits() {
+        let zeros = digits(&Hash::from_bytes([0; OUT_LEN]));
+        assert_eq!(zeros[..DIGEST_DIGITS], [0; DIGEST_DIGITS]);
+        // The checksum is 64 * 15 = 960 = 0x3c0.
+        assert_eq!(zeros[DIGEST_DIGITS..], [3, 0xc, 0]);
+        let mixed = digits(&Hash::from_bytes([0x1f; OUT_LEN]));
+        assert_eq!(mixed[..4], [1, 0xf, 1, 0xf]);
+        // The checksum is 32 * 14 = 448 = 0x1c0.
+        assert_eq!(mixed[DIGEST_DIGITS..], [1, 0xc, 0]);
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let height = 2;
+        let mut key = SigningKey::new(&test_seed(), height).unwrap();
+        let public_key = key.public_key();
+        assert_eq!(public_key.height(), height);
+        for i in 0..4 {
+            assert_eq!(key.next_index(), i);
+            assert_eq!(key.remaining_signatures(), 4 - i);
+            let message = [i as u8; 100];
+            let signature = key.sign(&message).unwrap();
+            assert_eq!(signature.index(), i);
+            public_key.verify(&message, &signature).unwrap();
+            assert!(public_key.verify(&message[1..], &signature).is_err());
+
+            let decoded = Signature::from_bytes(&signature.to_bytes()).unwrap();
+            assert_eq!(decoded, signature);
+            public_key.verify(&message, &decoded).unwrap();
+
+            // Corrupting any part of the signature fails.
+            let bytes = signature.to_bytes();
+            for &position in &[4, 4 + OUT_LEN, signature_len(0) - 1, bytes.len() - 1] {
+                let mut corrupt = bytes.clone();
+                corrupt[position] ^= 1;
+                let corrupt = Signature::from_bytes(&corrupt).unwrap();
+                assert!(public_key.verify(&message, &corrupt).is_err());
+            }
+        }
+        assert_eq!(key.remaining_signatures(), 0);
+        assert!(key.sign(b"one too many").is_err());
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut key = SigningKey::new(&test_seed(), 3).unwrap();
+        key.sign(b"zero").unwrap();
+        key.sign(b"one").unwrap();
+        let expected = key.sign(b"two").unwrap();
+
+        let mut restored = SigningKey::restore(&test_seed(), 3, 2).unwrap();
+        assert_eq!(restored.public_key(), key.public_key());
+        assert_eq!(restored.sign(b"two").unwrap(), expected);
+
+        SigningKey::restore(&test_seed(), 3, 8).unwrap();
+        assert!(SigningKey::restore(&test_seed(), 3, 9).is_err());
+        assert!(SigningKey::new(&test_seed(), 0).is_err());
+        assert!(SigningKey::new(&test_seed(), MAX_HEIGHT + 1).is_err());
+
+        // A different seed gives a different key.
+        let other = SigningKey::new(&[0; KEY_LEN], 3).unwrap();
+        assert_ne!(other.public_key(), key.public_key());
+        assert!(other.public_key().verify(b"two", &expected).is_err());
+    }
+
+    #[test]
+    fn test_encoding_errors() {
+        let mut key = SigningKey::new(&test_seed(), 2).unwrap();
+        let public_key = key.public_key();
+        let decoded = PublicKey::from_bytes(&public_key.to_bytes()).unwrap();
+        assert_eq!(decoded, public_key);
+        let mut bad_height = public_key.to_bytes();
+        bad_height[0] = 0;
+        assert!(PublicKey::from_bytes(&bad_height).is_err());
+        bad_height[0] = MAX_HEIGHT + 1;
+        assert!(PublicKey::from_bytes(&bad_height).is_err());
+
+        let signature = key.sign(b"message").unwrap().to_bytes();
+        assert!(Signature::from_bytes(&signature[..signature.len() - 1]).is_err());
+        assert!(Signature::from_bytes(&signature[..signature_len(0)]).is_err());
+        let mut out_of_range = signature.clone();
+        out_of_range[0] = 4;
+        assert!(Signature::from_bytes(&out_of_range).is_err());
+
+        // A signature from a tree of a different height doesn't verify.
+        let mut taller = SigningKey::new(&test_seed(), 3).unwrap();
+        let taller_signature = taller.sign(b"message").unwrap();
+        assert!(public_key.verify(b"message", &taller_signature).is_err());
+    }
+}
@@ -1,25 +1,354 @@
-//! Helper functions for efficient IO.
+//! Reader and writer adapters that hash the bytes passing through them.
+//!
+//! A [`HashingReader`] wraps any [`Read`] implementation, and a [`HashingWriter`] wraps any
+//! [`Write`] implementation. Both forward bytes unchanged, while also feeding them to a
+//! [`Hasher`]. This is useful when data needs to go somewhere else anyway, for example when
+//! writing a download to disk, and the hash should be computed along the way rather than in a
+//! second pass.
+//!
+//! A [`VerifyingReader`] also hashes what passes through it, and it fails at EOF if the hash
+//! doesn't match an expected value.
+//!
+//! This module requires the `std` Cargo feature, which is enabled by default.
+//!
+//! # Example
+//!
+//! ```
+//! # fn main() -> std::io::Result<()> {
+//! use blake3::io::HashingWriter;
+//!
+//! let mut source: &[u8] = b"some input bytes";
+//! let mut writer = HashingWriter::new(Vec::new());
+//! std::io::copy(&mut source, &mut writer)?;
+//! let (output, hash) = writer.into_parts();
+//! assert_eq!(output, b"some input bytes");
+//! assert_eq!(hash, blake3::hash(b"some input bytes"));
+//! # Ok(())
+//! # }
+//! ```
 
+use crate::{Hash, Hasher};
 #[cfg(feature = "mmap")]
 use std::io::Seek;
+use std::io::{Read, Write};
 
-#[cfg(feature = "std")]
-pub(crate) fn copy_wide(
-    mut reader: impl std::io::Read,
-    hasher: &mut crate::Hasher,
+/// A [`Read`] adapter that hashes everything read through it.
+///
+/// Bytes are hashed as they're returned to the caller, so the [`Hasher`] only sees what the
+/// caller has actually read. Read to EOF before calling [`into_parts`](Self::into_parts) if the
+/// hash should cover the whole input.
+#[derive(Clone, Debug)]
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Hasher,
+}
+
+impl<R: Read> HashingReader<R> {
+    /// Wrap a reader, hashing in the default mode.
+    pub fn new(inner: R) -> Self {
+        Self::with_hasher(inner, Hasher::new())
+    }
+
+    /// Wrap a reader, hashing with the given [`Hasher`]. This supports the keyed and key
+    /// derivation modes, as well as resuming from a hasher that has already seen some input.
+    pub fn with_hasher(inner: R, hasher: Hasher) -> Self {
+        Self { inner, hasher }
+    }
+
+    /// The running [`Hasher`], which has seen every byte read so far.
+    pub fn hasher(&self) -> &Hasher {
+        &self.hasher
+    }
+
+    /// Get a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner reader. Bytes read directly from the inner reader
+    /// aren't hashed.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Return the inner reader and the hash of every byte read so far.
+    pub fn into_parts(self) -> (R, Hash) {
+        let hash = self.hasher.finalize();
+        (self.inner, hash)
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// A [`Write`] adapter that hashes everything written through it.
+///
+/// Only the bytes that the inner writer accepts are hashed, so a short write doesn't throw off
+/// the hash.
+#[derive(Clone, Debug)]
+pub struct HashingWriter<W> {
+    inner: W,
+    hasher: Hasher,
+}
+
+impl<W: Write> HashingWriter<W> {
+    /// Wrap a writer, hashing in the default mode.
+    pub fn new(inner: W) -> Self {
+        Self::with_hasher(inner, Hasher::new())
+    }
+
+    /// Wrap a writer, hashing with the given [`Hasher`]. This supports the keyed and key
+    /// derivation modes, as well as resuming from a hasher that has already seen some input.
+    pub fn with_hasher(inner: W, hasher: Hasher) -> Self {
+        Self { inner, hasher }
+    }
+
+    /// The running [`Hasher`], which has seen every byte written so far.
+    pub fn hasher(&self) -> &Hasher {
+        &self.hasher
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner writer. Bytes written directly to the inner writer
+    /// aren't hashed.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Return the inner writer and the hash of every byte written so far. This doesn't flush the
+    /// inner writer.
+    pub fn into_parts(self) -> (W, Hash) {
+        let hash = self.hasher.finalize();
+        (self.inner, hash)
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A [`Read`] adapter that checks the hash of its input against an expected value at EOF.
+///
+/// Bytes are forwarded to the caller as they're read, and they're hashed along the way. When the
+/// inner reader reaches EOF, the hash is compared to the expected [`Hash`](struct@Hash) in constant time. If
+/// they match, the caller sees EOF as usual. If they don't, the caller gets an [`std::io::Error`]
+/// of kind [`InvalidData`](std::io::ErrorKind::InvalidData), wrapping a [`VerificationError`], and
+/// every later read returns the same error.
+///
+/// Note that the bytes before EOF are **not verified** as they arrive. The caller must not act on
+/// them until it has seen EOF without an error, for example by writing them to a temporary file
+/// and only moving it into place afterwards. To verify input incrementally instead, see the
+/// [`verified`](crate::verified) module.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use blake3::io::{VerificationError, VerifyingReader};
+/// use std::io::prelude::*;
+///
+/// let expected = blake3::hash(b"foobarbaz");
+/// let mut reader = VerifyingReader::new(&b"foobarbaz"[..], expected);
+/// let mut output = Vec::new();
+/// reader.read_to_end(&mut output)?;
+///
+/// let mut reader = VerifyingReader::new(&b"foobarbaz!"[..], expected);
+/// let err = reader.read_to_end(&mut output).unwrap_err();
+/// assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+/// assert!(err.get_ref().unwrap().is::<VerificationError>());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct VerifyingReader<R> {
+    inner: R,
+    hasher: Hasher,
+    expected: Hash,
+    state: VerifyingState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum VerifyingState {
+    Reading,
+    Verified,
+    Failed,
+}
+
+impl<R: Read> VerifyingReader<R> {
+    /// Wrap a reader, expecting its contents to hash to `expected` in the default mode.
+    pub fn new(inner: R, expected: Hash) -> Self {
+        Self::with_hasher(inner, Hasher::new(), expected)
+    }
+
+    /// Wrap a reader, hashing with the given [`Hasher`]. This supports the keyed and key
+    /// derivation modes.
+    pub fn with_hasher(inner: R, hasher: Hasher, expected: Hash) -> Self {
+        Self {
+            inner,
+            hasher,
+            expected,
+            state: VerifyingState::Reading,
+        }
+    }
+
+    /// Whether the inner reader has reached EOF and its hash matched.
+    pub fn is_verified(&self) -> bool {
+        self.state == VerifyingState::Verified
+    }
+
+    /// Get a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner reader. Bytes read directly from the inner reader
+    /// aren't hashed, which will most likely cause verification to fail.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Return the inner reader, discarding the verification state.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for VerifyingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.state {
+            VerifyingState::Verified => return Ok(0),
+            VerifyingState::Failed => return Err(VerificationError(()).into()),
+            VerifyingState::Reading => {}
+        }
+        // An empty read returns 0 without meaning EOF.
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.hasher.update(&buf[..n]);
+            return Ok(n);
+        }
+        // This comparison is constant-time.
+        if self.hasher.finalize() == self.expected {
+            self.state = VerifyingState::Verified;
+            Ok(0)
+        } else {
+            self.state = VerifyingState::Failed;
+            Err(VerificationError(()).into())
+        }
+    }
+}
+
+/// The error type wrapped by the [`std::io::Error`] that [`VerifyingReader`] returns when the
+/// hash of its input doesn't match.
+///
+/// This doesn't include the computed hash. In the keyed mode, that hash is a valid MAC for
+/// whatever input the reader received, and it shouldn't end up in logs or error messages.
+#[derive(Clone, Debug)]
+pub struct VerificationError(());
+
+impl core::fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("BLAKE3 hash mismatch")
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+impl From<VerificationError> for std::io::Error {
+    fn from(e: VerificationError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+    }
+}
+
+// The size of the read buffer in copy_wide and its async equivalents.
+const COPY_WIDE_BUF_LEN: usize = 65536;
+
+// The step shared by copy_wide and its async equivalents: hash the bytes from one read and add
+// them to the total. Returns Some at EOF or on an error, and None to keep reading.
+fn copy_wide_step(
+    result: std::io::Result<&[u8]>,
+    hasher: &mut Hasher,
+    total: &mut u64,
+) -> Option<std::io::Result<u64>> {
+    match result {
+        Ok([]) => Some(Ok(*total)),
+        Ok(bytes) => {
+            hasher.update(bytes);
+            *total += bytes.len() as u64;
+            None
+        }
+        // see test_update_reader_interrupted
+        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => None,
+        Err(e) => Some(Err(e)),
+    }
+}
+
+pub(crate) fn copy_wide(mut reader: impl Read, hasher: &mut Hasher) -> std::io::Result<u64> {
+    let mut buffer = [0; COPY_WIDE_BUF_LEN];
+    let mut total = 0;
+    loop {
+        let result = reader.read(&mut buffer).map(|n| &buffer[..n]);
+        if let Some(done) = copy_wide_step(result, hasher, &mut total) {
+            return done;
+        }
+    }
+}
+
+// The async equivalents of copy_wide. These use a heap buffer, because a 64 KiB array would
+// otherwise live in the returned future.
+#[cfg(feature = "async")]
+pub(crate) async fn copy_wide_futures(
+    mut reader: impl futures_io::AsyncRead + Unpin,
+    hasher: &mut Hasher,
 ) -> std::io::Result<u64> {
-    let mut buffer = [0; 65536];
+    let mut buffer = vec![0; COPY_WIDE_BUF_LEN];
     let mut total = 0;
     loop {
-        match reader.read(&mut buffer) {
-            Ok(0) => return Ok(total),
-            Ok(n) => {
-                hasher.update(&buffer[..n]);
-                total += n as u64;
-            }
-            // see test_update_reader_interrupted
-            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
-            Err(e) => return Err(e),
+        let result =
+            core::future::poll_fn(|cx| core::pin::Pin::new(&mut reader).poll_read(cx, &mut buffer))
+                .await
+                .map(|n| &buffer[..n]);
+        if let Some(done) = copy_wide_step(result, hasher, &mut total) {
+            return done;
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub(crate) async fn copy_wide_tokio(
+    mut reader: impl tokio::io::AsyncRead + Unpin,
+    hasher: &mut Hasher,
+) -> std::io::Result<u64> {
+    let mut buffer = vec![0; COPY_WIDE_BUF_LEN];
+    let mut total = 0;
+    loop {
+        let mut read_buf = tokio::io::ReadBuf::new(&mut buffer);
+        let result = core::future::poll_fn(|cx| {
+            core::pin::Pin::new(&mut reader).poll_read(cx, &mut read_buf)
+        })
+        .await
+        .map(|()| read_buf.filled());
+        if let Some(done) = copy_wide_step(result, hasher, &mut total) {
+            return done;
         }
     }
 }
@@ -68,3 +397,163 @@ pub(crate) fn maybe_mmap_file(file: &mut std::fs::File) -> std::io::Result<Optio
     file.rewind()?;
     Ok(None)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::paint_test_input;
+
+    // A writer that accepts at most 7 bytes per call, to exercise short writes.
+    struct ShortWriter(Vec<u8>);
+
+    impl Write for ShortWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let n = core::cmp::min(buf.len(), 7);
+            self.0.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_hashing_reader() -> std::io::Result<()> {
+        let mut input = vec![0; 100_000];
+        paint_test_input(&mut input);
+
+        let mut reader = HashingReader::new(&input[..]);
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output)?;
+        assert_eq!(reader.hasher().count(), input.len() as u64);
+        let (rest, hash) = reader.into_parts();
+        assert!(rest.is_empty());
+        assert_eq!(output, input);
+        assert_eq!(hash, crate::hash(&input));
+
+        // A partial read only hashes what was read.
+        let mut reader = HashingReader::new(&input[..]);
+        let mut buf = [0; 1000];
+        reader.read_exact(&mut buf)?;
+        let (rest, hash) = reader.into_parts();
+        assert_eq!(rest.len(), input.len() - 1000);
+        assert_eq!(hash, crate::hash(&input[..1000]));
+
+        // Keyed mode.
+        let key = [42; 32];
+        let mut reader = HashingReader::with_hasher(&input[..], Hasher::new_keyed(&key));
+        std::io::copy(&mut reader, &mut std::io::sink())?;
+        assert_eq!(reader.into_parts().1, crate::keyed_hash(&key, &input));
+        Ok(())
+    }
+
+    fn verification_error(e: &std::io::Error) -> bool {
+        e.kind() == std::io::ErrorKind::InvalidData
+            && e.get_ref()
+                .map_or(false, |inner| inner.is::<VerificationError>())
+    }
+
+    #[test]
+    fn test_verifying_reader() -> std::io::Result<()> {
+        for &case in crate::test::TEST_CASES {
+            let mut input = vec![0; case];
+            paint_test_input(&mut input);
+            let expected = crate::hash(&input);
+
+            let mut reader = VerifyingReader::new(&input[..], expected);
+            let mut output = Vec::new();
+            reader.read_to_end(&mut output)?;
+            assert_eq!(output, input);
+            assert!(reader.is_verified());
+            // Reads after EOF keep returning EOF.
+            assert_eq!(reader.read(&mut [0; 10])?, 0);
+
+            // Appending a byte changes the hash.
+            let mut longer = input.clone();
+            longer.push(0);
+            let mut reader = VerifyingReader::new(&longer[..], expected);
+            let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+            assert!(verification_error(&err));
+            assert!(!reader.is_verified());
+            // The failure is sticky.
+            let err = reader.read(&mut [0; 10]).unwrap_err();
+            assert!(verification_error(&err));
+
+            // Flipping a bit changes the hash.
+            if case > 0 {
+                let mut corrupt = input.clone();
+                corrupt[case / 2] ^= 1;
+                let mut reader = VerifyingReader::new(&corrupt[..], expected);
+                let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+                assert!(verification_error(&err));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_verifying_reader_keyed() -> std::io::Result<()> {
+        let mut input = vec![0; 10_000];
+        paint_test_input(&mut input);
+        let expected = crate::keyed_hash(&crate::test::TEST_KEY, &input);
+
+        let hasher = Hasher::new_keyed(&crate::test::TEST_KEY);
+        let mut reader = VerifyingReader::with_hasher(&input[..], hasher, expected);
+        std::io::copy(&mut reader, &mut std::io::sink())?;
+        assert!(reader.is_verified());
+
+        // The same input in the default mode doesn't match.
+        let mut reader = VerifyingReader::new(&input[..], expected);
+        let err = std::io::copy(&mut reader, &mut std::io::sink()).unwrap_err();
+        assert!(verification_error(&err));
+        Ok(())
+    }
+
+    #[test]
+    fn test_verifying_reader_empty_buf() -> std::io::Result<()> {
+        // An empty read isn't EOF, and it mustn't trigger verification.
+        let mut reader = VerifyingReader::new(&b"foo"[..], crate::hash(b"foo"));
+        assert_eq!(reader.read(&mut [])?, 0);
+        assert!(!reader.is_verified());
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output)?;
+        assert_eq!(output, b"foo");
+        assert!(reader.is_verified());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hashing_writer() -> std::io::Result<()> {
+        let mut input = vec![0; 100_000];
+        paint_test_input(&mut input);
+
+        let mut writer = HashingWriter::new(Vec::new());
+        writer.write_all(&input)?;
+        writer.flush()?;
+        assert_eq!(writer.get_ref().len(), input.len());
+        let (output, hash) = writer.into_parts();
+        assert_eq!(output, input);
+        assert_eq!(hash, crate::hash(&input));
+
+        // Short writes only hash what the inner writer accepted.
+        let mut writer = HashingWriter::new(ShortWriter(Vec::new()));
+        assert_eq!(writer.write(&input)?, 7);
+        assert_eq!(writer.hasher().finalize(), crate::hash(&input[..7]));
+        writer.write_all(&input[7..])?;
+        let (output, hash) = writer.into_parts();
+        assert_eq!(output.0, input);
+        assert_eq!(hash, crate::hash(&input));
+
+        // Derive-key mode.
+        let context = "blake3 io test context";
+        let mut writer =
+            HashingWriter::with_hasher(std::io::sink(), Hasher::new_derive_key(context));
+        writer.write_all(&input)?;
+        assert_eq!(
+            writer.into_parts().1.as_bytes(),
+            &crate::derive_key(context, &input),
+        );
+        Ok(())
+    }
+}
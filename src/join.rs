@@ -1,25 +1,36 @@
-//! The multi-threading abstractions used by `Hasher::update_with_join`.
+//! The multi-threading abstractions used by [`Hasher::update_with_join`].
 //!
-//! Different implementations of the `Join` trait determine whether
-//! `Hasher::update_with_join` performs multi-threading on sufficiently large
-//! inputs. The `SerialJoin` implementation is single-threaded, and the
-//! `RayonJoin` implementation (gated by the `rayon` feature) is multi-threaded.
-//! Interfaces other than `Hasher::update_with_join`, like [`hash`](crate::hash)
-//! and [`Hasher::update`](crate::Hasher::update), always use `SerialJoin`
-//! internally.
+//! Different implementations of the [`Join`] trait determine whether
+//! [`Hasher::update_with_join`] performs multi-threading on sufficiently large
+//! inputs. The [`SerialJoin`] implementation is single-threaded, the
+//! `RayonJoin` implementation (gated by the `rayon` feature) runs on the
+//! Rayon thread pool, and the [`ScopedThreadJoin`] implementation (gated by the
+//! `std` feature) spawns scoped threads from the standard library. Interfaces
+//! other than [`Hasher::update_with_join`], like [`hash`](crate::hash) and
+//! [`Hasher::update`], always use `SerialJoin` internally.
 //!
-//! The `Join` trait is an almost exact copy of the [`rayon::join`] API, and
-//! `RayonJoin` is the only non-trivial implementation. Previously this trait
-//! was public, but currently it's been re-privatized, as it's both 1) of no
-//! value to most callers and 2) a pretty big implementation detail to commit
-//! to.
+//! The `Join` trait is an almost exact copy of the [`rayon::join`] API. Callers
+//! who run their own thread pool or executor can implement it to hash large
+//! inputs on that pool, without depending on Rayon. See the example on
+//! [`Hasher::update_with_join`].
 //!
 //! [`rayon::join`]: https://docs.rs/rayon/1.3.0/rayon/fn.join.html
+//! [`Hasher::update_with_join`]: crate::Hasher::update_with_join
+//! [`Hasher::update`]: crate::Hasher::update
 
 /// The trait that abstracts over single-threaded and multi-threaded recursion.
 ///
 /// See the [`join` module docs](index.html) for more details.
 pub trait Join {
+    /// Run `oper_a` and `oper_b`, potentially in parallel, and return both of
+    /// their results.
+    ///
+    /// Implementations must run both closures to completion before returning,
+    /// and if either of them panics, the panic should propagate to the caller.
+    /// Both closures may themselves call `join` recursively, so
+    /// implementations that use a fixed pool of threads need to avoid
+    /// blocking a worker thread while it waits for the other side. (Rayon's
+    /// work stealing handles this by running other jobs while it waits.)
     fn join<A, B, RA, RB>(oper_a: A, oper_b: B) -> (RA, RB)
     where
         A: FnOnce() -> RA + Send,
@@ -71,6 +82,100 @@ impl Join for RayonJoin {
     }
 }
 
+/// An implementation of `Join` based on [`std::thread::scope`], for callers
+/// who want multithreading without the Rayon dependency. This implementation
+/// is gated by the `std` feature.
+///
+/// Each call to `join` runs the right side on a new scoped thread, if one is
+/// available, and the left side on the calling thread. The number of these
+/// threads running at once, across the whole process, is limited to one less
+/// than [`std::thread::available_parallelism`]. When that limit is reached, or
+/// if spawning a thread fails, both sides run serially on the calling thread.
+///
+/// Spawning a thread is much more expensive than handing a job to a thread
+/// pool, so this implementation is best suited to hashing a few very large
+/// inputs. Like `RayonJoin`, it's slower than [`SerialJoin`] for small
+/// inputs.
+///
+/// See the [`join` module docs](index.html) for more details.
+#[cfg(feature = "std")]
+pub enum ScopedThreadJoin {}
+
+// The number of threads spawned by ScopedThreadJoin that are currently running.
+#[cfg(feature = "std")]
+static SCOPED_THREADS_ACTIVE: core::sync::atomic::AtomicUsize =
+    core::sync::atomic::AtomicUsize::new(0);
+
+// One less than available_parallelism, with usize::MAX meaning it hasn't been checked yet.
+#[cfg(feature = "std")]
+static SCOPED_THREADS_MAX: core::sync::atomic::AtomicUsize =
+    core::sync::atomic::AtomicUsize::new(usize::MAX);
+
+#[cfg(feature = "std")]
+fn try_reserve_scoped_thread() -> bool {
+    use core::sync::atomic::Ordering::Relaxed;
+    let mut max = SCOPED_THREADS_MAX.load(Relaxed);
+    if max == usize::MAX {
+        max = std::thread::available_parallelism().map_or(1, |n| n.get()) - 1;
+        SCOPED_THREADS_MAX.store(max, Relaxed);
+    }
+    SCOPED_THREADS_ACTIVE
+        .fetch_update(Relaxed, Relaxed, |active| {
+            if active < max {
+                Some(active + 1)
+            } else {
+                None
+            }
+        })
+        .is_ok()
+}
+
+// Releases a reserved thread when the spawned closure returns or panics.
+#[cfg(feature = "std")]
+struct ScopedThreadGuard;
+
+#[cfg(feature = "std")]
+impl Drop for ScopedThreadGuard {
+    fn drop(&mut self) {
+        SCOPED_THREADS_ACTIVE.fetch_sub(1, core::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "std")]
+impl Join for ScopedThreadJoin {
+    fn join<A, B, RA, RB>(oper_a: A, oper_b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA + Send,
+        B: FnOnce() -> RB + Send,
+        RA: Send,
+        RB: Send,
+    {
+        if !try_reserve_scoped_thread() {
+            return (oper_a(), oper_b());
+        }
+        let guard = ScopedThreadGuard;
+        // The spawned closure only borrows oper_b, so that if spawning fails, we still have it.
+        let oper_b = std::sync::Mutex::new(Some(oper_b));
+        let take_oper_b = || oper_b.lock().unwrap().take().unwrap();
+        std::thread::scope(|scope| {
+            let spawned = std::thread::Builder::new().spawn_scoped(scope, || {
+                let _guard = guard;
+                take_oper_b()()
+            });
+            match spawned {
+                Ok(handle) => {
+                    let result_a = oper_a();
+                    match handle.join() {
+                        Ok(result_b) => (result_a, result_b),
+                        Err(panic) => std::panic::resume_unwind(panic),
+                    }
+                }
+                Err(_) => (oper_a(), take_oper_b()()),
+            }
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -89,4 +194,33 @@ mod test {
         let oper_b = || 2 + 2;
         assert_eq!((2, 4), RayonJoin::join(oper_a, oper_b));
     }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_scoped_thread_join() {
+        let oper_a = || 1 + 1;
+        let oper_b = || 2 + 2;
+        assert_eq!((2, 4), ScopedThreadJoin::join(oper_a, oper_b));
+
+        // Nested joins beyond the thread limit still work, and the limit is restored afterwards.
+        fn sum(range: core::ops::Range<u64>) -> u64 {
+            if range.end - range.start <= 1 {
+                return range.start;
+            }
+            let mid = range.start + (range.end - range.start) / 2;
+            let (left, right) =
+                ScopedThreadJoin::join(|| sum(range.start..mid), || sum(mid..range.end));
+            left + right
+        }
+        assert_eq!(sum(0..1000), 499500);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_scoped_thread_join_panic() {
+        let result =
+            std::panic::catch_unwind(|| ScopedThreadJoin::join(|| 1, || -> i32 { panic!("oops") }));
+        assert!(result.is_err());
+        assert_eq!((1, 2), ScopedThreadJoin::join(|| 1, || 2));
+    }
 }
@@ -0,0 +1,260 @@
+//! An extract-then-expand key derivation function, in the style of
+//! [HKDF](https://datatracker.ietf.org/doc/html/rfc5869).
+//!
+//! [`derive_key`](crate::derive_key) is the right tool when you have uniformly random key
+//! material and a hardcoded context string. This module covers the more general case:
+//!
+//! - [`extract`] takes an optional salt and input key material that might not be uniformly
+//!   random, like a Diffie-Hellman shared secret, and returns a pseudorandom key, a [`Prk`].
+//! - [`Prk::expand`] takes an application-specific `info` string and returns an
+//!   [`OutputReader`] of unlimited length, so that several keys and nonces can be read from it
+//!   one after another.
+//! - [`Prk::expand_label`] fills a single output, given a label, a context, and the output
+//!   length. Different labels, contexts, or lengths give independent outputs.
+//!
+//! # Construction
+//!
+//! All integers below are encoded as 8 little-endian bytes, and `||` is concatenation.
+//!
+//! ```text
+//! salt_key = derive_key("BLAKE3 2026-10-15 kdf extract salt v1", salt)
+//! prk      = keyed_hash(salt_key, ikm)
+//!
+//! expand(prk, info)
+//!     = XOF of keyed_hash(prk, 0x00 || len(info) || info)
+//! expand_label(prk, label, context, L)
+//!     = first L bytes of the XOF of
+//!       keyed_hash(prk, 0x01 || L || len(label) || label || len(context) || context)
+//! ```
+//!
+//! The salt goes through [`derive_key`](crate::derive_key) so that it can have any length,
+//! including zero. An empty salt is allowed, but as with HKDF, a random salt makes the
+//! extracted key stronger when the input key material isn't uniformly random. The leading byte
+//! and the length prefixes keep every `expand` and `expand_label` input distinct.
+//!
+//! Like [`derive_key`](crate::derive_key), **this is not a password hash.** Input key material
+//! must have high entropy.
+//!
+//! # Example
+//!
+//! ```
+//! use blake3::kdf;
+//!
+//! # let shared_secret = [1; 32];
+//! # let handshake_salt = [2; 32];
+//! let prk = kdf::extract(&handshake_salt, &shared_secret);
+//!
+//! // Read several outputs from one expansion.
+//! let mut reader = prk.expand(b"example.com 2026-10-15 session keys v1");
+//! let mut encryption_key = [0; 32];
+//! let mut mac_key = [0; 32];
+//! let mut nonce = [0; 24];
+//! reader.fill(&mut encryption_key);
+//! reader.fill(&mut mac_key);
+//! reader.fill(&mut nonce);
+//!
+//! // Or fill each one from its own label.
+//! let mut client_key = [0; 32];
+//! prk.expand_label("client key", b"transcript hash", &mut client_key);
+//! ```
+
+use crate::{Hasher, OutputReader, KEY_LEN};
+use core::fmt;
+
+const EXTRACT_SALT_CONTEXT: &str = "BLAKE3 2026-10-15 kdf extract salt v1";
+
+const EXPAND_TAG: u8 = 0x00;
+const EXPAND_LABEL_TAG: u8 = 0x01;
+
+/// A pseudorandom key, the output of [`extract`].
+///
+/// The only intended use of a `Prk` is with [`expand`](Prk::expand) and
+/// [`expand_label`](Prk::expand_label). In particular, don't use its bytes as a key for
+/// [`keyed_hash`](crate::keyed_hash) directly.
+#[derive(Clone)]
+pub struct Prk([u8; KEY_LEN]);
+
+impl Prk {
+    /// Use existing key material as a `Prk`, skipping [`extract`].
+    ///
+    /// This is only appropriate if `bytes` is already uniformly random and secret, for example a
+    /// key from a CSPRNG. When in doubt, use [`extract`].
+    pub fn from_bytes(bytes: [u8; KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// The raw bytes of this `Prk`, for example to store it.
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        &self.0
+    }
+
+    /// Return an [`OutputReader`] of unlimited length for the given `info`.
+    ///
+    /// Reading `n` bytes and then `m` more is the same as reading `n + m` bytes at once, so
+    /// several keys can be read one after another. Callers who need each output to depend on its
+    /// length, or who'd rather not keep track of offsets, should use
+    /// [`expand_label`](Prk::expand_label) instead.
+    pub fn expand(&self, info: &[u8]) -> OutputReader {
+        let mut hasher = Hasher::new_keyed(&self.0);
+        hasher.update(&[EXPAND_TAG]);
+        hasher.update(&(info.len() as u64).to_le_bytes());
+        hasher.update(info);
+        hasher.finalize_xof()
+    }
+
+    /// Fill `out` with output specific to `label`, `context`, and the length of `out`.
+    ///
+    /// The label is typically a hardcoded string that names the purpose of the output, like
+    /// `"client key"`, and the context is typically variable, like a handshake transcript hash.
+    /// Either can be empty.
+    pub fn expand_label(&self, label: &str, context: &[u8], out: &mut [u8]) {
+        let mut hasher = Hasher::new_keyed(&self.0);
+        hasher.update(&[EXPAND_LABEL_TAG]);
+        hasher.update(&(out.len() as u64).to_le_bytes());
+        hasher.update(&(label.len() as u64).to_le_bytes());
+        hasher.update(label.as_bytes());
+        hasher.update(&(context.len() as u64).to_le_bytes());
+        hasher.update(context);
+        hasher.finalize_xof().fill(out);
+    }
+}
+
+// Don't derive(Debug), because the key is secret.
+impl fmt::Debug for Prk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Prk").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for Prk {
+    fn zeroize(&mut self) {
+        // Destructuring to trigger compile error as a reminder to update this impl.
+        let Self(bytes) = self;
+
+        bytes.zeroize();
+    }
+}
+
+/// Extract a pseudorandom key from a salt and input key material.
+///
+/// The salt can have any length, including zero. See the [module level docs](self) for details.
+pub fn extract(salt: &[u8], ikm: &[u8]) -> Prk {
+    let salt_key = crate::derive_key(EXTRACT_SALT_CONTEXT, salt);
+    Prk(*crate::keyed_hash(&salt_key, ikm).as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Recompute the construction with the reference implementation.
+    #[test]
+    fn test_compare_reference_impl() {
+        let salt = b"salt";
+        let ikm = b"input key material";
+        let mut salt_key = [0; 32];
+        let mut reference = reference_impl::Hasher::new_derive_key(EXTRACT_SALT_CONTEXT);
+        reference.update(salt);
+        reference.finalize(&mut salt_key);
+        let mut prk_bytes = [0; 32];
+        let mut reference = reference_impl::Hasher::new_keyed(&salt_key);
+        reference.update(ikm);
+        reference.finalize(&mut prk_bytes);
+        let prk = extract(salt, ikm);
+        assert_eq!(prk.as_bytes(), &prk_bytes);
+
+        let mut expected = [0; 100];
+        let mut reference = reference_impl::Hasher::new_keyed(&prk_bytes);
+        reference.update(&[0x00]);
+        reference.update(&4u64.to_le_bytes());
+        reference.update(b"info");
+        reference.finalize(&mut expected);
+        let mut output = [0; 100];
+        prk.expand(b"info").fill(&mut output);
+        assert_eq!(expected, output);
+
+        let mut reference = reference_impl::Hasher::new_keyed(&prk_bytes);
+        reference.update(&[0x01]);
+        reference.update(&100u64.to_le_bytes());
+        reference.update(&5u64.to_le_bytes());
+        reference.update(b"label");
+        reference.update(&7u64.to_le_bytes());
+        reference.update(b"context");
+        reference.finalize(&mut expected);
+        prk.expand_label("label", b"context", &mut output);
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_extract_is_derive_key_then_keyed_hash() {
+        let salt_key = crate::derive_key(EXTRACT_SALT_CONTEXT, b"salt");
+        let expected = crate::keyed_hash(&salt_key, b"ikm");
+        assert_eq!(expected, *extract(b"salt", b"ikm").as_bytes());
+    }
+
+    #[test]
+    fn test_expand_is_a_stream() {
+        let prk = extract(b"salt", b"ikm");
+        let mut all_at_once = [0; 88];
+        prk.expand(b"info").fill(&mut all_at_once);
+        let mut reader = prk.expand(b"info");
+        let mut first = [0; 32];
+        let mut second = [0; 32];
+        let mut third = [0; 24];
+        reader.fill(&mut first);
+        reader.fill(&mut second);
+        reader.fill(&mut third);
+        assert_eq!(all_at_once[..32], first);
+        assert_eq!(all_at_once[32..64], second);
+        assert_eq!(all_at_once[64..], third);
+    }
+
+    #[test]
+    fn test_outputs_are_independent() {
+        let prk = extract(b"salt", b"ikm");
+        let mut outputs = arrayvec::ArrayVec::<[u8; 16], 16>::new();
+        let mut push = |out: [u8; 16]| {
+            assert!(!outputs.contains(&out));
+            outputs.push(out);
+        };
+        let mut out = [0; 16];
+        extract(b"other salt", b"ikm")
+            .expand(b"info")
+            .fill(&mut out);
+        push(out);
+        extract(b"salt", b"other ikm")
+            .expand(b"info")
+            .fill(&mut out);
+        push(out);
+        // Moving bytes between the salt and the IKM changes the key.
+        extract(b"sal", b"tikm").expand(b"info").fill(&mut out);
+        push(out);
+        prk.expand(b"info").fill(&mut out);
+        push(out);
+        prk.expand(b"other info").fill(&mut out);
+        push(out);
+        prk.expand_label("label", b"context", &mut out);
+        push(out);
+        prk.expand_label("other label", b"context", &mut out);
+        push(out);
+        prk.expand_label("label", b"other context", &mut out);
+        push(out);
+        // Moving bytes between the label and the context changes the output.
+        prk.expand_label("labelc", b"ontext", &mut out);
+        push(out);
+        // The output length is part of the input, so a shorter output isn't a prefix.
+        let mut longer = [0; 17];
+        prk.expand_label("label", b"context", &mut longer);
+        push(*arrayref::array_ref!(longer, 0, 16));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_prk_debug_is_redacted() {
+        assert_eq!(
+            std::format!("{:?}", Prk::from_bytes([0xaa; 32])),
+            "Prk { .. }"
+        );
+    }
+}
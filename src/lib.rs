@@ -35,19 +35,48 @@
 //! The `std` feature (the only feature enabled by default) enables the
 //! [`Write`] implementation and the [`update_reader`](Hasher::update_reader)
 //! method for [`Hasher`], and also the [`Read`] and [`Seek`] implementations
-//! for [`OutputReader`].
+//! for [`OutputReader`]. It also enables the [`accumulator`], [`cdc`], [`io`],
+//! and [`verified`] modules and [`ScopedThreadJoin`](join::ScopedThreadJoin).
+//!
+//! The `async` feature (disabled by default, but enabled for [docs.rs]) adds the
+//! [`update_async_reader`](Hasher::update_async_reader) and
+//! [`update_tokio_reader`](Hasher::update_tokio_reader) methods, and it implements
+//! the `AsyncWrite` traits from [`futures-io`] and [Tokio] for [`Hasher`], and the
+//! `AsyncRead` and `AsyncSeek` traits for [`OutputReader`]. It implies `std`.
 //!
 //! The `rayon` feature (disabled by default, but enabled for [docs.rs]) adds
 //! the [`update_rayon`](Hasher::update_rayon) and (in combination with `mmap`
 //! below) [`update_mmap_rayon`](Hasher::update_mmap_rayon) methods, for
-//! multithreaded hashing. However, even if this feature is enabled, all other
-//! APIs remain single-threaded.
+//! multithreaded hashing, and the [`fill_rayon`](OutputReader::fill_rayon)
+//! method, for multithreaded extended output. However, even if this feature is
+//! enabled, all other APIs remain single-threaded.
 //!
 //! The `mmap` feature (disabled by default, but enabled for [docs.rs]) adds the
 //! [`update_mmap`](Hasher::update_mmap) and (in combination with `rayon` above)
 //! [`update_mmap_rayon`](Hasher::update_mmap_rayon) helper methods for
 //! memory-mapped IO.
 //!
+//! The `pwhash` feature (disabled by default, but enabled for [docs.rs]) adds
+//! the [`pwhash`] module, memory-hard password hashing with PHC string encoding.
+//! It implies `std`.
+//!
+//! The `rng` feature (disabled by default, but enabled for [docs.rs]) adds the
+//! [`rng`] module, a deterministic CSPRNG that implements the traits from the
+//! [`rand_core`](https://crates.io/crates/rand_core) crate.
+//!
+//! The `stream` feature (disabled by default, but enabled for [docs.rs]) adds
+//! the [`stream`] module, a seekable stream cipher built from the keyed mode's
+//! extended output.
+//!
+//! The `aead-preview` feature (disabled by default) adds the experimental
+//! [`aead`] module, authenticated encryption built from the [`stream`] and
+//! [`mac`] modules. It implies `stream`. This crate makes no SemVer guarantees
+//! for this feature.
+//!
+//! The `hbs-preview` feature (disabled by default) adds the experimental
+//! [`hbs`] module, stateful hash-based signatures in the style of XMSS. It
+//! implies `std`. This crate makes no SemVer guarantees for this feature.
+//!
 //! The `zeroize` feature (disabled by default, but enabled for [docs.rs])
 //! implements
 //! [`Zeroize`](https://docs.rs/zeroize/latest/zeroize/trait.Zeroize.html) for
@@ -56,7 +85,8 @@
 //! The `serde` feature (disabled by default, but enabled for [docs.rs]) implements
 //! [`serde::Serialize`](https://docs.rs/serde/latest/serde/trait.Serialize.html) and
 //! [`serde::Deserialize`](https://docs.rs/serde/latest/serde/trait.Deserialize.html)
-//! for [`Hash`](struct@Hash).
+//! for [`Hash`](struct@Hash), and for [`Hasher`] using the state from
+//! [`export_state`](Hasher::export_state).
 //!
 //! The NEON implementation is enabled by default for AArch64 but requires the
 //! `neon` feature for other ARM targets. Not all ARMv7 CPUs support NEON, and
@@ -69,6 +99,11 @@
 //!
 //! The `traits-preview` feature enables implementations of traits from the
 //! RustCrypto [`digest`] crate, and re-exports that crate as `traits::digest`.
+//! The `stream-traits-preview` feature adds the stream cipher traits from the
+//! RustCrypto [`cipher`] crate, re-exported as `traits::cipher`, and the
+//! `aead-traits-preview` feature adds the `AeadInPlace` trait from the RustCrypto
+//! [`aead`](https://crates.io/crates/aead) crate, re-exported as `traits::aead`.
+//! Both imply `traits-preview`.
 //! However, the traits aren't stable, and they're expected to change in
 //! incompatible ways before that crate reaches 1.0. For that reason, this crate
 //! makes no SemVer guarantees for this feature, and callers who use it should
@@ -82,6 +117,9 @@
 //! [`Read`]: https://doc.rust-lang.org/std/io/trait.Read.html
 //! [`Write`]: https://doc.rust-lang.org/std/io/trait.Write.html
 //! [`Seek`]: https://doc.rust-lang.org/std/io/trait.Seek.html
+//! [`futures-io`]: https://crates.io/crates/futures-io
+//! [Tokio]: https://tokio.rs
+//! [`cipher`]: https://crates.io/crates/cipher
 //! [`digest`]: https://crates.io/crates/digest
 //! [`signature`]: https://crates.io/crates/signature
 
@@ -90,12 +128,39 @@
 #[cfg(test)]
 mod test;
 
+#[cfg(feature = "std")]
+pub mod accumulator;
+
+#[cfg(feature = "aead-preview")]
+pub mod aead;
+
+#[cfg(feature = "std")]
+pub mod cdc;
+
 #[doc(hidden)]
 #[deprecated(since = "1.8.0", note = "use the hazmat module instead")]
 pub mod guts;
 
 pub mod hazmat;
 
+#[cfg(feature = "hbs-preview")]
+pub mod hbs;
+
+pub mod kdf;
+
+pub mod mac;
+
+pub mod proof;
+
+#[cfg(feature = "pwhash")]
+pub mod pwhash;
+
+#[cfg(feature = "rng")]
+pub mod rng;
+
+#[cfg(feature = "stream")]
+pub mod stream;
+
 /// Undocumented and unstable, for benchmarks only.
 #[doc(hidden)]
 pub mod platform;
@@ -135,8 +200,13 @@ mod wasm32_simd;
 #[cfg(feature = "traits-preview")]
 pub mod traits;
 
-mod io;
-mod join;
+#[cfg(feature = "std")]
+pub mod verified;
+
+#[cfg(feature = "std")]
+pub mod io;
+pub mod join;
+mod state;
 
 use arrayref::{array_mut_ref, array_ref};
 use arrayvec::{ArrayString, ArrayVec};
@@ -146,6 +216,8 @@ use platform::{Platform, MAX_SIMD_DEGREE, MAX_SIMD_DEGREE_OR_2};
 #[cfg(feature = "zeroize")]
 use zeroize::Zeroize;
 
+pub use state::{HasherState, StateError};
+
 /// The number of bytes in a [`Hash`](struct.Hash.html), 32.
 pub const OUT_LEN: usize = 32;
 
@@ -919,7 +991,8 @@ pub fn hash(input: &[u8]) -> Hash {
 /// replace an HMAC instance. In that use case, the constant-time equality
 /// checking provided by [`Hash`](struct.Hash.html) is almost always a security
 /// requirement, and callers need to be careful not to compare MACs as raw
-/// bytes.
+/// bytes. The [`mac`] module handles that comparison, including for tags of
+/// other lengths.
 ///
 /// For an incremental version that accepts multiple writes, see [`Hasher::new_keyed`],
 /// [`Hasher::update`], and [`Hasher::finalize`]. These two lines are equivalent:
@@ -967,8 +1040,13 @@ pub fn keyed_hash(key: &[u8; KEY_LEN], input: &[u8]) -> Hash {
 ///
 /// Note that BLAKE3 is not a password hash, and **`derive_key` should never be
 /// used with passwords.** Instead, use a dedicated password hash like
-/// [Argon2]. Password hashes are entirely different from generic hash
-/// functions, with opposite design requirements.
+/// [Argon2], or the memory-hard construction in the `pwhash` module (which
+/// requires the `pwhash` Cargo feature). Password hashes are entirely
+/// different from generic hash functions, with opposite design requirements.
+///
+/// If your key material isn't uniformly random, like a Diffie-Hellman shared
+/// secret, or if you need a salt, see the extract-then-expand construction in
+/// the [`kdf`] module.
 ///
 /// For an incremental version that accepts multiple writes, see [`Hasher::new_derive_key`],
 /// [`Hasher::update`], and [`Hasher::finalize`]. These two statements are equivalent:
@@ -1000,6 +1078,153 @@ pub fn derive_key(context: &str, key_material: &[u8]) -> [u8; OUT_LEN] {
         .0
 }
 
+// Hash a batch of messages that are all between N and N + BLOCK_LEN bytes long, where N is a
+// non-zero multiple of BLOCK_LEN no larger than CHUNK_LEN. Each message is a single chunk and the
+// root of its own tree, so the first N bytes of every message can go through
+// Platform::hash_many() together. If the messages are exactly N bytes long, that's the whole
+// message, and hash_many() applies the root flags too. Otherwise the final partial block has a
+// different block length, which hash_many() doesn't support, so it's compressed separately.
+#[cfg(feature = "std")]
+fn hash_many_single_chunk<const N: usize>(
+    inputs: &[&[u8]],
+    indices: &[usize],
+    key: &CVWords,
+    flags: u8,
+    platform: Platform,
+    out: &mut [Hash],
+) {
+    let mut cvs = [0; MAX_SIMD_DEGREE * OUT_LEN];
+    for batch in indices.chunks(MAX_SIMD_DEGREE) {
+        let exact = inputs[batch[0]].len() == N;
+        let mut prefixes = ArrayVec::<&[u8; N], MAX_SIMD_DEGREE>::new();
+        for &i in batch {
+            debug_assert_eq!(exact, inputs[i].len() == N);
+            prefixes.push((&inputs[i][..N]).try_into().unwrap());
+        }
+        platform.hash_many(
+            &prefixes,
+            key,
+            0, // Every message is chunk 0 of its own tree.
+            IncrementCounter::No,
+            flags,
+            CHUNK_START,
+            if exact { CHUNK_END | ROOT } else { 0 },
+            &mut cvs,
+        );
+        for (&i, cv) in batch.iter().zip(cvs.chunks_exact(OUT_LEN)) {
+            let cv = array_ref!(cv, 0, OUT_LEN);
+            out[i] = if exact {
+                Hash(*cv)
+            } else {
+                let last_block = &inputs[i][N..];
+                let mut block = [0; BLOCK_LEN];
+                block[..last_block.len()].copy_from_slice(last_block);
+                Output {
+                    input_chaining_value: platform::words_from_le_bytes_32(cv),
+                    block,
+                    block_len: last_block.len() as u8,
+                    counter: 0,
+                    flags: flags | CHUNK_END,
+                    platform,
+                }
+                .root_hash()
+            };
+        }
+    }
+}
+
+// The shared implementation of hash_many(), keyed_hash_many(), and derive_key_many(). Messages
+// that fit in a single chunk are grouped by the number of full blocks that precede their final
+// block, and messages with the same count are hashed in parallel SIMD lanes. Longer messages are
+// hashed one at a time, where SIMD parallelism is already available within each message.
+#[cfg(feature = "std")]
+fn hash_many_impl(inputs: &[&[u8]], key: &CVWords, flags: u8) -> std::vec::Vec<Hash> {
+    const GROUPS: usize = 2 * (CHUNK_LEN / BLOCK_LEN);
+    let platform = Platform::detect();
+    let mut out = std::vec![Hash([0; OUT_LEN]); inputs.len()];
+    let mut groups: [std::vec::Vec<usize>; GROUPS] = Default::default();
+    for (i, input) in inputs.iter().enumerate() {
+        if input.len() < BLOCK_LEN || input.len() > CHUNK_LEN {
+            // Messages shorter than a block are a single compression, and messages longer than a
+            // chunk are a whole tree. Neither of those benefits from batching.
+            out[i] = hash_all_at_once::<join::SerialJoin>(input, key, flags).root_hash();
+        } else {
+            // Group 2k-2 holds messages of exactly k blocks, and group 2k-1 holds messages of k
+            // full blocks plus a partial block.
+            let full_blocks = input.len() / BLOCK_LEN;
+            let partial = input.len() > full_blocks * BLOCK_LEN;
+            groups[2 * (full_blocks - 1) + partial as usize].push(i);
+        }
+    }
+    for (group, indices) in groups.iter().enumerate() {
+        if indices.is_empty() {
+            continue;
+        }
+        // hash_many_single_chunk() needs the prefix length at compile time.
+        macro_rules! dispatch {
+            ($($full_blocks:literal)*) => {
+                match group / 2 + 1 {
+                    $($full_blocks => hash_many_single_chunk::<{ $full_blocks * BLOCK_LEN }>(
+                        inputs, indices, key, flags, platform, &mut out,
+                    ),)*
+                    _ => unreachable!(),
+                }
+            };
+        }
+        dispatch!(1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16);
+    }
+    out
+}
+
+/// As [`hash`], but hashing many independent messages at once.
+///
+/// Messages up to one [chunk](CHUNK_LEN) long are grouped by length and hashed in parallel SIMD
+/// lanes, in the same way that [`hash`] processes the chunks of a single long message. On
+/// platforms with AVX2 or AVX-512, this is several times faster than calling [`hash`] in a loop
+/// when most messages are short, as in deduplication indexes or the leaves of an application
+/// Merkle tree. Messages of less than 64 bytes, or longer than a chunk, get no benefit from
+/// batching and are hashed one at a time. The output is identical to calling [`hash`] on each
+/// message, in the same order.
+///
+/// This function requires the `std` Cargo feature, which is enabled by default. It's always
+/// single-threaded.
+///
+/// # Example
+///
+/// ```
+/// let messages: &[&[u8]] = &[b"foo", b"bar", &[42; 100]];
+/// let hashes = blake3::hash_many(messages);
+/// assert_eq!(hashes[0], blake3::hash(b"foo"));
+/// assert_eq!(hashes[2], blake3::hash(&[42; 100]));
+/// ```
+#[cfg(feature = "std")]
+pub fn hash_many(inputs: &[&[u8]]) -> std::vec::Vec<Hash> {
+    hash_many_impl(inputs, IV, 0)
+}
+
+/// As [`keyed_hash`], but hashing many independent messages at once. See [`hash_many`].
+///
+/// This function requires the `std` Cargo feature, which is enabled by default.
+#[cfg(feature = "std")]
+pub fn keyed_hash_many(key: &[u8; KEY_LEN], inputs: &[&[u8]]) -> std::vec::Vec<Hash> {
+    let key_words = platform::words_from_le_bytes_32(key);
+    hash_many_impl(inputs, &key_words, KEYED_HASH)
+}
+
+/// As [`derive_key`], but deriving keys from many independent pieces of key material at once,
+/// all with the same context string. See [`hash_many`].
+///
+/// This function requires the `std` Cargo feature, which is enabled by default.
+#[cfg(feature = "std")]
+pub fn derive_key_many(context: &str, key_materials: &[&[u8]]) -> std::vec::Vec<[u8; OUT_LEN]> {
+    let context_key = hazmat::hash_derive_key_context(context);
+    let context_key_words = platform::words_from_le_bytes_32(&context_key);
+    hash_many_impl(key_materials, &context_key_words, DERIVE_KEY_MATERIAL)
+        .into_iter()
+        .map(|hash| hash.0)
+        .collect()
+}
+
 fn parent_node_output(
     left_child: &CVBytes,
     right_child: &CVBytes,
@@ -1189,7 +1414,8 @@ impl Hasher {
     /// Add input bytes to the hash state. You can call this any number of times.
     ///
     /// This method is always single-threaded. For multithreading support, see
-    /// [`update_rayon`](#method.update_rayon) (enabled with the `rayon` Cargo feature).
+    /// [`update_rayon`](#method.update_rayon) (enabled with the `rayon` Cargo feature) and
+    /// [`update_with_join`](#method.update_with_join).
     ///
     /// Note that the degree of SIMD parallelism that `update` can use is limited by the size of
     /// this input buffer. See [`update_reader`](#method.update_reader).
@@ -1197,7 +1423,47 @@ impl Hasher {
         self.update_with_join::<join::SerialJoin>(input)
     }
 
-    fn update_with_join<J: join::Join>(&mut self, mut input: &[u8]) -> &mut Self {
+    /// As [`update`](Hasher::update), but using the given [`Join`](join::Join)
+    /// implementation for multithreading.
+    ///
+    /// [`update`](Hasher::update) uses [`SerialJoin`](join::SerialJoin), and
+    /// [`update_rayon`](#method.update_rayon) uses `RayonJoin`. This method lets
+    /// you plug in your own thread pool instead, or use
+    /// [`ScopedThreadJoin`](join::ScopedThreadJoin) (enabled with the `std`
+    /// Cargo feature) to avoid the Rayon dependency. The output is the same
+    /// regardless of the `Join` implementation. As with `update_rayon`, the input
+    /// needs to be large to benefit from multithreading.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use blake3::join::Join;
+    ///
+    /// // A Join implementation that runs the right side on a new thread.
+    /// enum SpawnJoin {}
+    ///
+    /// impl Join for SpawnJoin {
+    ///     fn join<A, B, RA, RB>(oper_a: A, oper_b: B) -> (RA, RB)
+    ///     where
+    ///         A: FnOnce() -> RA + Send,
+    ///         B: FnOnce() -> RB + Send,
+    ///         RA: Send,
+    ///         RB: Send,
+    ///     {
+    ///         std::thread::scope(|scope| {
+    ///             let handle = scope.spawn(oper_b);
+    ///             let result_a = oper_a();
+    ///             (result_a, handle.join().unwrap())
+    ///         })
+    ///     }
+    /// }
+    ///
+    /// let input = vec![0xab; 1 << 20];
+    /// let mut hasher = blake3::Hasher::new();
+    /// hasher.update_with_join::<SpawnJoin>(&input);
+    /// assert_eq!(hasher.finalize(), blake3::hash(&input));
+    /// ```
+    pub fn update_with_join<J: join::Join>(&mut self, mut input: &[u8]) -> &mut Self {
         let input_offset = self.initial_chunk_counter * CHUNK_LEN as u64;
         if let Some(max) = hazmat::max_subtree_len(input_offset) {
             let remaining = max - self.count();
@@ -1465,6 +1731,63 @@ impl Hasher {
         Ok(self)
     }
 
+    /// As [`update_reader`](Hasher::update_reader), but reading asynchronously from a
+    /// [`futures_io::AsyncRead`](https://docs.rs/futures-io/latest/futures_io/trait.AsyncRead.html)
+    /// implementation.
+    ///
+    /// This uses the same wide internal buffer as `update_reader`, which is allocated on the heap
+    /// to keep the returned future small. Hashing itself is CPU-bound and happens inline on the
+    /// calling task, between reads. For [Tokio](https://tokio.rs) readers, see
+    /// [`update_tokio_reader`](Hasher::update_tokio_reader).
+    ///
+    /// This method requires the `async` Cargo feature, which is disabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn example() -> std::io::Result<()> {
+    /// let input: &[u8] = b"foobarbaz";
+    /// let mut hasher = blake3::Hasher::new();
+    /// hasher.update_async_reader(input).await?;
+    /// assert_eq!(hasher.finalize(), blake3::hash(b"foobarbaz"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "async")]
+    pub async fn update_async_reader(
+        &mut self,
+        reader: impl futures_io::AsyncRead + Unpin,
+    ) -> std::io::Result<&mut Self> {
+        io::copy_wide_futures(reader, self).await?;
+        Ok(self)
+    }
+
+    /// As [`update_async_reader`](Hasher::update_async_reader), but for a
+    /// [`tokio::io::AsyncRead`](https://docs.rs/tokio/latest/tokio/io/trait.AsyncRead.html)
+    /// implementation.
+    ///
+    /// This method requires the `async` Cargo feature, which is disabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn example() -> std::io::Result<()> {
+    /// let input: &[u8] = b"foobarbaz";
+    /// let mut hasher = blake3::Hasher::new();
+    /// hasher.update_tokio_reader(input).await?;
+    /// assert_eq!(hasher.finalize(), blake3::hash(b"foobarbaz"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "async")]
+    pub async fn update_tokio_reader(
+        &mut self,
+        reader: impl tokio::io::AsyncRead + Unpin,
+    ) -> std::io::Result<&mut Self> {
+        io::copy_wide_tokio(reader, self).await?;
+        Ok(self)
+    }
+
     /// As [`update`](Hasher::update), but using Rayon-based multithreading
     /// internally.
     ///
@@ -1630,6 +1953,66 @@ impl std::io::Write for Hasher {
     }
 }
 
+#[cfg(feature = "async")]
+impl futures_io::AsyncWrite for Hasher {
+    /// This is equivalent to [`update`](#method.update). It never returns `Pending`.
+    #[inline]
+    fn poll_write(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+        input: &[u8],
+    ) -> core::task::Poll<std::io::Result<usize>> {
+        self.get_mut().update(input);
+        core::task::Poll::Ready(Ok(input.len()))
+    }
+
+    #[inline]
+    fn poll_flush(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<std::io::Result<()>> {
+        core::task::Poll::Ready(Ok(()))
+    }
+
+    #[inline]
+    fn poll_close(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<std::io::Result<()>> {
+        core::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "async")]
+impl tokio::io::AsyncWrite for Hasher {
+    /// This is equivalent to [`update`](#method.update). It never returns `Pending`.
+    #[inline]
+    fn poll_write(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+        input: &[u8],
+    ) -> core::task::Poll<std::io::Result<usize>> {
+        self.get_mut().update(input);
+        core::task::Poll::Ready(Ok(input.len()))
+    }
+
+    #[inline]
+    fn poll_flush(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<std::io::Result<()>> {
+        core::task::Poll::Ready(Ok(()))
+    }
+
+    #[inline]
+    fn poll_shutdown(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<std::io::Result<()>> {
+        core::task::Poll::Ready(Ok(()))
+    }
+}
+
 #[cfg(feature = "zeroize")]
 impl Zeroize for Hasher {
     fn zeroize(&mut self) {
@@ -1717,7 +2100,44 @@ impl OutputReader {
     /// reading further, the behavior is unspecified.
     ///
     /// [`Read::read`]: #method.read
-    pub fn fill(&mut self, mut buf: &mut [u8]) {
+    pub fn fill(&mut self, buf: &mut [u8]) {
+        self.fill_with_join::<join::SerialJoin>(buf)
+    }
+
+    /// As [`fill`](OutputReader::fill), but using Rayon-based multithreading
+    /// internally.
+    ///
+    /// This method is gated by the `rayon` Cargo feature, which is disabled by
+    /// default but enabled on [docs.rs](https://docs.rs).
+    ///
+    /// Each 64-byte block of output is computed independently from its block
+    /// counter, so the blocks can be split across threads without changing the
+    /// result. The output and the final position of the `OutputReader` are the
+    /// same as [`fill`](OutputReader::fill) would give. Like
+    /// [`update_rayon`](Hasher::update_rayon), this is only faster than `fill`
+    /// for large buffers, and buffers under a few hundred KiB are filled on
+    /// the calling thread.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut output_reader = blake3::Hasher::new().update(b"foo").finalize_xof();
+    /// let mut output = vec![0; 1 << 20];
+    /// output_reader.fill_rayon(&mut output);
+    ///
+    /// let mut expected = vec![0; 1 << 20];
+    /// blake3::Hasher::new()
+    ///     .update(b"foo")
+    ///     .finalize_xof()
+    ///     .fill(&mut expected);
+    /// assert_eq!(output, expected);
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn fill_rayon(&mut self, buf: &mut [u8]) {
+        self.fill_with_join::<join::RayonJoin>(buf)
+    }
+
+    fn fill_with_join<J: join::Join>(&mut self, mut buf: &mut [u8]) {
         if buf.is_empty() {
             return;
         }
@@ -1731,14 +2151,7 @@ impl OutputReader {
         let full_blocks_len = full_blocks * BLOCK_LEN;
         if full_blocks > 0 {
             debug_assert_eq!(0, self.position_within_block);
-            self.inner.platform.xof_many(
-                &self.inner.input_chaining_value,
-                &self.inner.block,
-                self.inner.block_len,
-                self.inner.counter,
-                self.inner.flags | ROOT,
-                &mut buf[..full_blocks_len],
-            );
+            xof_many_wide::<J>(&self.inner, self.inner.counter, &mut buf[..full_blocks_len]);
             self.inner.counter += full_blocks as u64;
             buf = &mut buf[full_blocks * BLOCK_LEN..];
         }
@@ -1775,6 +2188,33 @@ impl OutputReader {
     }
 }
 
+// Below this length, splitting up XOF output isn't worth the overhead of multithreading.
+const XOF_MIN_SPLIT_LEN: usize = 128 * 1024;
+
+// Fill whole output blocks starting at the given block counter, recursively splitting the output
+// in half with J::join for large outputs. (For SerialJoin, this is the same as calling xof_many
+// directly.)
+fn xof_many_wide<J: join::Join>(output: &Output, counter: u64, out: &mut [u8]) {
+    debug_assert_eq!(0, out.len() % BLOCK_LEN);
+    if out.len() < 2 * XOF_MIN_SPLIT_LEN {
+        output.platform.xof_many(
+            &output.input_chaining_value,
+            &output.block,
+            output.block_len,
+            counter,
+            output.flags | ROOT,
+            out,
+        );
+        return;
+    }
+    let left_blocks = out.len() / BLOCK_LEN / 2;
+    let (left, right) = out.split_at_mut(left_blocks * BLOCK_LEN);
+    J::join(
+        || xof_many_wide::<J>(output, counter, left),
+        || xof_many_wide::<J>(output, counter + left_blocks as u64, right),
+    );
+}
+
 // Don't derive(Debug), because the state may be secret.
 impl fmt::Debug for OutputReader {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -1818,6 +2258,68 @@ impl std::io::Seek for OutputReader {
     }
 }
 
+#[cfg(feature = "async")]
+impl futures_io::AsyncRead for OutputReader {
+    /// This is equivalent to [`fill`](#method.fill). It never returns `Pending`.
+    #[inline]
+    fn poll_read(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> core::task::Poll<std::io::Result<usize>> {
+        self.get_mut().fill(buf);
+        core::task::Poll::Ready(Ok(buf.len()))
+    }
+}
+
+#[cfg(feature = "async")]
+impl futures_io::AsyncSeek for OutputReader {
+    /// This is equivalent to the [`Seek`](#impl-Seek-for-OutputReader) implementation. It never
+    /// returns `Pending`.
+    fn poll_seek(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+        pos: std::io::SeekFrom,
+    ) -> core::task::Poll<std::io::Result<u64>> {
+        core::task::Poll::Ready(std::io::Seek::seek(self.get_mut(), pos))
+    }
+}
+
+#[cfg(feature = "async")]
+impl tokio::io::AsyncRead for OutputReader {
+    /// This is equivalent to [`fill`](#method.fill), filling all the remaining capacity of `buf`.
+    /// It never returns `Pending`.
+    #[inline]
+    fn poll_read(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> core::task::Poll<std::io::Result<()>> {
+        let n = buf.remaining();
+        self.get_mut().fill(buf.initialize_unfilled());
+        buf.advance(n);
+        core::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "async")]
+impl tokio::io::AsyncSeek for OutputReader {
+    /// This is equivalent to the [`Seek`](#impl-Seek-for-OutputReader) implementation. The seek
+    /// happens immediately, and [`poll_complete`](tokio::io::AsyncSeek::poll_complete) never
+    /// returns `Pending`.
+    fn start_seek(self: core::pin::Pin<&mut Self>, pos: std::io::SeekFrom) -> std::io::Result<()> {
+        std::io::Seek::seek(self.get_mut(), pos)?;
+        Ok(())
+    }
+
+    fn poll_complete(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<std::io::Result<u64>> {
+        core::task::Poll::Ready(Ok(self.position()))
+    }
+}
+
 #[cfg(feature = "zeroize")]
 impl Zeroize for OutputReader {
     fn zeroize(&mut self) {
@@ -0,0 +1,215 @@
+//! Message authentication with the keyed mode, with constant-time verification.
+//!
+//! [`keyed_hash`](crate::keyed_hash) and [`Hasher::new_keyed`] already make BLAKE3 a MAC, and
+//! [`Hash`](struct@Hash) compares in constant time. But it's easy to lose that property by
+//! comparing MACs as byte slices, especially when tags are shorter or longer than 32 bytes and
+//! come from the extended output of an [`OutputReader`](crate::OutputReader). A [`Mac`] does the
+//! comparison itself, for tags of any length, and returns a [`MacError`] if it fails.
+//!
+//! # Example
+//!
+//! ```
+//! # fn main() -> Result<(), blake3::mac::MacError> {
+//! use blake3::mac::Mac;
+//!
+//! let key = [42; 32];
+//!
+//! // The sender computes a tag.
+//! let tag: [u8; 32] = Mac::new(&key).update(b"request body").finalize().into();
+//!
+//! // The recipient verifies it.
+//! Mac::new(&key).update(b"request body").verify(&tag)?;
+//! assert!(Mac::new(&key).update(b"tampered body").verify(&tag).is_err());
+//!
+//! // Tags of other lengths are a prefix of the extended output.
+//! let mut short_tag = [0; 16];
+//! blake3::Hasher::new_keyed(&key)
+//!     .update(b"request body")
+//!     .finalize_xof()
+//!     .fill(&mut short_tag);
+//! Mac::new(&key).update(b"request body").verify_truncated(&short_tag)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Hash, Hasher, BLOCK_LEN, KEY_LEN, OUT_LEN};
+use core::fmt;
+
+/// An incremental MAC in the keyed mode, with constant-time verification.
+///
+/// The tag for a given key and message is the same as
+/// [`keyed_hash`](crate::keyed_hash) returns, or for lengths other than 32 bytes, a prefix of the
+/// extended output of [`Hasher::new_keyed`].
+#[derive(Clone, Debug)]
+pub struct Mac {
+    hasher: Hasher,
+}
+
+impl Mac {
+    /// Construct a new `Mac` with the given key.
+    pub fn new(key: &[u8; KEY_LEN]) -> Self {
+        Self {
+            hasher: Hasher::new_keyed(key),
+        }
+    }
+
+    /// Add input to the MAC. This is equivalent to [`Hasher::update`].
+    pub fn update(&mut self, input: &[u8]) -> &mut Self {
+        self.hasher.update(input);
+        self
+    }
+
+    /// Return the 32-byte tag of the input so far.
+    ///
+    /// The returned [`Hash`](struct@Hash) compares in constant time, but converting it to bytes
+    /// gives that up. To check a tag received from elsewhere, use [`verify`](Self::verify) or
+    /// [`verify_truncated`](Self::verify_truncated).
+    pub fn finalize(&self) -> Hash {
+        self.hasher.finalize()
+    }
+
+    /// Check a 32-byte tag against the input so far, in constant time.
+    pub fn verify(&self, tag: &[u8; OUT_LEN]) -> Result<(), MacError> {
+        if self.finalize() == *tag {
+            Ok(())
+        } else {
+            Err(MacError(MacErrorInner::Mismatch))
+        }
+    }
+
+    /// Check a tag of any non-zero length against the same number of bytes of extended output.
+    ///
+    /// The comparison is constant-time with respect to the contents of the tag, though not its
+    /// length, which is assumed to be public. Note that a short tag is easier to forge: an
+    /// attacker who can make many guesses needs about 2<sup>8n</sup> of them for an n-byte tag.
+    /// Applications should fix a tag length in advance and reject any other length before
+    /// calling this method, rather than accepting whatever length an attacker sends. An empty
+    /// tag is always an error.
+    pub fn verify_truncated(&self, tag: &[u8]) -> Result<(), MacError> {
+        if tag.is_empty() {
+            return Err(MacError(MacErrorInner::EmptyTag));
+        }
+        let mut reader = self.hasher.finalize_xof();
+        let mut expected = [0; BLOCK_LEN];
+        // Compare every block, without an early exit.
+        let mut equal = true;
+        for tag_block in tag.chunks(BLOCK_LEN) {
+            let expected_block = &mut expected[..tag_block.len()];
+            reader.fill(expected_block);
+            equal &= constant_time_eq::constant_time_eq(expected_block, tag_block);
+        }
+        if equal {
+            Ok(())
+        } else {
+            Err(MacError(MacErrorInner::Mismatch))
+        }
+    }
+
+    /// Reset the `Mac` to its initial state, keeping the key.
+    pub fn reset(&mut self) -> &mut Self {
+        self.hasher.reset();
+        self
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for Mac {
+    fn zeroize(&mut self) {
+        // Destructuring to trigger compile error as a reminder to update this impl.
+        let Self { hasher } = self;
+
+        hasher.zeroize();
+    }
+}
+
+/// The error type for [`Mac::verify`] and [`Mac::verify_truncated`].
+///
+/// The `.to_string()` representation of this error currently distinguishes between a tag that
+/// doesn't match and an empty tag. This is to help with logging and debugging, but it isn't a
+/// stable API detail, and it may change at any time.
+#[derive(Clone, Debug)]
+pub struct MacError(MacErrorInner);
+
+#[derive(Clone, Debug)]
+enum MacErrorInner {
+    Mismatch,
+    EmptyTag,
+}
+
+impl fmt::Display for MacError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            MacErrorInner::Mismatch => write!(f, "MAC verification failed"),
+            MacErrorInner::EmptyTag => write!(f, "empty MAC tag"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MacError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{paint_test_input, TEST_CASES, TEST_KEY};
+
+    #[test]
+    fn test_mac_matches_keyed_hash() {
+        let mut input = [0; crate::test::TEST_CASES_MAX];
+        paint_test_input(&mut input);
+        for &case in TEST_CASES {
+            let input = &input[..case];
+            let expected = crate::keyed_hash(&TEST_KEY, input);
+            let mut mac = Mac::new(&TEST_KEY);
+            mac.update(input);
+            assert_eq!(mac.finalize(), expected);
+            mac.verify(expected.as_bytes()).unwrap();
+
+            let mut wrong = *expected.as_bytes();
+            wrong[31] ^= 1;
+            assert!(mac.verify(&wrong).is_err());
+        }
+    }
+
+    #[test]
+    fn test_verify_truncated() {
+        let mut input = [0; 10_000];
+        paint_test_input(&mut input);
+        let mut mac = Mac::new(&TEST_KEY);
+        mac.update(&input);
+
+        let mut expected = [0; 3 * BLOCK_LEN + 1];
+        Hasher::new_keyed(&TEST_KEY)
+            .update(&input)
+            .finalize_xof()
+            .fill(&mut expected);
+
+        for len in 1..=expected.len() {
+            let mut tag = [0; 3 * BLOCK_LEN + 1];
+            let tag = &mut tag[..len];
+            tag.copy_from_slice(&expected[..len]);
+            mac.verify_truncated(tag).unwrap();
+            // Corrupting any byte, including the first and last, fails.
+            for i in [0, len / 2, len - 1] {
+                tag[i] ^= 1;
+                assert!(mac.verify_truncated(tag).is_err());
+                tag[i] ^= 1;
+            }
+        }
+
+        // The 32-byte case agrees with verify().
+        mac.verify_truncated(&expected[..OUT_LEN]).unwrap();
+        mac.verify(arrayref::array_ref!(expected, 0, OUT_LEN))
+            .unwrap();
+
+        assert!(mac.verify_truncated(&[]).is_err());
+    }
+
+    #[test]
+    fn test_mac_reset() {
+        let mut mac = Mac::new(&TEST_KEY);
+        mac.update(b"foo");
+        mac.reset().update(b"bar");
+        assert_eq!(mac.finalize(), crate::keyed_hash(&TEST_KEY, b"bar"));
+    }
+}
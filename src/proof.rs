@@ -0,0 +1,458 @@
+//! Merkle inclusion proofs for individual chunks
+//!
+//! A [`ChunkProof`] shows that one chunk (up to [`CHUNK_LEN`] bytes) is part of an input with a
+//! given [`Hash`](struct@Hash). It contains the length of the whole input, the index of the
+//! chunk, and the chaining values of the sibling subtrees along the path from the root down to
+//! the chunk. A verifier who knows only the root hash can check a chunk against its proof without
+//! seeing any other part of the input. That takes one chunk compression and one parent node
+//! compression per level of the tree, and proofs are at most [`MAX_PROOF_LEN`] bytes.
+//!
+//! You can build a proof either from the whole input with [`ChunkProof::from_input`], or from an
+//! outboard encoding (see [`verified::outboard`](crate::verified::outboard)) with
+//! [`ChunkProof::from_outboard`], which doesn't need to rehash anything. Verifying a proof with
+//! [`ChunkProof::verify`] works with `no_std` and doesn't allocate.
+//!
+//! The verifier walks up the tree using
+//! [`merge_subtrees_non_root`] and
+//! [`merge_subtrees_root`], as described in the
+//! [`hazmat`](crate::hazmat) module docs. Chunk chaining values depend on the chunk index, so a
+//! chunk can't be passed off as a chunk from somewhere else. The input length in the proof
+//! determines the shape of the tree, but it's only fully verified by a proof for the final chunk.
+//!
+//! # Example
+//!
+//! ```
+//! # fn main() -> Result<(), blake3::proof::ProofError> {
+//! use blake3::hazmat::Mode;
+//! use blake3::proof::ChunkProof;
+//! use blake3::CHUNK_LEN;
+//!
+//! let input = vec![0xab; 1_000_000];
+//! let hash = blake3::hash(&input);
+//!
+//! // A server with the whole input builds a proof for chunk 42.
+//! let proof = ChunkProof::from_input(&input, 42, Mode::Hash)?;
+//! let proof_bytes = proof.to_bytes();
+//!
+//! // A light client that knows only the hash verifies chunk 42 by itself.
+//! let chunk = &input[42 * CHUNK_LEN..][..CHUNK_LEN];
+//! let proof = ChunkProof::from_bytes(&proof_bytes)?;
+//! proof.verify(chunk, &hash, Mode::Hash)?;
+//! assert_eq!(proof.chunk_index(), 42);
+//! assert_eq!(proof.content_len(), 1_000_000);
+//!
+//! // A chunk from anywhere else doesn't verify.
+//! let other_chunk = &input[43 * CHUNK_LEN..][..CHUNK_LEN - 1];
+//! assert!(proof.verify(other_chunk, &hash, Mode::Hash).is_err());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::hazmat::{
+    left_subtree_len, merge_subtrees_non_root, merge_subtrees_root, ChainingValue, HasherExt, Mode,
+};
+use crate::{Hash, Hasher, CHUNK_LEN, MAX_DEPTH, OUT_LEN};
+use arrayvec::ArrayVec;
+use core::cmp;
+use core::fmt;
+
+/// The maximum length of an encoded [`ChunkProof`] in bytes.
+pub const MAX_PROOF_LEN: usize = 16 + MAX_DEPTH * OUT_LEN;
+
+// The empty input is a single (empty) chunk, so every input has at least one chunk. This and
+// parent_nodes_len are shared with the verified module.
+pub(crate) fn count_chunks(content_len: u64) -> u64 {
+    if content_len == 0 {
+        1
+    } else {
+        (content_len - 1) / CHUNK_LEN as u64 + 1
+    }
+}
+
+// The combined length of all the parent nodes in a subtree, as in the outboard encoding. This
+// can't overflow, because a u64 input has at most 2^54 chunks.
+pub(crate) fn parent_nodes_len(subtree_len: u64) -> u64 {
+    (count_chunks(subtree_len) - 1) * 2 * OUT_LEN as u64
+}
+
+// A parent node on the path from the root to a chunk.
+#[derive(Clone, Copy)]
+struct Level {
+    start: u64,
+    len: u64,
+    left_len: u64,
+    chunk_is_left: bool,
+}
+
+impl Level {
+    // The start and length of the subtree next to the path.
+    fn sibling(&self) -> (u64, u64) {
+        if self.chunk_is_left {
+            (self.start + self.left_len, self.len - self.left_len)
+        } else {
+            (self.start, self.left_len)
+        }
+    }
+}
+
+// The parent nodes from the root down to the given chunk. The chunk index must be in range.
+fn path(content_len: u64, chunk_index: u64) -> ArrayVec<Level, MAX_DEPTH> {
+    debug_assert!(chunk_index < count_chunks(content_len));
+    let chunk_start = chunk_index * CHUNK_LEN as u64;
+    let mut levels = ArrayVec::new();
+    let mut start = 0;
+    let mut len = content_len;
+    while len > CHUNK_LEN as u64 {
+        let left_len = left_subtree_len(len);
+        let chunk_is_left = chunk_start < start + left_len;
+        levels.push(Level {
+            start,
+            len,
+            left_len,
+            chunk_is_left,
+        });
+        if chunk_is_left {
+            len = left_len;
+        } else {
+            start += left_len;
+            len -= left_len;
+        }
+    }
+    levels
+}
+
+fn check_chunk_index(content_len: u64, chunk_index: u64) -> Result<(), ProofError> {
+    if chunk_index < count_chunks(content_len) {
+        Ok(())
+    } else {
+        Err(ProofError(ProofErrorInner::ChunkIndexOutOfRange))
+    }
+}
+
+/// A proof that one chunk is part of an input with a given root [`Hash`](struct@Hash).
+///
+/// See the [module level docs](index.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkProof {
+    content_len: u64,
+    chunk_index: u64,
+    // Sibling chaining values from the top of the tree down.
+    siblings: ArrayVec<ChainingValue, MAX_DEPTH>,
+}
+
+impl ChunkProof {
+    /// Build a proof for chunk `chunk_index` of `input`, by hashing the rest of the input.
+    ///
+    /// This returns an error if `chunk_index` is past the end of the input. Note that the empty
+    /// input has one (empty) chunk, so index 0 is always valid.
+    pub fn from_input(input: &[u8], chunk_index: u64, mode: Mode) -> Result<Self, ProofError> {
+        let content_len = input.len() as u64;
+        check_chunk_index(content_len, chunk_index)?;
+        let siblings = path(content_len, chunk_index)
+            .iter()
+            .map(|level| {
+                let (start, len) = level.sibling();
+                Hasher::new_internal(&mode.key_words(), mode.flags_byte())
+                    .set_input_offset(start)
+                    .update(&input[start as usize..][..len as usize])
+                    .finalize_non_root()
+            })
+            .collect();
+        Ok(Self {
+            content_len,
+            chunk_index,
+            siblings,
+        })
+    }
+
+    /// Build a proof for chunk `chunk_index` from an outboard encoding, as produced by
+    /// [`verified::outboard`](crate::verified::outboard), without rehashing anything.
+    ///
+    /// The outboard encoding isn't verified here. If it's corrupt, the resulting proof won't
+    /// [`verify`](Self::verify). This returns an error if the encoding is too short, or if
+    /// `chunk_index` is past the end of the input.
+    pub fn from_outboard(outboard: &[u8], chunk_index: u64) -> Result<Self, ProofError> {
+        let malformed = ProofError(ProofErrorInner::MalformedOutboard);
+        let header = outboard.get(..8).ok_or(malformed.clone())?;
+        let content_len = u64::from_le_bytes(header.try_into().unwrap());
+        check_chunk_index(content_len, chunk_index)?;
+        let mut pos: u64 = 8;
+        let mut siblings = ArrayVec::new();
+        for level in path(content_len, chunk_index) {
+            let parent = usize::try_from(pos)
+                .ok()
+                .and_then(|pos| outboard.get(pos..)?.get(..2 * OUT_LEN))
+                .ok_or(malformed.clone())?;
+            let (left_cv, right_cv) = parent.split_at(OUT_LEN);
+            pos += 2 * OUT_LEN as u64;
+            if level.chunk_is_left {
+                siblings.push(right_cv.try_into().unwrap());
+            } else {
+                siblings.push(left_cv.try_into().unwrap());
+                pos += parent_nodes_len(level.left_len);
+            }
+        }
+        Ok(Self {
+            content_len,
+            chunk_index,
+            siblings,
+        })
+    }
+
+    /// Decode a proof from the format produced by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofError> {
+        let invalid_len = ProofError(ProofErrorInner::InvalidLen(bytes.len()));
+        if bytes.len() < 16 {
+            return Err(invalid_len);
+        }
+        let content_len = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+        let chunk_index = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+        check_chunk_index(content_len, chunk_index)?;
+        let depth = path(content_len, chunk_index).len();
+        if bytes.len() != 16 + depth * OUT_LEN {
+            return Err(invalid_len);
+        }
+        let siblings = bytes[16..]
+            .chunks_exact(OUT_LEN)
+            .map(|cv| cv.try_into().unwrap())
+            .collect();
+        Ok(Self {
+            content_len,
+            chunk_index,
+            siblings,
+        })
+    }
+
+    /// Encode the proof as the little-endian content length (8 bytes), the little-endian chunk
+    /// index (8 bytes), and the sibling chaining values from the top of the tree down (32 bytes
+    /// each).
+    pub fn to_bytes(&self) -> ArrayVec<u8, MAX_PROOF_LEN> {
+        let mut bytes = ArrayVec::new();
+        bytes.extend(self.content_len.to_le_bytes());
+        bytes.extend(self.chunk_index.to_le_bytes());
+        for cv in &self.siblings {
+            bytes.extend(cv.iter().copied());
+        }
+        bytes
+    }
+
+    /// The length of the whole input in bytes.
+    pub fn content_len(&self) -> u64 {
+        self.content_len
+    }
+
+    /// The index of the chunk that this proof is for.
+    pub fn chunk_index(&self) -> u64 {
+        self.chunk_index
+    }
+
+    /// The byte range of the chunk within the whole input.
+    ///
+    /// The chunk is [`CHUNK_LEN`] bytes long, except that the final chunk can be shorter.
+    pub fn chunk_range(&self) -> core::ops::Range<u64> {
+        let start = self.chunk_index * CHUNK_LEN as u64;
+        start..start + cmp::min(CHUNK_LEN as u64, self.content_len - start)
+    }
+
+    /// The chaining values of the subtrees next to the path from the root down to the chunk, in
+    /// that order. This is empty if the input is only one chunk.
+    pub fn siblings(&self) -> &[ChainingValue] {
+        &self.siblings
+    }
+
+    /// Check that `chunk` is chunk [`chunk_index`](Self::chunk_index) of the input with root hash
+    /// `hash`.
+    ///
+    /// The `mode` must be the same one the root hash was computed with.
+    pub fn verify(&self, chunk: &[u8], hash: &Hash, mode: Mode) -> Result<(), ProofError> {
+        let range = self.chunk_range();
+        if chunk.len() as u64 != range.end - range.start {
+            return Err(ProofError(ProofErrorInner::HashMismatch));
+        }
+        let levels = path(self.content_len, self.chunk_index);
+        debug_assert_eq!(levels.len(), self.siblings.len());
+        let mut hasher = Hasher::new_internal(&mode.key_words(), mode.flags_byte());
+        let root_hash = if levels.is_empty() {
+            hasher.update(chunk).finalize()
+        } else {
+            let mut cv = hasher
+                .set_input_offset(range.start)
+                .update(chunk)
+                .finalize_non_root();
+            for (level, sibling) in levels.iter().zip(&self.siblings).skip(1).rev() {
+                cv = if level.chunk_is_left {
+                    merge_subtrees_non_root(&cv, sibling, mode)
+                } else {
+                    merge_subtrees_non_root(sibling, &cv, mode)
+                };
+            }
+            if levels[0].chunk_is_left {
+                merge_subtrees_root(&cv, &self.siblings[0], mode)
+            } else {
+                merge_subtrees_root(&self.siblings[0], &cv, mode)
+            }
+        };
+        // Hash equality is constant-time.
+        if root_hash == *hash {
+            Ok(())
+        } else {
+            Err(ProofError(ProofErrorInner::HashMismatch))
+        }
+    }
+}
+
+/// The error type for [`ChunkProof`] construction, decoding, and verification.
+///
+/// The `.to_string()` representation of this error currently distinguishes between the different
+/// kinds of errors. This is to help with logging and debugging, but it isn't a stable API detail,
+/// and it may change at any time.
+#[derive(Clone, Debug)]
+pub struct ProofError(ProofErrorInner);
+
+#[derive(Clone, Debug)]
+enum ProofErrorInner {
+    ChunkIndexOutOfRange,
+    MalformedOutboard,
+    InvalidLen(usize),
+    HashMismatch,
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            ProofErrorInner::ChunkIndexOutOfRange => write!(f, "chunk index out of range"),
+            ProofErrorInner::MalformedOutboard => write!(f, "outboard encoding too short"),
+            ProofErrorInner::InvalidLen(len) => write!(f, "invalid proof length: {}", len),
+            ProofErrorInner::HashMismatch => write!(f, "hash mismatch"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ProofError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hazmat::hash_derive_key_context;
+    use crate::test::{paint_test_input, TEST_CASES, TEST_CASES_MAX, TEST_KEY};
+
+    fn chunk_indexes(content_len: usize) -> impl Iterator<Item = u64> {
+        let count = count_chunks(content_len as u64);
+        [0, 1, count / 2, count.saturating_sub(2), count - 1]
+            .into_iter()
+            .filter(move |&i| i < count)
+    }
+
+    #[test]
+    fn test_proofs() {
+        let context_key = hash_derive_key_context("test context");
+        let mut input_buf = [0; TEST_CASES_MAX];
+        paint_test_input(&mut input_buf);
+        for &case in TEST_CASES {
+            let input = &input_buf[..case];
+            let modes = [
+                (Mode::Hash, crate::hash(input)),
+                (
+                    Mode::KeyedHash(&TEST_KEY),
+                    crate::keyed_hash(&TEST_KEY, input),
+                ),
+                (
+                    Mode::DeriveKeyMaterial(&context_key),
+                    crate::derive_key("test context", input).into(),
+                ),
+            ];
+            for (mode, hash) in modes {
+                for chunk_index in chunk_indexes(case) {
+                    let proof = ChunkProof::from_input(input, chunk_index, mode).unwrap();
+                    let range = proof.chunk_range();
+                    let chunk = &input[range.start as usize..range.end as usize];
+                    proof.verify(chunk, &hash, mode).unwrap();
+
+                    let decoded = ChunkProof::from_bytes(&proof.to_bytes()).unwrap();
+                    assert_eq!(proof, decoded);
+
+                    #[cfg(feature = "std")]
+                    {
+                        let (outboard, _) = crate::verified::outboard(input, mode);
+                        let from_outboard = ChunkProof::from_outboard(&outboard, chunk_index);
+                        assert_eq!(proof, from_outboard.unwrap());
+                    }
+
+                    assert!(proof.verify(chunk, &crate::hash(b"foo"), mode).is_err());
+                    if !chunk.is_empty() {
+                        let mut bad_chunk = chunk.to_vec();
+                        bad_chunk[0] ^= 1;
+                        assert!(proof.verify(&bad_chunk, &hash, mode).is_err());
+                    }
+                    for i in 0..proof.siblings.len() {
+                        let mut bad_proof = proof.clone();
+                        bad_proof.siblings[i][0] ^= 1;
+                        assert!(bad_proof.verify(chunk, &hash, mode).is_err());
+                    }
+                }
+                let count = count_chunks(case as u64);
+                assert!(ChunkProof::from_input(input, count, mode).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_wrong_chunk_index() {
+        let mut input = [0; 4 * CHUNK_LEN];
+        paint_test_input(&mut input);
+        let hash = crate::hash(&input);
+        let proof = ChunkProof::from_input(&input, 1, Mode::Hash).unwrap();
+        // Claim that chunk 1 is chunk 0.
+        let mut bytes = proof.to_bytes();
+        bytes[8] = 0;
+        let bad_proof = ChunkProof::from_bytes(&bytes).unwrap();
+        let chunk = &input[CHUNK_LEN..][..CHUNK_LEN];
+        assert!(bad_proof.verify(chunk, &hash, Mode::Hash).is_err());
+        // Claim a different length in a proof for the final chunk.
+        let proof = ChunkProof::from_input(&input, 3, Mode::Hash).unwrap();
+        let mut bytes = proof.to_bytes();
+        bytes[..8].copy_from_slice(&(4 * CHUNK_LEN as u64 - 1).to_le_bytes());
+        let bad_proof = ChunkProof::from_bytes(&bytes).unwrap();
+        let chunk = &input[3 * CHUNK_LEN..][..CHUNK_LEN - 1];
+        assert!(bad_proof.verify(chunk, &hash, Mode::Hash).is_err());
+    }
+
+    #[test]
+    fn test_from_bytes_errors() {
+        let input = [0; 3 * CHUNK_LEN];
+        let bytes = ChunkProof::from_input(&input, 2, Mode::Hash)
+            .unwrap()
+            .to_bytes();
+        assert!(ChunkProof::from_bytes(&bytes).is_ok());
+        assert!(ChunkProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(ChunkProof::from_bytes(&bytes[..15]).is_err());
+        let mut out_of_range = bytes.clone();
+        out_of_range[8] = 3;
+        assert!(ChunkProof::from_bytes(&out_of_range).is_err());
+        assert!(ChunkProof::from_outboard(&[0; 7], 0).is_err());
+        // A header claiming a longer input than the outboard encoding has room for.
+        let mut outboard = [0; 8 + 2 * OUT_LEN];
+        outboard[..8].copy_from_slice(&(3 * CHUNK_LEN as u64).to_le_bytes());
+        assert!(ChunkProof::from_outboard(&outboard, 0).is_err());
+        assert!(ChunkProof::from_outboard(&outboard, 2).is_ok());
+    }
+
+    #[test]
+    fn test_max_content_len() {
+        // The final chunk of the longest possible input ends at u64::MAX.
+        let content_len = u64::MAX;
+        let chunk_index = count_chunks(content_len) - 1;
+        let mut bytes = std::vec::Vec::new();
+        bytes.extend_from_slice(&content_len.to_le_bytes());
+        bytes.extend_from_slice(&chunk_index.to_le_bytes());
+        bytes.resize(MAX_PROOF_LEN, 0);
+        let proof = ChunkProof::from_bytes(&bytes).unwrap();
+        let range = proof.chunk_range();
+        assert_eq!(range.end, u64::MAX);
+        assert_eq!(range.end - range.start, (CHUNK_LEN - 1) as u64);
+        let chunk = [0; CHUNK_LEN - 1];
+        let hash = crate::hash(b"");
+        assert!(proof.verify(&chunk, &hash, Mode::Hash).is_err());
+    }
+}
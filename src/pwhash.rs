@@ -0,0 +1,704 @@
+//! Memory-hard password hashing, based on [Balloon hashing](https://eprint.iacr.org/2016/027).
+//!
+//! [`derive_key`](crate::derive_key) and [`keyed_hash`](crate::keyed_hash) are fast, which is
+//! exactly wrong for passwords. An attacker with a stolen password hash can try billions of
+//! guesses per second against a fast hash. A password hash makes each guess expensive, in both
+//! time and memory, so that guessing in parallel on GPUs or custom hardware doesn't help much.
+//!
+//! [`hash_password`] returns a [`PasswordHash`], which can be stored as a string in the
+//! [PHC format](https://github.com/P-H-C/phc-string-format/blob/master/phc-sf-spec.md), and
+//! [`verify_password`] checks a password against that string. For key stretching, where the
+//! output is a key rather than something to store, use [`hash_password_into`].
+//!
+//! The cost is set by [`Params`]: `m_cost` is the memory size in KiB, and `t_cost` is the number
+//! of passes over that memory. Choose the largest `m_cost` your servers can afford per
+//! concurrent login, and then raise `t_cost` until hashing takes as long as you can tolerate.
+//! The salt should be 16 random bytes, unique to each password, from a CSPRNG like
+//! [`getrandom`](https://crates.io/crates/getrandom).
+//!
+//! A PHC string records its own cost, and checking a password against it takes that much memory
+//! and time. The cost is capped at [`MAX_M_COST`] and [`MAX_T_COST`], but if the strings might
+//! come from an attacker, use [`verify_password_with_limits`] to set a lower cap.
+//!
+//! This module requires the `pwhash` Cargo feature.
+//!
+//! # Construction
+//!
+//! The memory is `n = m_cost` blocks of 1024 bytes each. All integers below are encoded as 8
+//! little-endian bytes, and `||` is concatenation.
+//!
+//! ```text
+//! key  = derive_key("BLAKE3 2026-10-15 pwhash balloon v1",
+//!                   m_cost || t_cost || output_len || len(salt) || salt)
+//! H(x) = XOF of keyed_hash(key, x || counter), where counter starts at 0 and
+//!        increments after every call
+//!
+//! B[0] = H(password)
+//! B[i] = H(B[i-1])                         for 0 < i < n
+//! for r in 0..t_cost, for i in 0..n:
+//!     B[i] = H(B[i-1 mod n] || B[i])
+//!     j0, j1, j2 = the first three 8-byte words of H(r || i)
+//!     B[i] = H(B[i] || B[j mod n])         for each j in j0, j1, j2
+//! output = H(B[n-1])
+//! ```
+//!
+//! Each block is the first 1024 bytes of its `H` output, and the final output is as long as the
+//! caller asks for. This is the Balloon construction with a delta of 3, with BLAKE3 in the keyed
+//! mode as the compression function. The counter keeps every input to `H` distinct, and the
+//! memory access pattern depends only on the public parameters and the salt, not the password.
+//!
+//! # Example
+//!
+//! ```
+//! # fn main() -> Result<(), blake3::pwhash::PwHashError> {
+//! use blake3::pwhash::{self, Params};
+//!
+//! # let salt = [42; 16];
+//! // In a real application, the salt comes from a CSPRNG.
+//! let params = Params::new(1024, 2)?;
+//! let stored = pwhash::hash_password(b"hunter2", &salt, &params)?.to_string();
+//! assert!(stored.starts_with("$blake3-balloon$v=1$m=1024,t=2$"));
+//!
+//! pwhash::verify_password(b"hunter2", &stored)?;
+//! assert!(pwhash::verify_password(b"hunter3", &stored).is_err());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Hasher, OutputReader, KEY_LEN};
+use core::fmt;
+use core::str::FromStr;
+
+const KEY_CONTEXT: &str = "BLAKE3 2026-10-15 pwhash balloon v1";
+
+const ALGORITHM_ID: &str = "blake3-balloon";
+const VERSION: u32 = 1;
+
+const MEMORY_BLOCK_LEN: usize = 1024;
+const DELTA: usize = 3;
+
+/// The smallest allowed `m_cost`, 8 KiB.
+pub const MIN_M_COST: u32 = 8;
+
+/// The largest allowed `m_cost`, 1 GiB.
+pub const MAX_M_COST: u32 = 1 << 20;
+
+/// The smallest allowed `t_cost`, 1.
+pub const MIN_T_COST: u32 = 1;
+
+/// The largest allowed `t_cost`, 1024.
+pub const MAX_T_COST: u32 = 1 << 10;
+
+/// The shortest allowed salt, 8 bytes. 16 random bytes are recommended.
+pub const MIN_SALT_LEN: usize = 8;
+
+/// The longest allowed salt, 64 bytes.
+pub const MAX_SALT_LEN: usize = 64;
+
+/// The length of the output of [`hash_password`], 32 bytes.
+pub const HASH_LEN: usize = 32;
+
+// The range of output lengths accepted when parsing a PHC string.
+const MIN_PHC_HASH_LEN: usize = 16;
+const MAX_PHC_HASH_LEN: usize = 64;
+
+/// The cost parameters for password hashing.
+///
+/// See the [module level docs](self) for how to choose them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Params {
+    m_cost: u32,
+    t_cost: u32,
+}
+
+impl Params {
+    /// Construct new `Params`, with `m_cost` in KiB and `t_cost` in passes over that memory.
+    ///
+    /// Returns an error unless `m_cost` is between [`MIN_M_COST`] and [`MAX_M_COST`] and
+    /// `t_cost` is between [`MIN_T_COST`] and [`MAX_T_COST`].
+    pub fn new(m_cost: u32, t_cost: u32) -> Result<Self, PwHashError> {
+        if !(MIN_M_COST..=MAX_M_COST).contains(&m_cost)
+            || !(MIN_T_COST..=MAX_T_COST).contains(&t_cost)
+        {
+            return Err(PwHashError(PwHashErrorInner::InvalidParams));
+        }
+        Ok(Self { m_cost, t_cost })
+    }
+
+    /// The memory size in KiB.
+    pub fn m_cost(&self) -> u32 {
+        self.m_cost
+    }
+
+    /// The number of passes over the memory.
+    pub fn t_cost(&self) -> u32 {
+        self.t_cost
+    }
+}
+
+/// The default parameters are 8 MiB of memory and 2 passes. These might change in a future
+/// version, but that doesn't affect existing hashes, which record their own parameters.
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            m_cost: 8 * 1024,
+            t_cost: 2,
+        }
+    }
+}
+
+// The H function from the module docs, with its counter.
+struct Balloon {
+    key: [u8; KEY_LEN],
+    counter: u64,
+}
+
+impl Balloon {
+    fn hash(&mut self, inputs: &[&[u8]]) -> OutputReader {
+        let mut hasher = Hasher::new_keyed(&self.key);
+        for input in inputs {
+            hasher.update(input);
+        }
+        hasher.update(&self.counter.to_le_bytes());
+        self.counter += 1;
+        hasher.finalize_xof()
+    }
+}
+
+/// Hash a password into an output of any length, for example to use it as a key.
+///
+/// Returns an error if the salt is shorter than [`MIN_SALT_LEN`] or longer than
+/// [`MAX_SALT_LEN`]. The output depends on its length, so a 32-byte output isn't a prefix of a
+/// 64-byte one. See the [module level docs](self) for the construction.
+pub fn hash_password_into(
+    password: &[u8],
+    salt: &[u8],
+    params: &Params,
+    output: &mut [u8],
+) -> Result<(), PwHashError> {
+    check_salt_len(salt.len())?;
+    let mut key_hasher = Hasher::new_derive_key(KEY_CONTEXT);
+    key_hasher.update(&(params.m_cost as u64).to_le_bytes());
+    key_hasher.update(&(params.t_cost as u64).to_le_bytes());
+    key_hasher.update(&(output.len() as u64).to_le_bytes());
+    key_hasher.update(&(salt.len() as u64).to_le_bytes());
+    key_hasher.update(salt);
+    let mut balloon = Balloon {
+        key: *key_hasher.finalize().as_bytes(),
+        counter: 0,
+    };
+
+    // Expand.
+    let n = params.m_cost as usize;
+    let mut blocks = vec![[0; MEMORY_BLOCK_LEN]; n];
+    balloon.hash(&[password]).fill(&mut blocks[0]);
+    for i in 1..n {
+        let mut new_block = [0; MEMORY_BLOCK_LEN];
+        balloon.hash(&[&blocks[i - 1]]).fill(&mut new_block);
+        blocks[i] = new_block;
+    }
+
+    // Mix.
+    for round in 0..params.t_cost as u64 {
+        for i in 0..n {
+            let mut new_block = [0; MEMORY_BLOCK_LEN];
+            let prev = (i + n - 1) % n;
+            balloon
+                .hash(&[&blocks[prev], &blocks[i]])
+                .fill(&mut new_block);
+            blocks[i] = new_block;
+
+            let mut indices = [0; 8 * DELTA];
+            balloon
+                .hash(&[&round.to_le_bytes(), &(i as u64).to_le_bytes()])
+                .fill(&mut indices);
+            for index_bytes in indices.chunks_exact(8) {
+                let index = u64::from_le_bytes(*arrayref::array_ref!(index_bytes, 0, 8));
+                let other = (index % n as u64) as usize;
+                balloon
+                    .hash(&[&blocks[i], &blocks[other]])
+                    .fill(&mut new_block);
+                blocks[i] = new_block;
+            }
+        }
+    }
+
+    // Extract.
+    balloon.hash(&[&blocks[n - 1]]).fill(output);
+
+    #[cfg(feature = "zeroize")]
+    {
+        for block in &mut blocks {
+            zeroize::Zeroize::zeroize(block);
+        }
+        zeroize::Zeroize::zeroize(&mut balloon.key);
+    }
+    Ok(())
+}
+
+/// Hash a password with the given salt, which should be freshly random for each password,
+/// returning a [`PasswordHash`] with a 32-byte output.
+///
+/// Returns an error if the salt is shorter than [`MIN_SALT_LEN`] or longer than
+/// [`MAX_SALT_LEN`]. To store the result, convert it to a PHC string with `.to_string()`.
+pub fn hash_password(
+    password: &[u8],
+    salt: &[u8],
+    params: &Params,
+) -> Result<PasswordHash, PwHashError> {
+    let mut hash = vec![0; HASH_LEN];
+    hash_password_into(password, salt, params, &mut hash)?;
+    Ok(PasswordHash {
+        params: *params,
+        salt: salt.to_vec(),
+        hash,
+    })
+}
+
+/// Check a password against a PHC string from [`hash_password`].
+///
+/// This is equivalent to parsing the string as a [`PasswordHash`] and calling
+/// [`verify`](PasswordHash::verify). Hashing uses as much memory and time as the string asks
+/// for, up to [`MAX_M_COST`] and [`MAX_T_COST`]. If the string might not come from trusted
+/// storage, use [`verify_password_with_limits`] instead.
+pub fn verify_password(password: &[u8], phc_string: &str) -> Result<(), PwHashError> {
+    phc_string.parse::<PasswordHash>()?.verify(password)
+}
+
+/// Check a password against a PHC string from [`hash_password`], with a limit on its cost.
+///
+/// Returns an error without hashing anything if the string's `m_cost` or `t_cost` is greater than
+/// the one in `limits`.
+pub fn verify_password_with_limits(
+    password: &[u8],
+    phc_string: &str,
+    limits: &Params,
+) -> Result<(), PwHashError> {
+    let hash = phc_string.parse::<PasswordHash>()?;
+    if hash.params.m_cost > limits.m_cost || hash.params.t_cost > limits.t_cost {
+        return Err(PwHashError(PwHashErrorInner::InvalidParams));
+    }
+    hash.verify(password)
+}
+
+fn check_salt_len(len: usize) -> Result<(), PwHashError> {
+    if (MIN_SALT_LEN..=MAX_SALT_LEN).contains(&len) {
+        Ok(())
+    } else {
+        Err(PwHashError(PwHashErrorInner::InvalidSaltLen(len)))
+    }
+}
+
+/// A password hash together with its parameters and salt.
+///
+/// This type converts to and from the
+/// [PHC string format](https://github.com/P-H-C/phc-string-format/blob/master/phc-sf-spec.md)
+/// with `.to_string()` and `.parse()`:
+///
+/// ```text
+/// $blake3-balloon$v=1$m=<m_cost>,t=<t_cost>$<salt>$<hash>
+/// ```
+///
+/// The salt and hash are in unpadded standard base64, as the format requires.
+#[derive(Clone, Debug)]
+pub struct PasswordHash {
+    params: Params,
+    salt: Vec<u8>,
+    hash: Vec<u8>,
+}
+
+impl PasswordHash {
+    /// The parameters used to compute this hash.
+    pub fn params(&self) -> Params {
+        self.params
+    }
+
+    /// The salt used to compute this hash.
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    /// The raw hash output.
+    pub fn hash(&self) -> &[u8] {
+        &self.hash
+    }
+
+    /// Hash `password` with the same parameters and salt, and compare the result in constant
+    /// time.
+    pub fn verify(&self, password: &[u8]) -> Result<(), PwHashError> {
+        let mut computed = vec![0; self.hash.len()];
+        hash_password_into(password, &self.salt, &self.params, &mut computed)?;
+        if constant_time_eq::constant_time_eq(&computed, &self.hash) {
+            Ok(())
+        } else {
+            Err(PwHashError(PwHashErrorInner::Mismatch))
+        }
+    }
+}
+
+impl fmt::Display for PasswordHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "${}$v={}$m={},t={}$",
+            ALGORITHM_ID, VERSION, self.params.m_cost, self.params.t_cost,
+        )?;
+        write_base64(f, &self.salt)?;
+        f.write_str("$")?;
+        write_base64(f, &self.hash)
+    }
+}
+
+impl FromStr for PasswordHash {
+    type Err = PwHashError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let format_error = PwHashError(PwHashErrorInner::InvalidFormat);
+        let mut fields = s.split('$');
+        if fields.next() != Some("") || fields.next() != Some(ALGORITHM_ID) {
+            return Err(format_error);
+        }
+        match fields.next().and_then(|f| f.strip_prefix("v=")) {
+            Some(version) if parse_decimal(version) == Some(VERSION) => {}
+            _ => return Err(format_error),
+        }
+        let params_field = fields.next().ok_or(format_error.clone())?;
+        let (m_field, t_field) = params_field.split_once(',').ok_or(format_error.clone())?;
+        let m_cost = m_field.strip_prefix("m=").and_then(parse_decimal);
+        let t_cost = t_field.strip_prefix("t=").and_then(parse_decimal);
+        let params = match (m_cost, t_cost) {
+            (Some(m_cost), Some(t_cost)) => Params::new(m_cost, t_cost)?,
+            _ => return Err(format_error),
+        };
+        let salt = fields
+            .next()
+            .and_then(decode_base64)
+            .ok_or(format_error.clone())?;
+        check_salt_len(salt.len())?;
+        let hash = fields
+            .next()
+            .and_then(decode_base64)
+            .ok_or(format_error.clone())?;
+        if fields.next().is_some() || !(MIN_PHC_HASH_LEN..=MAX_PHC_HASH_LEN).contains(&hash.len()) {
+            return Err(format_error);
+        }
+        Ok(Self { params, salt, hash })
+    }
+}
+
+// PHC decimals have no sign and no leading zeros.
+fn parse_decimal(s: &str) -> Option<u32> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) || (s.len() > 1 && s.starts_with('0'))
+    {
+        return None;
+    }
+    s.parse().ok()
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Standard base64 without padding, as the PHC format uses.
+fn write_base64(f: &mut fmt::Formatter, bytes: &[u8]) -> fmt::Result {
+    for group in bytes.chunks(3) {
+        let mut buf = [0; 3];
+        buf[..group.len()].copy_from_slice(group);
+        let bits = (buf[0] as u32) << 16 | (buf[1] as u32) << 8 | buf[2] as u32;
+        // n bytes encode to n+1 characters.
+        for i in 0..=group.len() {
+            let sextet = (bits >> (18 - 6 * i)) & 0x3f;
+            write!(f, "{}", BASE64_ALPHABET[sextet as usize] as char)?;
+        }
+    }
+    Ok(())
+}
+
+// Reject anything that write_base64 wouldn't produce, including nonzero trailing bits.
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    fn sextet(c: u8) -> Option<u32> {
+        BASE64_ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .map(|i| i as u32)
+    }
+    let mut bytes = Vec::with_capacity(s.len() * 3 / 4);
+    for group in s.as_bytes().chunks(4) {
+        if group.len() == 1 {
+            return None;
+        }
+        let mut bits = 0;
+        for (i, &c) in group.iter().enumerate() {
+            bits |= sextet(c)? << (18 - 6 * i);
+        }
+        let decoded = [(bits >> 16) as u8, (bits >> 8) as u8, bits as u8];
+        let len = group.len() - 1;
+        if decoded[len..].iter().any(|&b| b != 0) {
+            return None;
+        }
+        bytes.extend_from_slice(&decoded[..len]);
+    }
+    Some(bytes)
+}
+
+/// The error type for this module.
+///
+/// The `.to_string()` representation of this error currently distinguishes between bad
+/// parameters, a bad salt length, a malformed PHC string, and a password that doesn't match.
+/// This is to help with logging and debugging, but it isn't a stable API detail, and it may
+/// change at any time.
+#[derive(Clone, Debug)]
+pub struct PwHashError(PwHashErrorInner);
+
+#[derive(Clone, Debug)]
+enum PwHashErrorInner {
+    InvalidParams,
+    InvalidSaltLen(usize),
+    InvalidFormat,
+    Mismatch,
+}
+
+impl fmt::Display for PwHashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            PwHashErrorInner::InvalidParams => {
+                write!(f, "password hash parameters out of range")
+            }
+            PwHashErrorInner::InvalidSaltLen(len) => {
+                write!(
+                    f,
+                    "expected {MIN_SALT_LEN} to {MAX_SALT_LEN} salt bytes, got {len}"
+                )
+            }
+            PwHashErrorInner::InvalidFormat => write!(f, "invalid PHC string"),
+            PwHashErrorInner::Mismatch => write!(f, "password verification failed"),
+        }
+    }
+}
+
+impl std::error::Error for PwHashError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::paint_test_input;
+
+    const TEST_SALT: &[u8; 16] = b"sixteen byte slt";
+
+    fn small_params() -> Params {
+        Params::new(MIN_M_COST, 2).unwrap()
+    }
+
+    // A complete PHC string, to pin down its encoding of the parameters, salt, and hash.
+    #[test]
+    fn test_phc_vector() {
+        let hash = hash_password(b"password", TEST_SALT, &small_params()).unwrap();
+        assert_eq!(hash.to_string(), SMALL_PHC_VECTOR);
+    }
+
+    const SMALL_PHC_VECTOR: &str = concat!(
+        "$blake3-balloon$v=1$m=8,t=2$c2l4dGVlbiBieXRlIHNsdA",
+        "$BdbPMxwPF33BHAaPiEqirGGOjvtydji2ByywC7ZNqwc",
+    );
+
+    // Recompute the construction with the reference implementation.
+    #[test]
+    fn test_compare_reference_impl() {
+        let mut password = [0; 1500];
+        paint_test_input(&mut password);
+        let salt = [7; 20];
+        let (m_cost, t_cost) = (9, 2);
+        let mut output = [0; 100];
+        let params = Params::new(m_cost, t_cost).unwrap();
+        hash_password_into(&password, &salt, &params, &mut output).unwrap();
+
+        let mut key_hasher = reference_impl::Hasher::new_derive_key(KEY_CONTEXT);
+        key_hasher.update(&(m_cost as u64).to_le_bytes());
+        key_hasher.update(&(t_cost as u64).to_le_bytes());
+        key_hasher.update(&(output.len() as u64).to_le_bytes());
+        key_hasher.update(&(salt.len() as u64).to_le_bytes());
+        key_hasher.update(&salt);
+        let mut key = [0; KEY_LEN];
+        key_hasher.finalize(&mut key);
+
+        let mut counter = 0u64;
+        let mut h = |input: &[u8], out: &mut [u8]| {
+            let mut hasher = reference_impl::Hasher::new_keyed(&key);
+            hasher.update(input);
+            hasher.update(&counter.to_le_bytes());
+            counter += 1;
+            hasher.finalize(out);
+        };
+        let n = m_cost as usize;
+        let mut blocks = vec![[0; MEMORY_BLOCK_LEN]; n];
+        h(&password, &mut blocks[0]);
+        for i in 1..n {
+            let prev = blocks[i - 1];
+            h(&prev, &mut blocks[i]);
+        }
+        for r in 0..t_cost as u64 {
+            for i in 0..n {
+                let input = [blocks[(i + n - 1) % n], blocks[i]].concat();
+                h(&input, &mut blocks[i]);
+                let mut indices = [0; 24];
+                h(
+                    &[r.to_le_bytes(), (i as u64).to_le_bytes()].concat(),
+                    &mut indices,
+                );
+                for j in 0..3 {
+                    let index = u64::from_le_bytes(indices[8 * j..][..8].try_into().unwrap());
+                    let other = (index % n as u64) as usize;
+                    let input = [blocks[i], blocks[other]].concat();
+                    h(&input, &mut blocks[i]);
+                }
+            }
+        }
+        let mut expected = [0; 100];
+        h(&blocks[n - 1], &mut expected);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_inputs_matter() {
+        let params = small_params();
+        let mut base = [0; 32];
+        hash_password_into(b"password", TEST_SALT, &params, &mut base).unwrap();
+
+        let mut other = [0; 32];
+        hash_password_into(b"passwore", TEST_SALT, &params, &mut other).unwrap();
+        assert_ne!(base, other);
+        hash_password_into(b"password", b"sixteen byte slu", &params, &mut other).unwrap();
+        assert_ne!(base, other);
+        let more_memory = Params::new(MIN_M_COST + 1, 2).unwrap();
+        hash_password_into(b"password", TEST_SALT, &more_memory, &mut other).unwrap();
+        assert_ne!(base, other);
+        let more_time = Params::new(MIN_M_COST, 3).unwrap();
+        hash_password_into(b"password", TEST_SALT, &more_time, &mut other).unwrap();
+        assert_ne!(base, other);
+
+        // The output length matters too, so a shorter output isn't a prefix.
+        let mut shorter = [0; 31];
+        hash_password_into(b"password", TEST_SALT, &params, &mut shorter).unwrap();
+        assert_ne!(base[..31], shorter);
+    }
+
+    #[test]
+    fn test_verify() {
+        let hash = hash_password(b"password", TEST_SALT, &small_params()).unwrap();
+        hash.verify(b"password").unwrap();
+        assert!(hash.verify(b"Password").is_err());
+
+        let phc_string = hash.to_string();
+        verify_password(b"password", &phc_string).unwrap();
+        assert!(verify_password(b"", &phc_string).is_err());
+
+        let parsed: PasswordHash = phc_string.parse().unwrap();
+        assert_eq!(parsed.params(), small_params());
+        assert_eq!(parsed.salt(), TEST_SALT);
+        assert_eq!(parsed.hash(), hash.hash());
+    }
+
+    #[test]
+    fn test_verify_with_limits() {
+        let phc_string = hash_password(b"password", TEST_SALT, &small_params())
+            .unwrap()
+            .to_string();
+        verify_password_with_limits(b"password", &phc_string, &small_params()).unwrap();
+        verify_password_with_limits(b"password", &phc_string, &Params::default()).unwrap();
+        assert!(verify_password_with_limits(b"Password", &phc_string, &small_params()).is_err());
+
+        // Strings over the limits are rejected before hashing. These would be slow otherwise.
+        let limits = Params::new(MIN_M_COST, 1).unwrap();
+        let err = verify_password_with_limits(b"password", &phc_string, &limits).unwrap_err();
+        assert!(matches!(err.0, PwHashErrorInner::InvalidParams));
+        let expensive =
+            "$blake3-balloon$v=1$m=1048576,t=1024$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAA";
+        let err = verify_password_with_limits(b"password", expensive, &limits).unwrap_err();
+        assert!(matches!(err.0, PwHashErrorInner::InvalidParams));
+        let err =
+            verify_password_with_limits(b"password", expensive, &Params::default()).unwrap_err();
+        assert!(matches!(err.0, PwHashErrorInner::InvalidParams));
+    }
+
+    #[test]
+    fn test_params() {
+        assert!(Params::new(MIN_M_COST - 1, 1).is_err());
+        assert!(Params::new(MIN_M_COST, 0).is_err());
+        assert!(Params::new(MAX_M_COST + 1, 1).is_err());
+        assert!(Params::new(MIN_M_COST, MAX_T_COST + 1).is_err());
+        assert!(Params::new(u32::MAX, u32::MAX).is_err());
+        let params = Params::new(MAX_M_COST, MAX_T_COST).unwrap();
+        assert_eq!(params.m_cost(), MAX_M_COST);
+        assert_eq!(params.t_cost(), MAX_T_COST);
+        let params = Params::new(MIN_M_COST, MIN_T_COST).unwrap();
+        assert_eq!(params.m_cost(), MIN_M_COST);
+        assert_eq!(params.t_cost(), MIN_T_COST);
+
+        let mut output = [0; 32];
+        for &len in &[0, MIN_SALT_LEN - 1, MAX_SALT_LEN + 1] {
+            let salt = [0; MAX_SALT_LEN + 1];
+            assert!(hash_password_into(b"", &salt[..len], &params, &mut output).is_err());
+        }
+        for &len in &[MIN_SALT_LEN, MAX_SALT_LEN] {
+            let salt = [0; MAX_SALT_LEN];
+            hash_password_into(b"", &salt[..len], &params, &mut output).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let good = "$blake3-balloon$v=1$m=8,t=2$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAA";
+        good.parse::<PasswordHash>().unwrap();
+        for bad in &[
+            "",
+            "blake3-balloon$v=1$m=8,t=2$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAA",
+            "$argon2id$v=1$m=8,t=2$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAA",
+            "$blake3-balloon$v=2$m=8,t=2$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAA",
+            "$blake3-balloon$m=8,t=2$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAA",
+            "$blake3-balloon$v=1$t=2,m=8$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAA",
+            "$blake3-balloon$v=1$m=08,t=2$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAA",
+            "$blake3-balloon$v=1$m=+8,t=2$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAA",
+            "$blake3-balloon$v=1$m=7,t=2$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAA",
+            "$blake3-balloon$v=1$m=4294967295,t=2$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAA",
+            "$blake3-balloon$v=1$m=8,t=4294967295$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAA",
+            "$blake3-balloon$v=1$m=8,t=2$c2l4dGVlbiBieXRlIHNsdA==$AAAAAAAAAAAAAAAAAAAAAA",
+            "$blake3-balloon$v=1$m=8,t=2$c2l4dGVl$AAAAAAAAAAAAAAAAAAAAAA",
+            "$blake3-balloon$v=1$m=8,t=2$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAB",
+            "$blake3-balloon$v=1$m=8,t=2$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAA",
+            "$blake3-balloon$v=1$m=8,t=2$c2l4dGVlbiBieXRlIHNsdA$AAAAAAAAAAAAAAAAAAAAAA$",
+        ] {
+            assert!(bad.parse::<PasswordHash>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_base64() {
+        struct Encode<'a>(&'a [u8]);
+        impl fmt::Display for Encode<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write_base64(f, self.0)
+            }
+        }
+        // The vectors from RFC 4648, without padding.
+        for &(decoded, encoded) in &[
+            ("", ""),
+            ("f", "Zg"),
+            ("fo", "Zm8"),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg"),
+            ("fooba", "Zm9vYmE"),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(Encode(decoded.as_bytes()).to_string(), encoded);
+            assert_eq!(decode_base64(encoded).unwrap(), decoded.as_bytes());
+        }
+        let mut input = [0; 256];
+        paint_test_input(&mut input);
+        let encoded = Encode(&input).to_string();
+        assert_eq!(decode_base64(&encoded).unwrap(), &input[..]);
+
+        // Nonzero trailing bits, a dangling character, padding, and bad characters are errors.
+        for bad in &["Zh", "Zm9", "Z", "Zg==", "Zm9v-A", "Zm 9v"] {
+            assert!(decode_base64(bad).is_none(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! A deterministic, seekable CSPRNG backed by the extended output of the keyed mode.
+//!
+//! [`Blake3Rng`] implements the [`RngCore`], [`CryptoRng`], and [`SeedableRng`] traits from the
+//! [`rand_core`](https://crates.io/crates/rand_core) crate, so it works with everything in the
+//! [`rand`](https://crates.io/crates/rand) ecosystem. The same seed always produces the same
+//! stream, on every platform, which makes it useful for reproducible simulations and tests. Like
+//! `ChaCha20Rng` from [`rand_chacha`](https://crates.io/crates/rand_chacha), it supports random
+//! access with [`set_word_pos`](Blake3Rng::set_word_pos).
+//!
+//! The output stream for a seed is the extended output of [`keyed_hash`](crate::keyed_hash) of
+//! the empty input, using the seed as the key. The generator is only as unpredictable as its seed.
+//! To get a random seed from the operating system, use `SeedableRng::from_os_rng` with the
+//! `os_rng` feature of `rand_core`, or [`SeedableRng::from_rng`] with another CSPRNG.
+//!
+//! This module requires the `rng` Cargo feature.
+//!
+//! # Example
+//!
+//! ```
+//! use blake3::rng::Blake3Rng;
+//! use rand_core::{RngCore, SeedableRng};
+//!
+//! let mut rng = Blake3Rng::from_key_material("example.com 2026-10-15 simulation v1", b"run 42");
+//! let first = rng.next_u64();
+//!
+//! // Jump back to the start and get the same output.
+//! rng.set_word_pos(0);
+//! assert_eq!(rng.next_u64(), first);
+//! ```
+
+use crate::{Hasher, OutputReader, BLOCK_LEN, KEY_LEN};
+use core::fmt;
+use rand_core::{CryptoRng, RngCore, SeedableRng};
+
+// Generate four blocks at a time. That's the same buffer size as ChaCha20Rng.
+const BUF_LEN: usize = 4 * BLOCK_LEN;
+
+const WORD_LEN: usize = 4;
+
+/// A deterministic CSPRNG that reads from the extended output of the keyed mode.
+///
+/// See the [module level docs](self) for details.
+#[derive(Clone)]
+pub struct Blake3Rng {
+    // The reader is positioned just past the end of `buf`.
+    reader: OutputReader,
+    buf: [u8; BUF_LEN],
+    // The number of bytes of `buf` already used. This is always a multiple of WORD_LEN, and
+    // BUF_LEN means the buffer is empty.
+    index: usize,
+}
+
+impl Blake3Rng {
+    /// Construct a `Blake3Rng` from a seed derived from key material with
+    /// [`derive_key`](crate::derive_key).
+    ///
+    /// This has the same requirements as [`derive_key`](crate::derive_key). **The context string
+    /// should be hardcoded, globally unique, and application-specific.**
+    pub fn from_key_material(context: &str, key_material: &[u8]) -> Self {
+        Self::from_seed(crate::derive_key(context, key_material))
+    }
+
+    fn refill(&mut self) {
+        self.reader.fill(&mut self.buf);
+        self.index = 0;
+    }
+
+    /// The current position in the output stream, in 32-bit words.
+    ///
+    /// Each call to [`next_u32`](RngCore::next_u32) advances the position by one word, and each
+    /// call to [`next_u64`](RngCore::next_u64) advances it by two. Each call to
+    /// [`fill_bytes`](RngCore::fill_bytes) advances it by the number of bytes filled, divided by 4
+    /// and rounded up.
+    pub fn get_word_pos(&self) -> u128 {
+        // The reader is always at a block boundary. Compute its position from the block counter
+        // in u128, because after the last buffer it's 2^64 bytes, which doesn't fit in a u64.
+        debug_assert_eq!(self.reader.position_within_block, 0);
+        let reader_pos = self.reader.inner.counter as u128 * BLOCK_LEN as u128;
+        (reader_pos - (BUF_LEN - self.index) as u128) / WORD_LEN as u128
+    }
+
+    /// Set the position in the output stream, in 32-bit words.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `word_offset` is 2<sup>62</sup> or greater. That's the end of the BLAKE3
+    /// extended output, 2<sup>64</sup> bytes.
+    pub fn set_word_pos(&mut self, word_offset: u128) {
+        assert!(
+            word_offset < 1 << 62,
+            "word offset past the end of the output"
+        );
+        let byte_pos = word_offset as u64 * WORD_LEN as u64;
+        let buf_start = byte_pos - byte_pos % BUF_LEN as u64;
+        self.reader.set_position(buf_start);
+        self.refill();
+        self.index = (byte_pos - buf_start) as usize;
+    }
+}
+
+impl RngCore for Blake3Rng {
+    fn next_u32(&mut self) -> u32 {
+        if self.index == BUF_LEN {
+            self.refill();
+        }
+        let word = u32::from_le_bytes(*arrayref::array_ref!(self.buf, self.index, WORD_LEN));
+        self.index += WORD_LEN;
+        word
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = self.next_u32() as u64;
+        let high = self.next_u32() as u64;
+        (high << 32) | low
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let mut dest = dest;
+        while !dest.is_empty() {
+            if self.index == BUF_LEN {
+                self.refill();
+            }
+            let take = core::cmp::min(dest.len(), BUF_LEN - self.index);
+            dest[..take].copy_from_slice(&self.buf[self.index..][..take]);
+            // Round up to a whole word, so that the position stays word-aligned.
+            self.index += (take + WORD_LEN - 1) & !(WORD_LEN - 1);
+            dest = &mut dest[take..];
+        }
+    }
+}
+
+impl CryptoRng for Blake3Rng {}
+
+impl SeedableRng for Blake3Rng {
+    type Seed = [u8; KEY_LEN];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self {
+            reader: Hasher::new_keyed(&seed).finalize_xof(),
+            buf: [0; BUF_LEN],
+            index: BUF_LEN,
+        }
+    }
+}
+
+// Don't derive(Debug), because the state may be secret.
+impl fmt::Debug for Blake3Rng {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Blake3Rng")
+            .field("word_pos", &self.get_word_pos())
+            .finish()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for Blake3Rng {
+    fn zeroize(&mut self) {
+        // Destructuring to trigger compile error as a reminder to update this impl.
+        let Self { reader, buf, index } = self;
+
+        reader.zeroize();
+        buf.zeroize();
+        index.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::TEST_KEY;
+
+    fn expected_stream() -> [u8; 3 * BUF_LEN] {
+        let mut expected = [0; 3 * BUF_LEN];
+        Hasher::new_keyed(&TEST_KEY)
+            .finalize_xof()
+            .fill(&mut expected);
+        expected
+    }
+
+    fn word(stream: &[u8], word_pos: usize) -> u32 {
+        u32::from_le_bytes(*arrayref::array_ref!(stream, word_pos * WORD_LEN, WORD_LEN))
+    }
+
+    #[test]
+    fn test_words_match_xof() {
+        let expected = expected_stream();
+        let mut rng = Blake3Rng::from_seed(TEST_KEY);
+        for i in 0..(expected.len() / WORD_LEN / 2) {
+            assert_eq!(rng.get_word_pos(), 2 * i as u128);
+            let low = word(&expected, 2 * i) as u64;
+            let high = word(&expected, 2 * i + 1) as u64;
+            assert_eq!(rng.next_u64(), (high << 32) | low);
+        }
+
+        let mut rng = Blake3Rng::from_seed(TEST_KEY);
+        for i in 0..(expected.len() / WORD_LEN) {
+            assert_eq!(rng.next_u32(), word(&expected, i));
+        }
+    }
+
+    #[test]
+    fn test_fill_bytes() {
+        let expected = expected_stream();
+        let mut rng = Blake3Rng::from_seed(TEST_KEY);
+        let mut buf = [0; 3 * BUF_LEN];
+        rng.fill_bytes(&mut buf);
+        assert_eq!(buf[..], expected[..]);
+
+        // Odd lengths skip the rest of the last word.
+        let mut rng = Blake3Rng::from_seed(TEST_KEY);
+        let mut position = 0;
+        for &len in &[1, 7, 64, 300, 0, 5] {
+            let mut buf = [0; 300];
+            rng.fill_bytes(&mut buf[..len]);
+            assert_eq!(buf[..len], expected[position..][..len]);
+            position += (len + 3) / 4 * 4;
+            assert_eq!(rng.get_word_pos(), (position / 4) as u128);
+        }
+        assert_eq!(rng.next_u32(), word(&expected, position / 4));
+    }
+
+    #[test]
+    fn test_word_pos() {
+        let expected = expected_stream();
+        let mut rng = Blake3Rng::from_seed(TEST_KEY);
+        for &pos in &[0, 1, 15, 16, 63, 64, 65, 150, 0, 191] {
+            rng.set_word_pos(pos as u128);
+            assert_eq!(rng.get_word_pos(), pos as u128);
+            assert_eq!(rng.next_u32(), word(&expected, pos));
+        }
+
+        // Seeking to the end of the output works.
+        let max = (1 << 62) - 1;
+        rng.set_word_pos(max);
+        assert_eq!(rng.get_word_pos(), max);
+        rng.next_u32();
+        assert_eq!(rng.get_word_pos(), 1 << 62);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_word_pos_past_end_panics() {
+        Blake3Rng::from_seed(TEST_KEY).set_word_pos(1 << 62);
+    }
+
+    #[test]
+    fn test_seeding() {
+        let mut rng1 = Blake3Rng::from_key_material("blake3 rng test context", b"material");
+        let mut rng2 =
+            Blake3Rng::from_seed(crate::derive_key("blake3 rng test context", b"material"));
+        assert_eq!(rng1.next_u64(), rng2.next_u64());
+
+        let mut rng3 = Blake3Rng::seed_from_u64(42);
+        let mut rng4 = Blake3Rng::seed_from_u64(42);
+        let mut rng5 = Blake3Rng::seed_from_u64(43);
+        let x = rng3.next_u64();
+        assert_eq!(x, rng4.next_u64());
+        assert_ne!(x, rng5.next_u64());
+    }
+
+    #[test]
+    fn test_rand_interop() {
+        use rand::Rng;
+        let mut rng = Blake3Rng::from_seed(TEST_KEY);
+        let mut clone = rng.clone();
+        let x: u32 = rng.random_range(0..1000);
+        assert!(x < 1000);
+        assert_eq!(x, clone.random_range(0..1000));
+    }
+}
@@ -0,0 +1,437 @@
+// Exporting and importing the internal state of a Hasher, so that hashing can be checkpointed and
+// resumed later, possibly in a different process.
+//
+// The version 1 format is fixed-size fields followed by the CV stack, all little-endian:
+//
+//   offset  len  field
+//   0       1    version (1)
+//   1       1    flags (0, KEYED_HASH, or DERIVE_KEY_MATERIAL)
+//   2       1    ChunkState::buf_len
+//   3       1    ChunkState::blocks_compressed
+//   4       32   key
+//   36      8    initial_chunk_counter
+//   44      8    ChunkState::chunk_counter
+//   52      32   ChunkState::cv
+//   84      64   ChunkState::buf
+//   148     1    number of CVs in the stack
+//   149     32*n the CV stack, bottom first
+//
+// If we ever need to change this, we'll bump the version and keep importing version 1.
+
+use crate::{
+    platform, ChunkState, Hasher, Platform, BLOCK_LEN, CHUNK_LEN, DERIVE_KEY_MATERIAL, IV,
+    KEYED_HASH, MAX_DEPTH, OUT_LEN,
+};
+use arrayref::array_ref;
+use arrayvec::ArrayVec;
+use core::fmt;
+
+const VERSION: u8 = 1;
+const STACK_OFFSET: usize = 149;
+const MAX_STATE_LEN: usize = STACK_OFFSET + (MAX_DEPTH + 1) * OUT_LEN;
+
+/// The exported state of a [`Hasher`], returned by [`Hasher::export_state`].
+///
+/// This is a versioned binary format, which future versions of this crate will continue to
+/// import with [`Hasher::import_state`]. Note that the state includes the key, if any, and it
+/// should be protected like the key itself.
+#[derive(Clone)]
+pub struct HasherState(ArrayVec<u8, MAX_STATE_LEN>);
+
+impl HasherState {
+    /// The bytes of the exported state.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for HasherState {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+// Don't derive(Debug), because the state may be secret.
+impl fmt::Debug for HasherState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HasherState")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for HasherState {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// The error type for [`Hasher::import_state`].
+///
+/// The `.to_string()` representation of this error currently describes which check failed. This
+/// is to help with logging and debugging, but it isn't a stable API detail, and it may change at
+/// any time.
+#[derive(Clone, Debug)]
+pub struct StateError(StateErrorInner);
+
+#[derive(Clone, Debug)]
+enum StateErrorInner {
+    InvalidLen(usize),
+    UnsupportedVersion(u8),
+    Invalid(&'static str),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            StateErrorInner::InvalidLen(len) => write!(f, "invalid state length: {}", len),
+            StateErrorInner::UnsupportedVersion(version) => {
+                write!(f, "unsupported state version: {}", version)
+            }
+            StateErrorInner::Invalid(reason) => write!(f, "invalid state: {}", reason),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StateError {}
+
+fn invalid(reason: &'static str) -> StateError {
+    StateError(StateErrorInner::Invalid(reason))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref!(bytes, offset, 8))
+}
+
+impl Hasher {
+    /// Export the internal state of the `Hasher`, so that hashing can be resumed later with
+    /// [`import_state`](Hasher::import_state).
+    ///
+    /// This is useful for checkpointing a long-running hash, for example of a large upload, so
+    /// that it can continue after a restart without rehashing what came before. The state is at
+    /// most a couple of kilobytes, and it doesn't depend on how much input has been hashed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), blake3::StateError> {
+    /// let mut hasher = blake3::Hasher::new();
+    /// hasher.update(b"foo");
+    /// let state = hasher.export_state();
+    ///
+    /// // Later, possibly in a different process...
+    /// let mut resumed = blake3::Hasher::import_state(state.as_bytes())?;
+    /// resumed.update(b"bar");
+    /// assert_eq!(resumed.finalize(), blake3::hash(b"foobar"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_state(&self) -> HasherState {
+        let mut state = ArrayVec::new();
+        state.push(VERSION);
+        state.push(self.chunk_state.flags);
+        state.push(self.chunk_state.buf_len);
+        state.push(self.chunk_state.blocks_compressed);
+        state.extend(platform::le_bytes_from_words_32(&self.key));
+        state.extend(self.initial_chunk_counter.to_le_bytes());
+        state.extend(self.chunk_state.chunk_counter.to_le_bytes());
+        state.extend(platform::le_bytes_from_words_32(&self.chunk_state.cv));
+        state.extend(self.chunk_state.buf);
+        state.push(self.cv_stack.len() as u8);
+        for cv in &self.cv_stack {
+            state.extend(cv.iter().copied());
+        }
+        debug_assert_eq!(state.len(), STACK_OFFSET + self.cv_stack.len() * OUT_LEN);
+        HasherState(state)
+    }
+
+    /// Construct a `Hasher` from a state previously returned by
+    /// [`export_state`](Hasher::export_state).
+    ///
+    /// The state is checked for consistency, and this returns an error rather than panicking if
+    /// it's truncated, corrupt, or from a newer version of this crate. Those checks can't detect
+    /// every possible corruption, though. States stored somewhere that an attacker could modify
+    /// them need to be authenticated by the caller.
+    pub fn import_state(state: &[u8]) -> Result<Self, StateError> {
+        if state.len() < STACK_OFFSET {
+            return Err(StateError(StateErrorInner::InvalidLen(state.len())));
+        }
+        if state[0] != VERSION {
+            return Err(StateError(StateErrorInner::UnsupportedVersion(state[0])));
+        }
+        let stack_len = state[STACK_OFFSET - 1] as usize;
+        if stack_len > MAX_DEPTH + 1 || state.len() != STACK_OFFSET + stack_len * OUT_LEN {
+            return Err(StateError(StateErrorInner::InvalidLen(state.len())));
+        }
+        let flags = state[1];
+        let buf_len = state[2];
+        let blocks_compressed = state[3];
+        let key = platform::words_from_le_bytes_32(array_ref!(state, 4, 32));
+        let initial_chunk_counter = read_u64(state, 36);
+        let chunk_counter = read_u64(state, 44);
+        let cv = platform::words_from_le_bytes_32(array_ref!(state, 52, 32));
+        let buf = *array_ref!(state, 84, BLOCK_LEN);
+
+        match flags {
+            0 if key != *IV => return Err(invalid("unkeyed state with a key")),
+            0 | KEYED_HASH | DERIVE_KEY_MATERIAL => {}
+            _ => return Err(invalid("flags")),
+        }
+
+        // The chunk state always buffers the last block it's seen, and the unused part of the
+        // buffer is always zero.
+        if buf_len as usize > BLOCK_LEN {
+            return Err(invalid("chunk buffer length"));
+        }
+        let chunk_len = blocks_compressed as usize * BLOCK_LEN + buf_len as usize;
+        if chunk_len > CHUNK_LEN || (blocks_compressed > 0 && buf_len == 0) {
+            return Err(invalid("chunk length"));
+        }
+        if blocks_compressed == 0 && cv != key {
+            return Err(invalid("chunk chaining value"));
+        }
+        if buf[buf_len as usize..].iter().any(|&b| b != 0) {
+            return Err(invalid("chunk buffer padding"));
+        }
+
+        // Chunk counters are limited so that byte counts fit in a u64.
+        if chunk_counter >= 1 << MAX_DEPTH || initial_chunk_counter > chunk_counter {
+            return Err(invalid("chunk counter"));
+        }
+        let subtree_chunks = chunk_counter - initial_chunk_counter;
+        let count = subtree_chunks * CHUNK_LEN as u64 + chunk_len as u64;
+        let input_offset = initial_chunk_counter * CHUNK_LEN as u64;
+        if let Some(max) = crate::hazmat::max_subtree_len(input_offset) {
+            if count > max {
+                return Err(invalid("input longer than the subtree at its offset"));
+            }
+        }
+
+        // Each CV in the stack corresponds to a 1-bit in the number of completed chunks. When the
+        // chunk state is empty, the stack might not be merged yet, so it can be longer than that,
+        // but then it always has at least two CVs for finalization to merge.
+        let merged_stack_len = subtree_chunks.count_ones() as usize;
+        let stack_ok = if chunk_len > 0 {
+            stack_len == merged_stack_len
+        } else if subtree_chunks == 0 {
+            stack_len == 0
+        } else {
+            stack_len >= merged_stack_len && stack_len >= 2
+        };
+        if !stack_ok {
+            return Err(invalid("chaining value stack length"));
+        }
+        let cv_stack = state[STACK_OFFSET..]
+            .chunks_exact(OUT_LEN)
+            .map(|cv| *array_ref!(cv, 0, OUT_LEN))
+            .collect();
+
+        Ok(Self {
+            key,
+            chunk_state: ChunkState {
+                cv,
+                chunk_counter,
+                buf,
+                buf_len,
+                blocks_compressed,
+                flags,
+                platform: Platform::detect(),
+            },
+            initial_chunk_counter,
+            cv_stack,
+        })
+    }
+}
+
+/// Serializes the state from [`Hasher::export_state`] as a byte string.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Hasher {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.export_state().as_bytes())
+    }
+}
+
+/// Deserializes a byte string (or a sequence of bytes) with [`Hasher::import_state`].
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Hasher {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct StateVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for StateVisitor {
+            type Value = Hasher;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an exported BLAKE3 hasher state")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Hasher, E> {
+                Hasher::import_state(bytes).map_err(E::custom)
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Hasher, A::Error> {
+                let mut state = ArrayVec::<u8, MAX_STATE_LEN>::new();
+                while let Some(byte) = seq.next_element()? {
+                    state.try_push(byte).map_err(|_| {
+                        serde::de::Error::custom(StateError(StateErrorInner::InvalidLen(
+                            MAX_STATE_LEN + 1,
+                        )))
+                    })?;
+                }
+                Hasher::import_state(&state).map_err(serde::de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_bytes(StateVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hazmat::HasherExt;
+    use crate::test::{paint_test_input, TEST_CASES, TEST_CASES_MAX, TEST_KEY};
+
+    fn test_hashers() -> [Hasher; 3] {
+        [
+            Hasher::new(),
+            Hasher::new_keyed(&TEST_KEY),
+            Hasher::new_derive_key("test context"),
+        ]
+    }
+
+    #[test]
+    fn test_export_import() {
+        let mut input = [0; TEST_CASES_MAX];
+        paint_test_input(&mut input);
+        for &case in TEST_CASES {
+            for split in [0, case / 3, case / 2, case] {
+                for mut hasher in test_hashers() {
+                    let mut expected = hasher.clone();
+                    expected.update(&input[..case]);
+                    hasher.update(&input[..split]);
+                    let state = hasher.export_state();
+                    let mut resumed = Hasher::import_state(state.as_bytes()).unwrap();
+                    assert_eq!(resumed.count(), split as u64);
+                    resumed.update(&input[split..case]);
+                    assert_eq!(resumed.count(), case as u64);
+                    assert_eq!(resumed.finalize(), expected.finalize());
+                    // Exporting again gives the same state.
+                    assert_eq!(
+                        Hasher::import_state(state.as_bytes())
+                            .unwrap()
+                            .export_state()
+                            .as_bytes(),
+                        state.as_bytes(),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_export_import_subtree() {
+        let mut input = [0; 4 * CHUNK_LEN];
+        paint_test_input(&mut input);
+        let mut hasher = Hasher::new();
+        hasher.set_input_offset(4 * CHUNK_LEN as u64);
+        hasher.update(&input[..2 * CHUNK_LEN]);
+        let mut resumed = Hasher::import_state(hasher.export_state().as_bytes()).unwrap();
+        resumed.update(&input[2 * CHUNK_LEN..]);
+        let expected = Hasher::new()
+            .set_input_offset(4 * CHUNK_LEN as u64)
+            .update(&input)
+            .finalize_non_root();
+        assert_eq!(resumed.finalize_non_root(), expected);
+
+        // A state that claims more input than its subtree can hold doesn't import.
+        let mut hasher = Hasher::new();
+        hasher.set_input_offset(CHUNK_LEN as u64);
+        hasher.update(&input[..CHUNK_LEN]);
+        let mut state = hasher.export_state().as_bytes().to_vec();
+        state[44..52].copy_from_slice(&3u64.to_le_bytes());
+        assert!(Hasher::import_state(&state).is_err());
+    }
+
+    #[test]
+    fn test_import_invalid() {
+        let mut input = [0; 3 * CHUNK_LEN + 100];
+        paint_test_input(&mut input);
+        let mut hasher = Hasher::new();
+        hasher.update(&input);
+        let state = hasher.export_state().as_bytes().to_vec();
+        assert!(Hasher::import_state(&state).is_ok());
+
+        let corrupt = |offset: usize, value: u8| {
+            let mut corrupt = state.clone();
+            corrupt[offset] = value;
+            Hasher::import_state(&corrupt)
+        };
+        assert!(corrupt(0, 2).is_err(), "version");
+        assert!(
+            corrupt(1, KEYED_HASH | DERIVE_KEY_MATERIAL).is_err(),
+            "flags"
+        );
+        assert!(corrupt(1, crate::ROOT).is_err(), "flags");
+        assert!(corrupt(2, BLOCK_LEN as u8 + 1).is_err(), "buf_len");
+        assert!(corrupt(2, 0).is_err(), "buf_len with compressed blocks");
+        assert!(corrupt(3, 16).is_err(), "blocks_compressed");
+        assert!(corrupt(4, 0).is_err(), "unkeyed with a key");
+        assert!(corrupt(44, 4).is_err(), "chunk counter");
+        assert!(corrupt(51, 0xff).is_err(), "chunk counter");
+        assert!(corrupt(84 + 40, 1).is_err(), "padding");
+        assert!(corrupt(STACK_OFFSET - 1, 3).is_err(), "stack length");
+
+        // An empty chunk state with compressed blocks, or a non-key CV without them.
+        let mut hasher = Hasher::new();
+        hasher.update(&input[..100]);
+        let state = hasher.export_state().as_bytes().to_vec();
+        let mut corrupt = state.clone();
+        corrupt[3] = 0;
+        assert!(Hasher::import_state(&corrupt).is_err());
+
+        // Truncations and extensions.
+        for len in [0, 1, STACK_OFFSET - 1, state.len() - 1] {
+            assert!(Hasher::import_state(&state[..len]).is_err());
+        }
+        let mut extended = state.clone();
+        extended.push(0);
+        assert!(Hasher::import_state(&extended).is_err());
+
+        // The stack of an empty chunk state can be unmerged, but it can't have just one CV.
+        let mut hasher = Hasher::new();
+        hasher.update(&input[..2 * CHUNK_LEN]);
+        let mut state = hasher.export_state().as_bytes().to_vec();
+        assert_eq!(state[STACK_OFFSET - 1], 2);
+        assert!(Hasher::import_state(&state).is_ok());
+        state[STACK_OFFSET - 1] = 1;
+        state.truncate(STACK_OFFSET + OUT_LEN);
+        assert!(Hasher::import_state(&state).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    #[cfg(feature = "std")]
+    fn test_serde() {
+        let mut hasher = Hasher::new_keyed(&TEST_KEY);
+        hasher.update(&[42; 5000]);
+
+        let json = serde_json::to_string(&hasher).unwrap();
+        let mut resumed: Hasher = serde_json::from_str(&json).unwrap();
+        resumed.update(b"foo");
+        hasher.update(b"foo");
+        assert_eq!(resumed.finalize(), hasher.finalize());
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&hasher, &mut cbor).unwrap();
+        let resumed: Hasher = ciborium::from_reader(&cbor[..]).unwrap();
+        assert_eq!(resumed.finalize(), hasher.finalize());
+
+        assert!(serde_json::from_str::<Hasher>("[1, 2, 3]").is_err());
+    }
+}
@@ -0,0 +1,231 @@
+//! A stream cipher built from the keyed mode's extended output.
+//!
+//! A [`KeyStream`] takes a 32-byte key and a 24-byte nonce. It derives a subkey from the key with
+//! [`derive_key`](crate::derive_key), and produces the extended output of
+//! [`keyed_hash`](crate::keyed_hash) of the nonce under that subkey:
+//!
+//! ```text
+//! subkey    = derive_key("BLAKE3 2026-10-15 stream key v1", key)
+//! keystream = XOF of keyed_hash(subkey, nonce)
+//! ```
+//!
+//! XORing the keystream with a plaintext encrypts it, and XORing it with the ciphertext decrypts
+//! it again. The keystream supports random access, so any part of a message can be encrypted or
+//! decrypted without generating the output that comes before it.
+//!
+//! **A stream cipher alone provides no integrity.** An attacker who flips a bit of the
+//! ciphertext flips the same bit of the decrypted plaintext. Most applications should use
+//! authenticated encryption instead.
+//!
+//! **Never use the same key and nonce for two different messages.** The XOR of two ciphertexts
+//! under the same keystream is the XOR of their plaintexts. The nonce is long enough to be
+//! chosen at random.
+//!
+//! **Use each key for this cipher only.** Don't also use it with [`Mac`](crate::mac::Mac),
+//! [`keyed_hash`](crate::keyed_hash), or [`Hasher::new_keyed`](crate::Hasher::new_keyed). The
+//! subkey keeps the keystream from being a MAC under the key, but nothing here is designed for
+//! keys shared between different constructions.
+//!
+//! This module requires the `stream` Cargo feature. With the `stream-traits-preview` feature,
+//! [`KeyStream`] also implements the `StreamCipher` and `StreamCipherSeek` traits from the
+//! RustCrypto [`cipher`](https://crates.io/crates/cipher) crate.
+//!
+//! # Example
+//!
+//! ```
+//! use blake3::stream::KeyStream;
+//!
+//! let key = [42; 32];
+//! let nonce = [7; 24];
+//! let mut message = *b"attack at dawn";
+//!
+//! KeyStream::new(&key, &nonce).apply_keystream(&mut message);
+//! assert_ne!(&message, b"attack at dawn");
+//!
+//! // Decrypt just the last four bytes.
+//! let mut stream = KeyStream::new(&key, &nonce);
+//! stream.seek(10);
+//! stream.apply_keystream(&mut message[10..]);
+//! assert_eq!(&message[10..], b"dawn");
+//! ```
+
+use crate::{Hasher, OutputReader, BLOCK_LEN, KEY_LEN};
+
+/// The number of bytes in a [`KeyStream`] nonce, 24.
+pub const NONCE_LEN: usize = 24;
+
+pub(crate) const KEYSTREAM_BUF_LEN: usize = 16 * BLOCK_LEN;
+
+const SUBKEY_CONTEXT: &str = "BLAKE3 2026-10-15 stream key v1";
+
+/// A seekable keystream, the extended output of the keyed mode applied to a nonce.
+///
+/// See the [module level docs](self) for the security requirements.
+#[derive(Clone, Debug)]
+pub struct KeyStream {
+    reader: OutputReader,
+}
+
+impl KeyStream {
+    /// Construct a new `KeyStream` at position 0.
+    pub fn new(key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN]) -> Self {
+        Self::from_subkey(&crate::derive_key(SUBKEY_CONTEXT, key), nonce)
+    }
+
+    // The keystream without the subkey derivation. The aead module's encryption key is already a
+    // subkey of its own.
+    pub(crate) fn from_subkey(subkey: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN]) -> Self {
+        Self {
+            reader: Hasher::new_keyed(subkey).update(nonce).finalize_xof(),
+        }
+    }
+
+    /// XOR the keystream into `buf` and advance the position by `buf.len()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this would advance the position past the maximum output length of BLAKE3,
+    /// 2<sup>64</sup>-1 bytes.
+    pub fn apply_keystream(&mut self, buf: &mut [u8]) {
+        assert!(self.has_remaining(buf.len()), "keystream position overflow");
+        // Generate several blocks at a time, so that the OutputReader can use SIMD.
+        let mut keystream = [0; KEYSTREAM_BUF_LEN];
+        for buf_chunk in buf.chunks_mut(KEYSTREAM_BUF_LEN) {
+            let keystream_chunk = &mut keystream[..buf_chunk.len()];
+            self.reader.fill(keystream_chunk);
+            for (byte, key_byte) in buf_chunk.iter_mut().zip(keystream_chunk.iter()) {
+                *byte ^= *key_byte;
+            }
+        }
+    }
+
+    // Whether `len` more bytes of keystream are available from the current position.
+    pub(crate) fn has_remaining(&self, len: usize) -> bool {
+        self.reader.position().checked_add(len as u64).is_some()
+    }
+
+    // Write raw keystream bytes, for the StreamCipher implementation.
+    #[cfg(feature = "stream-traits-preview")]
+    pub(crate) fn fill_keystream(&mut self, keystream: &mut [u8]) {
+        self.reader.fill(keystream);
+    }
+
+    /// Set the position in the keystream, in bytes from the start.
+    pub fn seek(&mut self, position: u64) {
+        self.reader.set_position(position);
+    }
+
+    /// The current position in the keystream, in bytes from the start.
+    pub fn current_pos(&self) -> u64 {
+        self.reader.position()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for KeyStream {
+    fn zeroize(&mut self) {
+        // Destructuring to trigger compile error as a reminder to update this impl.
+        let Self { reader } = self;
+
+        reader.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{paint_test_input, TEST_KEY};
+
+    const TEST_NONCE: [u8; NONCE_LEN] = *b"twenty four byte nonce!!";
+
+    // Recompute the construction with the reference implementation.
+    #[test]
+    fn test_keystream_construction() {
+        let mut subkey = [0; KEY_LEN];
+        let mut reference = reference_impl::Hasher::new_derive_key(SUBKEY_CONTEXT);
+        reference.update(&TEST_KEY);
+        reference.finalize(&mut subkey);
+        let mut expected = [0; 3000];
+        let mut reference = reference_impl::Hasher::new_keyed(&subkey);
+        reference.update(&TEST_NONCE);
+        reference.finalize(&mut expected);
+
+        let mut buf = [0; 3000];
+        paint_test_input(&mut buf);
+        KeyStream::new(&TEST_KEY, &TEST_NONCE).apply_keystream(&mut buf);
+        let mut input = [0; 3000];
+        paint_test_input(&mut input);
+        for i in 0..buf.len() {
+            assert_eq!(buf[i], input[i] ^ expected[i]);
+        }
+
+        // Applying the keystream again is the inverse.
+        KeyStream::new(&TEST_KEY, &TEST_NONCE).apply_keystream(&mut buf);
+        assert_eq!(buf, input);
+    }
+
+    #[test]
+    fn test_keystream_is_not_a_mac() {
+        let mut keystream = [0; 32];
+        KeyStream::new(&TEST_KEY, &TEST_NONCE).apply_keystream(&mut keystream);
+        assert_ne!(
+            keystream,
+            *crate::keyed_hash(&TEST_KEY, &TEST_NONCE).as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_keystream_seek() {
+        let mut expected = [0; 3000];
+        KeyStream::new(&TEST_KEY, &TEST_NONCE).apply_keystream(&mut expected);
+
+        let mut stream = KeyStream::new(&TEST_KEY, &TEST_NONCE);
+        for &(start, len) in &[(0, 1), (1, 63), (64, 64), (100, 2000), (2999, 1), (7, 0)] {
+            let mut buf = [0; 3000];
+            stream.seek(start);
+            assert_eq!(stream.current_pos(), start);
+            stream.apply_keystream(&mut buf[..len]);
+            assert_eq!(stream.current_pos(), start + len as u64);
+            let start = start as usize;
+            assert_eq!(buf[..len], expected[start..][..len]);
+        }
+
+        // Incremental application in odd sizes matches one call.
+        let mut buf = [0; 3000];
+        let mut stream = KeyStream::new(&TEST_KEY, &TEST_NONCE);
+        for chunk in buf.chunks_mut(333) {
+            stream.apply_keystream(chunk);
+        }
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn test_keystream_nonce_and_key_matter() {
+        let mut a = [0; 64];
+        let mut b = [0; 64];
+        let mut c = [0; 64];
+        KeyStream::new(&TEST_KEY, &TEST_NONCE).apply_keystream(&mut a);
+        KeyStream::new(&TEST_KEY, &[0; NONCE_LEN]).apply_keystream(&mut b);
+        KeyStream::new(&[0; KEY_LEN], &TEST_NONCE).apply_keystream(&mut c);
+        assert_ne!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_keystream_end() {
+        let mut stream = KeyStream::new(&TEST_KEY, &TEST_NONCE);
+        stream.seek(u64::MAX - 10);
+        assert!(!stream.has_remaining(11));
+        assert!(stream.has_remaining(10));
+        stream.apply_keystream(&mut [0; 10]);
+        assert_eq!(stream.current_pos(), u64::MAX);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_keystream_overflow_panics() {
+        let mut stream = KeyStream::new(&TEST_KEY, &TEST_NONCE);
+        stream.seek(u64::MAX);
+        stream.apply_keystream(&mut [0; 1]);
+    }
+}
@@ -364,6 +364,14 @@ fn test_compare_reference_impl() {
                 assert_eq!(hasher.finalize(), *array_ref!(expected_out, 0, 32));
                 assert_eq!(hasher.finalize(), test_out);
             }
+            // incremental (scoped threads)
+            #[cfg(feature = "std")]
+            {
+                let mut hasher = crate::Hasher::new();
+                hasher.update_with_join::<crate::join::ScopedThreadJoin>(input);
+                assert_eq!(hasher.finalize(), *array_ref!(expected_out, 0, 32));
+                assert_eq!(hasher.finalize(), test_out);
+            }
             // xof
             let mut extended = [0; OUT];
             hasher.finalize_xof().fill(&mut extended);
@@ -544,6 +552,39 @@ fn test_fuzz_hasher() {
     }
 }
 
+#[test]
+#[cfg(feature = "std")]
+fn test_hash_many_api() {
+    let mut input_buf = [0; 2 * CHUNK_LEN + 1];
+    paint_test_input(&mut input_buf);
+    let context = "BLAKE3 2019-12-27 16:13:59 example context (not the test vector one)";
+
+    // Every length up to just over two chunks, in order. This covers every grouping, and every
+    // group has enough members to fill the SIMD lanes, with a partial batch at the end.
+    let mut messages: Vec<&[u8]> = (0..input_buf.len()).map(|len| &input_buf[..len]).collect();
+    // Repeat the single-chunk lengths in a different order, with offset starting points, so that
+    // groups are interleaved and contain different messages of the same length.
+    for len in (0..=CHUNK_LEN).rev() {
+        for offset in 1..4 {
+            messages.push(&input_buf[offset..][..len]);
+        }
+    }
+
+    let hashes = crate::hash_many(&messages);
+    let keyed_hashes = crate::keyed_hash_many(&TEST_KEY, &messages);
+    let derived_keys = crate::derive_key_many(context, &messages);
+    assert_eq!(hashes.len(), messages.len());
+    assert_eq!(keyed_hashes.len(), messages.len());
+    assert_eq!(derived_keys.len(), messages.len());
+    for (i, message) in messages.iter().enumerate() {
+        assert_eq!(hashes[i], crate::hash(message), "message {}", i);
+        assert_eq!(keyed_hashes[i], crate::keyed_hash(&TEST_KEY, message));
+        assert_eq!(derived_keys[i], crate::derive_key(context, message));
+    }
+
+    assert!(crate::hash_many(&[]).is_empty());
+}
+
 #[test]
 fn test_fuzz_xof() {
     let mut input_buf = [0u8; 3 * BLOCK_LEN];
@@ -580,6 +621,29 @@ fn test_fuzz_xof() {
     }
 }
 
+#[test]
+#[cfg(feature = "rayon")]
+fn test_xof_fill_rayon() {
+    // Long enough to split a couple of times, and not a multiple of the block length.
+    const OUT: usize = 4 * 128 * 1024 + 3 * BLOCK_LEN + 7;
+    let mut expected = vec![0; OUT];
+    let mut hasher = crate::Hasher::new_keyed(&TEST_KEY);
+    hasher.update(b"hello world");
+    hasher.finalize_xof().fill(&mut expected);
+
+    for start in [0, 1, BLOCK_LEN, 2 * BLOCK_LEN - 1] {
+        for end in [start, start + 1, OUT - 1000, OUT] {
+            let mut reader = hasher.finalize_xof();
+            let mut out = vec![0; OUT];
+            reader.fill(&mut out[..start]);
+            reader.fill_rayon(&mut out[start..end]);
+            assert_eq!(reader.position(), end as u64);
+            reader.fill(&mut out[end..]);
+            assert_eq!(expected, out);
+        }
+    }
+}
+
 #[test]
 fn test_xof_seek() {
     let mut out = [0; 533];
@@ -884,6 +948,79 @@ fn test_update_reader_interrupted() -> std::io::Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg(feature = "async")]
+fn test_update_async_reader() -> std::io::Result<()> {
+    // Like test_update_reader, this is brief, and it just checks that the async wrappers agree
+    // with update().
+    let mut input = vec![0; 1_000_000];
+    paint_test_input(&mut input);
+    let expected = crate::hash(&input);
+
+    let mut hasher = crate::Hasher::new();
+    futures::executor::block_on(hasher.update_async_reader(&input[..]))?;
+    assert_eq!(hasher.finalize(), expected);
+
+    let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    let mut hasher = crate::Hasher::new();
+    runtime.block_on(hasher.update_tokio_reader(&input[..]))?;
+    assert_eq!(hasher.finalize(), expected);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "async")]
+fn test_async_write_and_read() -> std::io::Result<()> {
+    use futures::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    let mut input = vec![0; 100_000];
+    paint_test_input(&mut input);
+    let mut expected = [0; 1000];
+    crate::Hasher::new()
+        .update(&input)
+        .finalize_xof()
+        .fill(&mut expected);
+
+    futures::executor::block_on(async {
+        let mut hasher = crate::Hasher::new();
+        hasher.write_all(&input).await?;
+        hasher.close().await?;
+        let mut reader = hasher.finalize_xof();
+        let mut output = [0; 1000];
+        reader.read_exact(&mut output).await?;
+        assert_eq!(expected, output);
+        let pos = reader.seek(std::io::SeekFrom::Start(303)).await?;
+        assert_eq!(pos, 303);
+        reader.read_exact(&mut output[..100]).await?;
+        assert_eq!(expected[303..403], output[..100]);
+        assert!(reader.seek(std::io::SeekFrom::End(0)).await.is_err());
+        Ok::<_, std::io::Error>(())
+    })?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    runtime.block_on(async {
+        let mut hasher = crate::Hasher::new();
+        tokio::io::AsyncWriteExt::write_all(&mut hasher, &input).await?;
+        tokio::io::AsyncWriteExt::shutdown(&mut hasher).await?;
+        let mut reader = hasher.finalize_xof();
+        let mut output = [0; 1000];
+        tokio::io::AsyncReadExt::read_exact(&mut reader, &mut output).await?;
+        assert_eq!(expected, output);
+        let pos =
+            tokio::io::AsyncSeekExt::seek(&mut reader, std::io::SeekFrom::Current(-500)).await?;
+        assert_eq!(pos, 500);
+        tokio::io::AsyncReadExt::read_exact(&mut reader, &mut output[..100]).await?;
+        assert_eq!(expected[500..600], output[..100]);
+        assert!(
+            tokio::io::AsyncSeekExt::seek(&mut reader, std::io::SeekFrom::Current(-1000))
+                .await
+                .is_err()
+        );
+        Ok::<_, std::io::Error>(())
+    })?;
+    Ok(())
+}
+
 #[test]
 #[cfg(feature = "mmap")]
 // NamedTempFile isn't Miri-compatible
@@ -1,6 +1,13 @@
 //! Implementations of commonly used traits like `Digest` and `Mac` from the
-//! [`digest`](https://crates.io/crates/digest) crate.
+//! [`digest`](https://crates.io/crates/digest) crate, and with the `stream-traits-preview`
+//! feature, `StreamCipher` and `StreamCipherSeek` from the
+//! [`cipher`](https://crates.io/crates/cipher) crate. With the `aead-traits-preview` feature, this
+//! also implements `AeadInPlace` from the [`aead`](https://crates.io/crates/aead) crate.
 
+#[cfg(feature = "aead-traits-preview")]
+pub use aead;
+#[cfg(feature = "stream-traits-preview")]
+pub use cipher;
 pub use digest;
 
 use crate::{Hasher, OutputReader};
@@ -85,6 +92,105 @@ impl digest::KeyInit for Hasher {
     }
 }
 
+#[cfg(feature = "stream-traits-preview")]
+impl cipher::KeySizeUser for crate::stream::KeyStream {
+    type KeySize = U32;
+}
+
+#[cfg(feature = "stream-traits-preview")]
+impl cipher::IvSizeUser for crate::stream::KeyStream {
+    type IvSize = cipher::consts::U24;
+}
+
+#[cfg(feature = "stream-traits-preview")]
+impl cipher::KeyIvInit for crate::stream::KeyStream {
+    #[inline]
+    fn new(key: &cipher::Key<Self>, iv: &cipher::Iv<Self>) -> Self {
+        Self::new(key.as_ref(), iv.as_ref())
+    }
+}
+
+#[cfg(feature = "stream-traits-preview")]
+impl cipher::StreamCipher for crate::stream::KeyStream {
+    fn try_apply_keystream_inout(
+        &mut self,
+        mut buf: cipher::inout::InOutBuf<'_, '_, u8>,
+    ) -> Result<(), cipher::StreamCipherError> {
+        if !self.has_remaining(buf.len()) {
+            return Err(cipher::StreamCipherError);
+        }
+        let mut keystream = [0; crate::stream::KEYSTREAM_BUF_LEN];
+        while !buf.is_empty() {
+            let chunk_len = core::cmp::min(buf.len(), keystream.len());
+            let (mut chunk, rest) = buf.split_at(chunk_len);
+            self.fill_keystream(&mut keystream[..chunk_len]);
+            chunk.xor_in2out(&keystream[..chunk_len]);
+            buf = rest;
+        }
+        Ok(())
+    }
+}
+
+// The cipher crate describes positions as a block counter and a byte offset within the block.
+// This keystream is seekable to any byte, so we use a "block size" of 1, which makes the block
+// counter equal to the byte position.
+#[cfg(feature = "stream-traits-preview")]
+impl cipher::StreamCipherSeek for crate::stream::KeyStream {
+    fn try_current_pos<T: cipher::SeekNum>(&self) -> Result<T, cipher::OverflowError> {
+        T::from_block_byte(self.current_pos(), 0, 1)
+    }
+
+    fn try_seek<T: cipher::SeekNum>(&mut self, pos: T) -> Result<(), cipher::StreamCipherError> {
+        let (pos, _) = pos.into_block_byte::<u64>(1)?;
+        self.seek(pos);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "aead-traits-preview")]
+impl aead::KeySizeUser for crate::aead::AeadKey {
+    type KeySize = U32;
+}
+
+#[cfg(feature = "aead-traits-preview")]
+impl aead::KeyInit for crate::aead::AeadKey {
+    #[inline]
+    fn new(key: &aead::Key<Self>) -> Self {
+        Self::new(key.as_ref())
+    }
+}
+
+#[cfg(feature = "aead-traits-preview")]
+impl aead::AeadCore for crate::aead::AeadKey {
+    type NonceSize = aead::consts::U24;
+    type TagSize = U32;
+    type CiphertextOverhead = aead::consts::U0;
+}
+
+#[cfg(feature = "aead-traits-preview")]
+impl aead::AeadInPlace for crate::aead::AeadKey {
+    fn encrypt_in_place_detached(
+        &self,
+        nonce: &aead::Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> aead::Result<aead::Tag<Self>> {
+        let tag = self.seal_in_place(nonce.as_ref(), associated_data, buffer);
+        Ok(tag.into())
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &aead::Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &aead::Tag<Self>,
+    ) -> aead::Result<()> {
+        self.open_in_place(nonce.as_ref(), associated_data, buffer, tag.as_ref())
+            .map_err(|_| aead::Error)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -224,4 +330,78 @@ mod test {
             assert_eq!(expected, output.as_ref());
         }
     }
+
+    #[test]
+    #[cfg(feature = "stream-traits-preview")]
+    fn test_cipher_traits() {
+        use crate::stream::KeyStream;
+        use cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+
+        let key = [42; 32];
+        let nonce = [7; 24];
+        let mut expected = [0; 3000];
+        crate::test::paint_test_input(&mut expected);
+        let mut input = expected;
+        KeyStream::new(&key, &nonce).apply_keystream(&mut expected);
+
+        // In place.
+        let mut stream: KeyStream = KeyIvInit::new(&key.into(), &nonce.into());
+        let mut buf = input;
+        StreamCipher::apply_keystream(&mut stream, &mut buf);
+        assert_eq!(buf[..], expected[..]);
+        assert_eq!(StreamCipherSeek::current_pos::<u64>(&stream), 3000);
+
+        // Separate input and output buffers.
+        let mut stream: KeyStream = KeyIvInit::new(&key.into(), &nonce.into());
+        let mut out = [0; 3000];
+        stream.apply_keystream_b2b(&input, &mut out).unwrap();
+        assert_eq!(out[..], expected[..]);
+
+        // Seeking, with a few integer types.
+        StreamCipherSeek::seek(&mut stream, 1000u32);
+        assert_eq!(StreamCipherSeek::current_pos::<u32>(&stream), 1000);
+        StreamCipher::apply_keystream(&mut stream, &mut input[1000..1100]);
+        assert_eq!(StreamCipherSeek::current_pos::<u128>(&stream), 1100);
+        assert_eq!(input[1000..1100], expected[1000..1100]);
+        assert!(stream.try_seek(-1i32).is_err());
+
+        // Overflowing the keystream is an error, and it leaves the buffer alone.
+        StreamCipherSeek::seek(&mut stream, u64::MAX - 1);
+        let mut buf = [1; 2];
+        assert!(stream.try_apply_keystream(&mut buf).is_err());
+        assert_eq!(buf, [1; 2]);
+    }
+
+    #[test]
+    #[cfg(feature = "aead-traits-preview")]
+    fn test_aead_traits() {
+        use crate::aead::AeadKey;
+        use aead::{AeadInPlace, KeyInit};
+
+        let key = [42; 32];
+        let nonce = [7; 24];
+        let inherent_key = AeadKey::new(&key);
+        let trait_key: AeadKey = KeyInit::new(&key.into());
+
+        let mut expected = *b"attack at dawn";
+        let expected_tag = inherent_key.seal_in_place(&nonce, b"ad", &mut expected);
+
+        let mut buffer = *b"attack at dawn";
+        let tag = trait_key
+            .encrypt_in_place_detached(&nonce.into(), b"ad", &mut buffer)
+            .unwrap();
+        assert_eq!(buffer, expected);
+        assert_eq!(tag[..], expected_tag[..]);
+
+        trait_key
+            .decrypt_in_place_detached(&nonce.into(), b"ad", &mut buffer, &tag)
+            .unwrap();
+        assert_eq!(&buffer, b"attack at dawn");
+
+        let mut bad_tag = tag;
+        bad_tag[0] ^= 1;
+        assert!(trait_key
+            .decrypt_in_place_detached(&nonce.into(), b"ad", &mut buffer, &bad_tag)
+            .is_err());
+    }
 }
@@ -0,0 +1,1071 @@
+//! Verified streaming, in the style of [Bao](https://github.com/oconnor663/bao)
+//!
+//! A BLAKE3 [`Hash`](struct@Hash) commits to the entire tree of chunk and parent chaining values
+//! beneath it. If you store those interior chaining values alongside the input, a recipient who
+//! knows only the root hash can verify each chunk as it arrives, rather than buffering the whole
+//! input and checking it at the end. This module produces that encoding and decodes it again.
+//!
+//! There are two encoding formats:
+//!
+//! - The **combined** encoding, from [`encode`], interleaves parent nodes and chunk bytes in a
+//!   single stream. It's [`encoded_len`] bytes long.
+//! - The **outboard** encoding, from [`outboard`], contains only the parent nodes. The recipient
+//!   reads chunk bytes from the original input and parent nodes from the outboard encoding. It's
+//!   [`outboard_len`] bytes long.
+//!
+//! Both formats begin with an 8-byte header, the little-endian length of the input. That's followed
+//! by the nodes of the tree in pre-order: each parent node (the 32-byte chaining values of its left
+//! and right children) comes before its left subtree, which comes before its right subtree. The
+//! split between the left and right subtrees is given by
+//! [`left_subtree_len`], so the tree is exactly the one that
+//! [`hash`](crate::hash()) computes. This is the same layout as the Bao encoding format.
+//!
+//! The header isn't authenticated by itself. Instead, the [`Decoder`] verifies the header
+//! implicitly, because a different length would lead to a different tree with a different root.
+//! Verifying the final chunk is what confirms the length.
+//!
+//! The [`Decoder`] also implements [`std::io::Seek`], verifying only the parent nodes on
+//! the path to the target chunk. Similarly, a [`SliceExtractor`] pulls out just the parts of an
+//! encoding needed to verify a range of the input, and a [`SliceDecoder`] verifies that slice.
+//!
+//! All three modes of BLAKE3 are supported, using [`hazmat::Mode`](crate::hazmat::Mode). The mode
+//! given to the [`Decoder`] must match the mode given to the encoder.
+//!
+//! # Example
+//!
+//! ```
+//! # fn main() -> std::io::Result<()> {
+//! use blake3::hazmat::Mode;
+//! use blake3::verified::{encode, Decoder};
+//! use std::io::prelude::*;
+//!
+//! let input = vec![0xab; 1_000_000];
+//! let (encoded, hash) = encode(&input, Mode::Hash);
+//! assert_eq!(hash, blake3::hash(&input));
+//!
+//! // The decoder returns input bytes only after verifying them.
+//! let mut decoded = Vec::new();
+//! Decoder::new(&encoded[..], &hash, Mode::Hash).read_to_end(&mut decoded)?;
+//! assert_eq!(input, decoded);
+//!
+//! // Corrupting the encoding makes reads fail.
+//! let mut corrupt = encoded.clone();
+//! corrupt[500_000] ^= 1;
+//! let err = Decoder::new(&corrupt[..], &hash, Mode::Hash)
+//!     .read_to_end(&mut Vec::new())
+//!     .unwrap_err();
+//! assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::hazmat::{left_subtree_len, ChainingValue, Mode};
+use crate::platform::Platform;
+use crate::proof::parent_nodes_len;
+use crate::{CVWords, ChunkState, Hash, Output, CHUNK_LEN, OUT_LEN};
+use std::cmp;
+use std::io;
+use std::io::prelude::*;
+
+/// The length of the encoding header in bytes, 8.
+pub const HEADER_LEN: usize = 8;
+
+/// The length of an encoded parent node in bytes, 64.
+pub const PARENT_LEN: usize = 2 * OUT_LEN;
+
+/// The length of the combined encoding of an input of `content_len` bytes.
+///
+/// The result is a `u128`, because the encoding of a very large input can be longer than
+/// `u64::MAX`.
+pub fn encoded_len(content_len: u64) -> u128 {
+    HEADER_LEN as u128 + parent_nodes_len(content_len) as u128 + content_len as u128
+}
+
+/// The length of the outboard encoding of an input of `content_len` bytes.
+pub fn outboard_len(content_len: u64) -> u128 {
+    HEADER_LEN as u128 + parent_nodes_len(content_len) as u128
+}
+
+/// Compute the combined encoding of `input`, returning the encoding and the root hash.
+///
+/// The root hash is the same as [`hash`](crate::hash), [`keyed_hash`](crate::keyed_hash), or
+/// [`derive_key`](crate::derive_key) would return for the same input, depending on the `mode`.
+pub fn encode(input: &[u8], mode: Mode) -> (Vec<u8>, Hash) {
+    encode_inner(input, mode, true)
+}
+
+/// Compute the outboard encoding of `input`, returning the encoding and the root hash.
+///
+/// The outboard encoding doesn't include any input bytes. To decode it, give both the original
+/// input and the outboard encoding to [`Decoder::new_outboard`].
+pub fn outboard(input: &[u8], mode: Mode) -> (Vec<u8>, Hash) {
+    encode_inner(input, mode, false)
+}
+
+fn encode_inner(input: &[u8], mode: Mode, combined: bool) -> (Vec<u8>, Hash) {
+    let content_len = input.len() as u64;
+    let capacity = if combined {
+        encoded_len(content_len)
+    } else {
+        outboard_len(content_len)
+    };
+    let mut encoding = Vec::with_capacity(capacity as usize);
+    encoding.extend_from_slice(&content_len.to_le_bytes());
+    let root_output = encode_subtree(
+        input,
+        0,
+        &mode.key_words(),
+        mode.flags_byte(),
+        Platform::detect(),
+        combined,
+        &mut encoding,
+    );
+    debug_assert_eq!(encoding.len() as u128, capacity);
+    (encoding, root_output.root_hash())
+}
+
+// Write out the pre-order encoding of a subtree and return its Output, which the caller finalizes
+// as either a chaining value or the root hash. Parent nodes are written before their children, so
+// we reserve space for each parent and fill it in once both children are done.
+fn encode_subtree(
+    input: &[u8],
+    chunk_counter: u64,
+    key: &CVWords,
+    flags: u8,
+    platform: Platform,
+    combined: bool,
+    encoding: &mut Vec<u8>,
+) -> Output {
+    if input.len() <= CHUNK_LEN {
+        if combined {
+            encoding.extend_from_slice(input);
+        }
+        return ChunkState::new(key, chunk_counter, flags, platform)
+            .update(input)
+            .output();
+    }
+    let parent_start = encoding.len();
+    encoding.extend_from_slice(&[0; PARENT_LEN]);
+    let (left, right) = input.split_at(left_subtree_len(input.len() as u64) as usize);
+    let right_chunk_counter = chunk_counter + (left.len() / CHUNK_LEN) as u64;
+    let left_cv = encode_subtree(
+        left,
+        chunk_counter,
+        key,
+        flags,
+        platform,
+        combined,
+        encoding,
+    )
+    .chaining_value();
+    let right_cv = encode_subtree(
+        right,
+        right_chunk_counter,
+        key,
+        flags,
+        platform,
+        combined,
+        encoding,
+    )
+    .chaining_value();
+    encoding[parent_start..][..OUT_LEN].copy_from_slice(&left_cv);
+    encoding[parent_start + OUT_LEN..][..OUT_LEN].copy_from_slice(&right_cv);
+    crate::parent_node_output(&left_cv, &right_cv, key, flags, platform)
+}
+
+// A subtree that the decoder hasn't verified yet, along with the chaining value (or, for the root,
+// the hash) that it's expected to have.
+#[derive(Clone, Copy, Debug)]
+struct Subtree {
+    start: u64,
+    len: u64,
+    expected: ChainingValue,
+    is_root: bool,
+}
+
+impl Subtree {
+    fn chunk_counter(&self) -> u64 {
+        self.start / CHUNK_LEN as u64
+    }
+
+    fn end(&self) -> u64 {
+        self.start + self.len
+    }
+}
+
+fn hash_mismatch() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "hash mismatch")
+}
+
+// Slices always include at least one byte of content (if there is any), even when the requested
+// slice is empty or starts past the end. That way the final chunk is included whenever the slice
+// reaches the end, and the decoder can verify the content length. Returns the range of content
+// bytes whose chunks and parent nodes belong in the slice.
+fn slice_bounds(slice_start: u64, slice_len: u64, content_len: u64) -> (u64, u64) {
+    let start = cmp::min(slice_start, content_len.saturating_sub(1));
+    let end = cmp::min(slice_start.saturating_add(slice_len), content_len);
+    (start, cmp::max(end, start + 1))
+}
+
+// The root always belongs in a slice, including the empty root chunk of the empty input.
+fn in_slice(start: u64, len: u64, is_root: bool, bounds: (u64, u64)) -> bool {
+    is_root || (start < bounds.1 && start + len > bounds.0)
+}
+
+// Reads the header, parent nodes, and chunks of an encoding in order, from either a combined
+// encoding or an input and its outboard encoding. This keeps track of how far into each stream
+// we've read, so that seeking can use relative offsets and doesn't need to assume that the
+// encoding starts at offset zero.
+struct EncodingReader<T, O> {
+    input: T,
+    outboard: Option<O>,
+    input_pos: u64,
+    outboard_pos: u64,
+}
+
+impl<T: Read, O: Read> EncodingReader<T, O> {
+    fn new(input: T, outboard: Option<O>) -> Self {
+        Self {
+            input,
+            outboard,
+            input_pos: 0,
+            outboard_pos: 0,
+        }
+    }
+
+    // The header and the parent nodes come from the outboard encoding if there is one, or from
+    // the combined encoding otherwise.
+    fn read_tree_bytes(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        match self.outboard {
+            Some(ref mut outboard) => {
+                outboard.read_exact(buf)?;
+                self.outboard_pos += buf.len() as u64;
+            }
+            None => {
+                self.input.read_exact(buf)?;
+                self.input_pos += buf.len() as u64;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_header(&mut self) -> io::Result<u64> {
+        let mut header = [0; HEADER_LEN];
+        self.read_tree_bytes(&mut header)?;
+        Ok(u64::from_le_bytes(header))
+    }
+
+    fn read_parent(&mut self) -> io::Result<[u8; PARENT_LEN]> {
+        let mut parent = [0; PARENT_LEN];
+        self.read_tree_bytes(&mut parent)?;
+        Ok(parent)
+    }
+
+    fn read_chunk(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.input.read_exact(buf)?;
+        self.input_pos += buf.len() as u64;
+        Ok(())
+    }
+}
+
+fn seek_forward_to(reader: &mut impl Seek, pos: &mut u64, target: u64) -> io::Result<()> {
+    let offset = i64::try_from(target as i128 - *pos as i128)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "seek offset overflow"))?;
+    if offset != 0 {
+        reader.seek(io::SeekFrom::Current(offset))?;
+        *pos = target;
+    }
+    Ok(())
+}
+
+impl<T: Read + Seek, O: Read + Seek> EncodingReader<T, O> {
+    // Seek back to the first node after the header.
+    fn seek_to_root(&mut self) -> io::Result<()> {
+        match self.outboard {
+            Some(ref mut outboard) => {
+                seek_forward_to(outboard, &mut self.outboard_pos, HEADER_LEN as u64)?;
+                seek_forward_to(&mut self.input, &mut self.input_pos, 0)
+            }
+            None => seek_forward_to(&mut self.input, &mut self.input_pos, HEADER_LEN as u64),
+        }
+    }
+
+    // Seek past all the parent nodes and chunks of a subtree of `len` content bytes.
+    fn skip_subtree(&mut self, len: u64) -> io::Result<()> {
+        let parents_len = parent_nodes_len(len);
+        match self.outboard {
+            Some(ref mut outboard) => {
+                let outboard_target = self.outboard_pos + parents_len;
+                seek_forward_to(outboard, &mut self.outboard_pos, outboard_target)?;
+                let input_target = self.input_pos + len;
+                seek_forward_to(&mut self.input, &mut self.input_pos, input_target)
+            }
+            None => {
+                let target = self.input_pos + parents_len + len;
+                seek_forward_to(&mut self.input, &mut self.input_pos, target)
+            }
+        }
+    }
+}
+
+/// An incremental decoder for the combined and outboard encodings, which implements [`Read`] and
+/// (if the underlying readers do) [`Seek`].
+///
+/// Every byte returned by [`read`](Read::read) belongs to a chunk that's already been verified
+/// against the root hash. If the encoding has been corrupted, `read` returns an error of kind
+/// [`InvalidData`](std::io::ErrorKind::InvalidData), and no bytes from the corrupt chunk are
+/// returned. Callers who read to the end without an error have received exactly the original
+/// input.
+///
+/// Note that an attacker who controls the encoding can always truncate it, which leads to an
+/// [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) error. Bytes that were returned before
+/// that error are still correct, but they're not the whole input.
+///
+/// Seeking verifies the parent nodes on the path from the root to the target chunk and skips
+/// everything else, so reading a small part of a large encoding only costs a logarithmic number
+/// of parent nodes in addition to the chunks that are actually read. Seeking past the end is
+/// allowed, but the following read still verifies the final chunk before returning EOF.
+pub struct Decoder<T, O = T> {
+    reader: EncodingReader<T, O>,
+    key: CVWords,
+    flags: u8,
+    platform: Platform,
+    root_hash: Hash,
+    content_len: Option<u64>,
+    // Pending subtrees, with the next one to be decoded on top.
+    stack: Vec<Subtree>,
+    // The current read position in the content.
+    pos: u64,
+    // The most recently verified chunk.
+    buf: [u8; CHUNK_LEN],
+    buf_start: u64,
+    buf_len: usize,
+    // Set by SliceDecoder. See slice_bounds().
+    slice: Option<(u64, u64)>,
+}
+
+impl<T: Read> Decoder<T> {
+    /// Construct a new `Decoder` for a combined encoding from [`encode`].
+    pub fn new(encoded: T, hash: &Hash, mode: Mode) -> Self {
+        Self::new_inner(encoded, None, hash, mode)
+    }
+}
+
+impl<T: Read, O: Read> Decoder<T, O> {
+    /// Construct a new `Decoder` that reads input bytes from `input` and parent nodes from an
+    /// outboard encoding from [`outboard`].
+    pub fn new_outboard(input: T, outboard: O, hash: &Hash, mode: Mode) -> Self {
+        Self::new_inner(input, Some(outboard), hash, mode)
+    }
+
+    fn new_inner(input: T, outboard: Option<O>, hash: &Hash, mode: Mode) -> Self {
+        Self {
+            reader: EncodingReader::new(input, outboard),
+            key: mode.key_words(),
+            flags: mode.flags_byte(),
+            platform: Platform::detect(),
+            root_hash: *hash,
+            content_len: None,
+            stack: Vec::new(),
+            pos: 0,
+            buf: [0; CHUNK_LEN],
+            buf_start: 0,
+            buf_len: 0,
+            slice: None,
+        }
+    }
+
+    /// Return the length of the input, reading the encoding header if it hasn't been read yet.
+    ///
+    /// The header isn't verified until the final chunk is decoded. See the [module level
+    /// docs](index.html).
+    pub fn content_len(&mut self) -> io::Result<u64> {
+        if let Some(len) = self.content_len {
+            return Ok(len);
+        }
+        let len = self.reader.read_header()?;
+        self.content_len = Some(len);
+        self.stack.push(self.root_subtree(len));
+        Ok(len)
+    }
+
+    /// Return the underlying reader (or, with an outboard encoding, the reader of input bytes).
+    pub fn into_inner(self) -> T {
+        self.reader.input
+    }
+
+    fn root_subtree(&self, content_len: u64) -> Subtree {
+        Subtree {
+            start: 0,
+            len: content_len,
+            expected: *self.root_hash.as_bytes(),
+            is_root: true,
+        }
+    }
+
+    fn verify(&self, subtree: &Subtree, output: &Output) -> io::Result<()> {
+        let verified = if subtree.is_root {
+            output.root_hash() == subtree.expected
+        } else {
+            constant_time_eq::constant_time_eq_32(&output.chaining_value(), &subtree.expected)
+        };
+        if verified {
+            Ok(())
+        } else {
+            Err(hash_mismatch())
+        }
+    }
+
+    // Read and verify the parent node of the subtree on top of the stack, and replace that
+    // subtree with its children. We don't pop the subtree until it's verified, so after an error
+    // we never skip ahead to bytes that come after the corrupt node.
+    fn decode_parent(&mut self) -> io::Result<()> {
+        let subtree = *self.stack.last().unwrap();
+        debug_assert!(subtree.len > CHUNK_LEN as u64);
+        let parent = self.reader.read_parent()?;
+        let left_cv: ChainingValue = parent[..OUT_LEN].try_into().unwrap();
+        let right_cv: ChainingValue = parent[OUT_LEN..].try_into().unwrap();
+        let output =
+            crate::parent_node_output(&left_cv, &right_cv, &self.key, self.flags, self.platform);
+        self.verify(&subtree, &output)?;
+        let left_len = left_subtree_len(subtree.len);
+        self.stack.pop();
+        self.stack.push(Subtree {
+            start: subtree.start + left_len,
+            len: subtree.len - left_len,
+            expected: right_cv,
+            is_root: false,
+        });
+        self.stack.push(Subtree {
+            start: subtree.start,
+            len: left_len,
+            expected: left_cv,
+            is_root: false,
+        });
+        Ok(())
+    }
+
+    // Read and verify the chunk on top of the stack into the buffer.
+    fn decode_chunk(&mut self) -> io::Result<()> {
+        let subtree = *self.stack.last().unwrap();
+        debug_assert!(subtree.len <= CHUNK_LEN as u64);
+        debug_assert_eq!(subtree.start % CHUNK_LEN as u64, 0);
+        let chunk_len = subtree.len as usize;
+        // Clear the buffer first, so that nothing unverified is ever readable.
+        self.buf_len = 0;
+        self.reader.read_chunk(&mut self.buf[..chunk_len])?;
+        let output = ChunkState::new(
+            &self.key,
+            subtree.chunk_counter(),
+            self.flags,
+            self.platform,
+        )
+        .update(&self.buf[..chunk_len])
+        .output();
+        self.verify(&subtree, &output)?;
+        self.stack.pop();
+        self.buf_start = subtree.start;
+        self.buf_len = chunk_len;
+        Ok(())
+    }
+}
+
+impl<T: Read, O: Read> Read for Decoder<T, O> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let content_len = self.content_len()?;
+        let output_end = match self.slice {
+            Some((slice_start, slice_len)) => {
+                cmp::min(slice_start.saturating_add(slice_len), content_len)
+            }
+            None => content_len,
+        };
+        loop {
+            let buf_end = cmp::min(self.buf_start + self.buf_len as u64, output_end);
+            if self.buf_start <= self.pos && self.pos < buf_end {
+                let offset = (self.pos - self.buf_start) as usize;
+                let take = cmp::min(buf.len() as u64, buf_end - self.pos) as usize;
+                buf[..take].copy_from_slice(&self.buf[offset..][..take]);
+                self.pos += take as u64;
+                return Ok(take);
+            }
+            let Some(&subtree) = self.stack.last() else {
+                return Ok(0);
+            };
+            if let Some((slice_start, slice_len)) = self.slice {
+                // Subtrees outside the slice aren't in the stream at all. Subtrees on the stack
+                // are in order, so once we reach one past the end, we're done.
+                let bounds = slice_bounds(slice_start, slice_len, content_len);
+                if !in_slice(subtree.start, subtree.len, subtree.is_root, bounds) {
+                    if subtree.start >= bounds.1 {
+                        return Ok(0);
+                    }
+                    self.stack.pop();
+                    continue;
+                }
+            }
+            if subtree.len > CHUNK_LEN as u64 {
+                self.decode_parent()?;
+            } else {
+                self.decode_chunk()?;
+            }
+        }
+    }
+}
+
+impl<T: Read + Seek, O: Read + Seek> Seek for Decoder<T, O> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let content_len = self.content_len()?;
+        let target: i128 = match pos {
+            io::SeekFrom::Start(x) => x as i128,
+            io::SeekFrom::Current(x) => self.pos as i128 + x as i128,
+            io::SeekFrom::End(x) => content_len as i128 + x as i128,
+        };
+        if target < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before start",
+            ));
+        }
+        let target = cmp::min(target, u64::MAX as i128) as u64;
+        self.pos = target;
+        if self.buf_start <= target && target < self.buf_start + self.buf_len as u64 {
+            // The target is in the chunk we've already verified.
+            return Ok(target);
+        }
+        // If the target comes before the next subtree on the stack, start over from the root.
+        // Otherwise we can keep going from where we are.
+        let target_is_behind = match self.stack.last() {
+            Some(subtree) => target < subtree.start,
+            None => target < content_len,
+        };
+        if target_is_behind {
+            self.reader.seek_to_root()?;
+            self.stack.clear();
+            self.stack.push(self.root_subtree(content_len));
+            self.buf_len = 0;
+        }
+        // Descend to the chunk containing the target, skipping subtrees that end before it. When
+        // the target is past the end, this stops at the final chunk, so that the length still
+        // gets verified.
+        let last_byte = cmp::min(target, content_len.saturating_sub(1));
+        while let Some(&subtree) = self.stack.last() {
+            if !subtree.is_root && subtree.end() <= last_byte {
+                self.reader.skip_subtree(subtree.len)?;
+                self.stack.pop();
+            } else if subtree.len > CHUNK_LEN as u64 {
+                self.decode_parent()?;
+            } else {
+                break;
+            }
+        }
+        Ok(target)
+    }
+}
+
+impl<T, O> std::fmt::Debug for Decoder<T, O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Decoder")
+            .field("content_len", &self.content_len)
+            .field("position", &self.pos)
+            .finish()
+    }
+}
+
+/// Extract a slice of a combined or outboard encoding, which implements [`Read`].
+///
+/// The slice includes the header, the parent nodes on the path from the root to each chunk that
+/// overlaps the content range `slice_start..slice_start + slice_len`, and those chunks. Everything
+/// else is skipped with [`Seek`]. The [`SliceDecoder`] verifies a slice against the root hash and
+/// returns the content bytes in the requested range.
+///
+/// The extractor doesn't verify anything. It's intended for servers that have the whole
+/// encoding, and the client decoding the slice is the one who needs to trust it.
+///
+/// If the requested range is empty or goes past the end of the content, the slice still includes
+/// the final chunk, so that the decoder can verify the content length. This means that asking for
+/// an empty slice at the end of the content is a way to prove how long the content is.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use blake3::hazmat::Mode;
+/// use blake3::verified::{encode, SliceDecoder, SliceExtractor};
+/// use std::io::{prelude::*, Cursor};
+///
+/// let input = vec![0xab; 1_000_000];
+/// let (encoded, hash) = encode(&input, Mode::Hash);
+///
+/// // Extract 1000 bytes from the middle of the input. The slice is much shorter than the whole
+/// // encoding.
+/// let (slice_start, slice_len) = (500_000, 1000);
+/// let mut slice = Vec::new();
+/// SliceExtractor::new(Cursor::new(&encoded), slice_start, slice_len).read_to_end(&mut slice)?;
+/// assert!(slice.len() < 3000);
+///
+/// // Decoding the slice verifies it and returns only the requested bytes.
+/// let mut decoded = Vec::new();
+/// SliceDecoder::new(&slice[..], &hash, slice_start, slice_len, Mode::Hash)
+///     .read_to_end(&mut decoded)?;
+/// assert_eq!(&input[500_000..501_000], &decoded[..]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct SliceExtractor<T, O = T> {
+    reader: EncodingReader<T, O>,
+    slice_start: u64,
+    slice_len: u64,
+    content_len: Option<u64>,
+    // Pending subtrees as (start, len) pairs, with the next one on top.
+    stack: Vec<(u64, u64)>,
+    // The bytes of the most recently read header, parent node, or chunk.
+    buf: [u8; CHUNK_LEN],
+    buf_len: usize,
+    buf_pos: usize,
+}
+
+impl<T: Read + Seek> SliceExtractor<T> {
+    /// Construct a new `SliceExtractor` from a combined encoding.
+    pub fn new(encoded: T, slice_start: u64, slice_len: u64) -> Self {
+        Self::new_inner(encoded, None, slice_start, slice_len)
+    }
+}
+
+impl<T: Read + Seek, O: Read + Seek> SliceExtractor<T, O> {
+    /// Construct a new `SliceExtractor` from an input and its outboard encoding. The resulting
+    /// slice is the same as it would be from the combined encoding.
+    pub fn new_outboard(input: T, outboard: O, slice_start: u64, slice_len: u64) -> Self {
+        Self::new_inner(input, Some(outboard), slice_start, slice_len)
+    }
+
+    fn new_inner(input: T, outboard: Option<O>, slice_start: u64, slice_len: u64) -> Self {
+        Self {
+            reader: EncodingReader::new(input, outboard),
+            slice_start,
+            slice_len,
+            content_len: None,
+            stack: Vec::new(),
+            buf: [0; CHUNK_LEN],
+            buf_len: 0,
+            buf_pos: 0,
+        }
+    }
+
+    /// Return the underlying reader (or, with an outboard encoding, the reader of input bytes).
+    pub fn into_inner(self) -> T {
+        self.reader.input
+    }
+
+    // Fill the buffer with the next part of the slice. Returns false at the end of the slice.
+    fn fill_buf(&mut self) -> io::Result<bool> {
+        let Some(content_len) = self.content_len else {
+            let len = self.reader.read_header()?;
+            self.content_len = Some(len);
+            self.stack.push((0, len));
+            self.buf[..HEADER_LEN].copy_from_slice(&len.to_le_bytes());
+            self.buf_len = HEADER_LEN;
+            self.buf_pos = 0;
+            return Ok(true);
+        };
+        let bounds = slice_bounds(self.slice_start, self.slice_len, content_len);
+        while let Some((start, len)) = self.stack.pop() {
+            let is_root = start == 0 && len == content_len;
+            if !in_slice(start, len, is_root, bounds) {
+                if start >= bounds.1 {
+                    self.stack.clear();
+                    return Ok(false);
+                }
+                self.reader.skip_subtree(len)?;
+                continue;
+            }
+            if len > CHUNK_LEN as u64 {
+                let parent = self.reader.read_parent()?;
+                let left_len = left_subtree_len(len);
+                self.stack.push((start + left_len, len - left_len));
+                self.stack.push((start, left_len));
+                self.buf[..PARENT_LEN].copy_from_slice(&parent);
+                self.buf_len = PARENT_LEN;
+            } else {
+                self.reader.read_chunk(&mut self.buf[..len as usize])?;
+                self.buf_len = len as usize;
+            }
+            self.buf_pos = 0;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+impl<T: Read + Seek, O: Read + Seek> Read for SliceExtractor<T, O> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // Note that the empty root chunk produces an empty buffer, so this is a loop.
+        while self.buf_pos == self.buf_len {
+            if !self.fill_buf()? {
+                return Ok(0);
+            }
+        }
+        let take = cmp::min(buf.len(), self.buf_len - self.buf_pos);
+        buf[..take].copy_from_slice(&self.buf[self.buf_pos..][..take]);
+        self.buf_pos += take;
+        Ok(take)
+    }
+}
+
+impl<T, O> std::fmt::Debug for SliceExtractor<T, O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("SliceExtractor")
+            .field("slice_start", &self.slice_start)
+            .field("slice_len", &self.slice_len)
+            .field("content_len", &self.content_len)
+            .finish()
+    }
+}
+
+/// A decoder for slices from [`SliceExtractor`], which implements [`Read`].
+///
+/// The `slice_start`, `slice_len`, and `mode` arguments must be the same as the ones used to
+/// extract the slice, and `hash` is the root hash of the whole input. Reads return only the
+/// content bytes within the requested range, each of them verified like in [`Decoder`]. Note that
+/// the content length in the header is only verified if the slice includes the final chunk.
+///
+/// See the [`SliceExtractor`] example.
+pub struct SliceDecoder<T> {
+    inner: Decoder<T>,
+}
+
+impl<T: Read> SliceDecoder<T> {
+    /// Construct a new `SliceDecoder`.
+    pub fn new(slice: T, hash: &Hash, slice_start: u64, slice_len: u64, mode: Mode) -> Self {
+        let mut inner = Decoder::new(slice, hash, mode);
+        inner.slice = Some((slice_start, slice_len));
+        inner.pos = slice_start;
+        Self { inner }
+    }
+
+    /// Return the underlying reader.
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+impl<T: Read> Read for SliceDecoder<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<T> std::fmt::Debug for SliceDecoder<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("SliceDecoder")
+            .field("slice", &self.inner.slice)
+            .field("content_len", &self.inner.content_len)
+            .field("position", &self.inner.pos)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hazmat::{hash_derive_key_context, ContextKey};
+    use crate::test::{paint_test_input, TEST_CASES, TEST_KEY};
+
+    fn test_modes(context_key: &ContextKey) -> [Mode<'_>; 3] {
+        [
+            Mode::Hash,
+            Mode::KeyedHash(&TEST_KEY),
+            Mode::DeriveKeyMaterial(context_key),
+        ]
+    }
+
+    fn expected_hash(input: &[u8], mode: Mode) -> Hash {
+        match mode {
+            Mode::Hash => crate::hash(input),
+            Mode::KeyedHash(key) => crate::keyed_hash(key, input),
+            Mode::DeriveKeyMaterial(_) => crate::derive_key("test context", input).into(),
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let context_key = hash_derive_key_context("test context");
+        let mut input_buf = [0; crate::test::TEST_CASES_MAX];
+        paint_test_input(&mut input_buf);
+        for &case in TEST_CASES {
+            let input = &input_buf[..case];
+            for mode in test_modes(&context_key) {
+                let (encoded, hash) = encode(input, mode);
+                assert_eq!(hash, expected_hash(input, mode));
+                assert_eq!(encoded.len() as u128, encoded_len(case as u64));
+                let mut decoded = Vec::new();
+                Decoder::new(&encoded[..], &hash, mode)
+                    .read_to_end(&mut decoded)
+                    .unwrap();
+                assert_eq!(input, &decoded[..]);
+
+                let (outboard_encoded, outboard_hash) = outboard(input, mode);
+                assert_eq!(outboard_hash, hash);
+                assert_eq!(outboard_encoded.len() as u128, outboard_len(case as u64));
+                let mut decoded = Vec::new();
+                Decoder::new_outboard(input, &outboard_encoded[..], &hash, mode)
+                    .read_to_end(&mut decoded)
+                    .unwrap();
+                assert_eq!(input, &decoded[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_corrupt_encoding() {
+        let mut input = [0; 3 * CHUNK_LEN + 1];
+        paint_test_input(&mut input);
+        let (encoded, hash) = encode(&input, Mode::Hash);
+        // Flip a bit in the header, in each parent node, and in each chunk.
+        let mut offsets = vec![0];
+        offsets.extend((0..3).map(|i| HEADER_LEN + i * PARENT_LEN));
+        let chunks_start = HEADER_LEN + 3 * PARENT_LEN;
+        offsets.extend((0..4).map(|i| chunks_start + i * CHUNK_LEN));
+        for offset in offsets {
+            let mut corrupt = encoded.clone();
+            corrupt[offset] ^= 1;
+            let err = Decoder::new(&corrupt[..], &hash, Mode::Hash)
+                .read_to_end(&mut Vec::new())
+                .unwrap_err();
+            // A corrupt header usually leads to a hash mismatch, but it might also make the
+            // decoder run out of input.
+            assert!(
+                matches!(
+                    err.kind(),
+                    io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof
+                ),
+                "offset {offset}: {err}",
+            );
+        }
+
+        // A decoder that hits a bad chunk returns every chunk before it, and nothing after.
+        let bad_chunk_offset = chunks_start + 2 * CHUNK_LEN;
+        let mut corrupt = encoded.clone();
+        corrupt[bad_chunk_offset] ^= 1;
+        let mut decoder = Decoder::new(&corrupt[..], &hash, Mode::Hash);
+        let mut decoded = Vec::new();
+        let err = decoder.read_to_end(&mut decoded).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(&input[..2 * CHUNK_LEN], &decoded[..]);
+        assert!(decoder.read(&mut [0; 1]).is_err());
+    }
+
+    #[test]
+    fn test_wrong_mode_or_hash() {
+        let input = [42; 2 * CHUNK_LEN];
+        let (encoded, hash) = encode(&input, Mode::Hash);
+        let err = Decoder::new(&encoded[..], &hash, Mode::KeyedHash(&TEST_KEY))
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = Decoder::new(&encoded[..], &crate::hash(b"foo"), Mode::Hash)
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    fn slice_ranges(content_len: u64) -> Vec<(u64, u64)> {
+        let mut ranges = Vec::new();
+        for start in [
+            0,
+            1,
+            CHUNK_LEN as u64 - 1,
+            CHUNK_LEN as u64,
+            3 * CHUNK_LEN as u64 + 7,
+        ] {
+            for len in [0, 1, CHUNK_LEN as u64, 2 * CHUNK_LEN as u64 + 1, u64::MAX] {
+                ranges.push((start, len));
+            }
+        }
+        for start in [content_len.saturating_sub(1), content_len, content_len + 1] {
+            ranges.push((start, 0));
+            ranges.push((start, 1));
+        }
+        ranges
+    }
+
+    fn extract_slice(encoded: &[u8], slice_start: u64, slice_len: u64) -> Vec<u8> {
+        let mut slice = Vec::new();
+        SliceExtractor::new(io::Cursor::new(encoded), slice_start, slice_len)
+            .read_to_end(&mut slice)
+            .unwrap();
+        slice
+    }
+
+    #[test]
+    fn test_slices() {
+        let context_key = hash_derive_key_context("test context");
+        let mut input_buf = [0; crate::test::TEST_CASES_MAX];
+        paint_test_input(&mut input_buf);
+        for &case in TEST_CASES {
+            let input = &input_buf[..case];
+            for mode in test_modes(&context_key) {
+                let (encoded, hash) = encode(input, mode);
+                let (outboard_encoded, _) = outboard(input, mode);
+                for (slice_start, slice_len) in slice_ranges(case as u64) {
+                    let slice = extract_slice(&encoded, slice_start, slice_len);
+                    let mut outboard_slice = Vec::new();
+                    SliceExtractor::new_outboard(
+                        io::Cursor::new(input),
+                        io::Cursor::new(&outboard_encoded),
+                        slice_start,
+                        slice_len,
+                    )
+                    .read_to_end(&mut outboard_slice)
+                    .unwrap();
+                    assert_eq!(slice, outboard_slice);
+
+                    let mut decoded = Vec::new();
+                    SliceDecoder::new(&slice[..], &hash, slice_start, slice_len, mode)
+                        .read_to_end(&mut decoded)
+                        .unwrap();
+                    let start = cmp::min(slice_start, case as u64) as usize;
+                    let end = cmp::min(slice_start.saturating_add(slice_len), case as u64);
+                    assert_eq!(&input[start..end as usize], &decoded[..]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_slice_size() {
+        let input = vec![0; 1 << 20];
+        let (encoded, hash) = encode(&input, Mode::Hash);
+        // One chunk in the middle needs the header, one parent node per level, and the chunk.
+        let slice = extract_slice(&encoded, 500_000, 1);
+        assert_eq!(slice.len(), HEADER_LEN + 10 * PARENT_LEN + CHUNK_LEN);
+        // An empty slice at the end still includes the final chunk, which proves the length.
+        let slice = extract_slice(&encoded, input.len() as u64, 0);
+        let mut decoder = SliceDecoder::new(&slice[..], &hash, input.len() as u64, 0, Mode::Hash);
+        assert_eq!(decoder.read(&mut [0; 1]).unwrap(), 0);
+        // But claiming a different length doesn't verify.
+        let mut bad_slice = slice.clone();
+        bad_slice[..HEADER_LEN].copy_from_slice(&(input.len() as u64 + 1).to_le_bytes());
+        let mut decoder =
+            SliceDecoder::new(&bad_slice[..], &hash, input.len() as u64, 0, Mode::Hash);
+        assert!(decoder.read(&mut [0; 1]).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_slice() {
+        let mut input = [0; 5 * CHUNK_LEN];
+        paint_test_input(&mut input);
+        let (encoded, hash) = encode(&input, Mode::Hash);
+        let (slice_start, slice_len) = (2 * CHUNK_LEN as u64 + 10, CHUNK_LEN as u64);
+        let slice = extract_slice(&encoded, slice_start, slice_len);
+        // This slice doesn't include the final chunk, so the header isn't verified, and a corrupt
+        // header might still decode to the right bytes. Every other byte matters.
+        for offset in 0..slice.len() {
+            let mut corrupt = slice.clone();
+            corrupt[offset] ^= 1;
+            let mut decoded = Vec::new();
+            let result = SliceDecoder::new(&corrupt[..], &hash, slice_start, slice_len, Mode::Hash)
+                .read_to_end(&mut decoded);
+            if offset >= HEADER_LEN {
+                assert!(result.is_err(), "offset {offset}");
+            }
+            // Anything returned before the error is correct.
+            assert_eq!(
+                &input[slice_start as usize..][..decoded.len()],
+                &decoded[..]
+            );
+        }
+    }
+
+    #[test]
+    fn test_seek() {
+        let mut input_buf = [0; crate::test::TEST_CASES_MAX];
+        paint_test_input(&mut input_buf);
+        for &case in TEST_CASES {
+            let input = &input_buf[..case];
+            let (encoded, hash) = encode(input, Mode::Hash);
+            let (outboard_encoded, _) = outboard(input, Mode::Hash);
+            let mut decoder = Decoder::new(io::Cursor::new(&encoded), &hash, Mode::Hash);
+            let mut outboard_decoder = Decoder::new_outboard(
+                io::Cursor::new(input),
+                io::Cursor::new(&outboard_encoded),
+                &hash,
+                Mode::Hash,
+            );
+            // Seek forwards and backwards, including past the end.
+            let mut targets: Vec<u64> = TEST_CASES.iter().map(|&x| x as u64).collect();
+            targets.extend(TEST_CASES.iter().rev().map(|&x| x as u64 / 2));
+            for target in targets {
+                let expected_pos = cmp::min(target as usize, case);
+                for decoder in [&mut decoder as &mut dyn ReadSeek, &mut outboard_decoder] {
+                    assert_eq!(decoder.seek(io::SeekFrom::Start(target)).unwrap(), target);
+                    let mut buf = [0; 100];
+                    let n = read_up_to(decoder, &mut buf);
+                    assert_eq!(&input[expected_pos..][..n], &buf[..n]);
+                    assert_eq!(n, cmp::min(100, case - expected_pos));
+                }
+            }
+            let end = decoder.seek(io::SeekFrom::End(0)).unwrap();
+            assert_eq!(end, case as u64);
+            if case > 0 {
+                let pos = decoder.seek(io::SeekFrom::Current(-1)).unwrap();
+                assert_eq!(pos, case as u64 - 1);
+                let mut buf = [0; 10];
+                assert_eq!(decoder.read(&mut buf).unwrap(), 1);
+                assert_eq!(buf[0], input[case - 1]);
+            }
+            assert!(decoder
+                .seek(io::SeekFrom::Current(-(case as i64) - 1))
+                .is_err());
+        }
+    }
+
+    trait ReadSeek: Read + Seek {}
+    impl<T: Read + Seek> ReadSeek for T {}
+
+    fn read_up_to(reader: &mut dyn ReadSeek, buf: &mut [u8]) -> usize {
+        let mut n = 0;
+        while n < buf.len() {
+            match reader.read(&mut buf[n..]).unwrap() {
+                0 => break,
+                x => n += x,
+            }
+        }
+        n
+    }
+
+    #[test]
+    fn test_seek_corrupt() {
+        let mut input = [0; 4 * CHUNK_LEN];
+        paint_test_input(&mut input);
+        let (mut encoded, hash) = encode(&input, Mode::Hash);
+        // Corrupt the first chunk. Seeking past it should never touch it.
+        encoded[HEADER_LEN + 3 * PARENT_LEN] ^= 1;
+        let mut decoder = Decoder::new(io::Cursor::new(&encoded), &hash, Mode::Hash);
+        decoder.seek(io::SeekFrom::Start(CHUNK_LEN as u64)).unwrap();
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(&input[CHUNK_LEN..], &decoded[..]);
+        // Seeking back to it fails.
+        decoder.seek(io::SeekFrom::Start(0)).unwrap();
+        let err = decoder.read(&mut [0; 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_truncated_encoding() {
+        let input = [42; 2 * CHUNK_LEN];
+        let (encoded, hash) = encode(&input, Mode::Hash);
+        for len in [0, HEADER_LEN - 1, HEADER_LEN, encoded.len() - 1] {
+            let err = Decoder::new(&encoded[..len], &hash, Mode::Hash)
+                .read_to_end(&mut Vec::new())
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        }
+    }
+}